@@ -1,4 +1,5 @@
 import { NextRequest, NextResponse } from 'next/server'
+import { signatureHeaders } from '../signature'
 
 const API_URL = process.env.MATCHING_ENGINE_URL || 'http://localhost:3001'
 
@@ -31,6 +32,7 @@ export async function DELETE(
   try {
     const response = await fetch(`${API_URL}/api/orders/${params.id}`, {
       method: 'DELETE',
+      headers: signatureHeaders(request),
     })
     const data = await response.json()
 
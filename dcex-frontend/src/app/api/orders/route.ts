@@ -1,17 +1,20 @@
 import { NextRequest, NextResponse } from 'next/server'
+import { signatureHeaders } from './signature'
 
 const API_URL = process.env.MATCHING_ENGINE_URL || 'http://localhost:3001'
 
 export async function POST(request: NextRequest) {
   try {
-    const body = await request.json()
+    // Forwarded byte for byte: the engine checks the signature over the body.
+    const body = await request.text()
     
     const response = await fetch(`${API_URL}/api/orders`, {
       method: 'POST',
       headers: {
         'Content-Type': 'application/json',
+        ...signatureHeaders(request),
       },
-      body: JSON.stringify(body),
+      body,
     })
 
     const data = await response.json()
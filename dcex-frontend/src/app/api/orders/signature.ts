@@ -0,0 +1,13 @@
+import { NextRequest } from 'next/server'
+
+const SIGNATURE_HEADERS = ['x-dcex-wallet', 'x-dcex-delegate', 'x-dcex-key', 'x-dcex-timestamp', 'x-dcex-signature']
+
+/** The request-signing headers to pass through to the engine, which rejects unsigned trading requests. */
+export function signatureHeaders(request: NextRequest): Record<string, string> {
+  const headers: Record<string, string> = {}
+  for (const name of SIGNATURE_HEADERS) {
+    const value = request.headers.get(name)
+    if (value) headers[name] = value
+  }
+  return headers
+}
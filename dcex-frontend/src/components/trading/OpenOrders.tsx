@@ -13,7 +13,7 @@ import { createCancelOrderTransaction } from '@/lib/solana/orders'
 
 export const OpenOrders: FC = () => {
   const { connection } = useConnection()
-  const { publicKey, sendTransaction } = useWallet()
+  const { publicKey, sendTransaction, signMessage } = useWallet()
   const openOrders = useTradingStore((state) => state.openOrders)
   const setOpenOrders = useTradingStore((state) => state.setOpenOrders)
   const selectedMarket = useTradingStore((state) => state.selectedMarket)
@@ -24,7 +24,7 @@ export const OpenOrders: FC = () => {
   )
 
   const handleCancel = async (orderId: string) => {
-    if (!publicKey || !selectedMarket) return
+    if (!publicKey || !signMessage || !selectedMarket) return
 
     setCancellingId(orderId)
     try {
@@ -45,7 +45,7 @@ export const OpenOrders: FC = () => {
       await connection.confirmTransaction(signature, 'confirmed')
 
       // 3. Call matching engine to remove from orderbook
-      await api.cancelOrder(orderId)
+      await api.cancelOrder(orderId, { wallet: publicKey.toBase58(), signMessage })
       setOpenOrders(openOrders.filter((o) => o.order_id !== orderId))
     } catch (err) {
       console.error('Failed to cancel order:', err)
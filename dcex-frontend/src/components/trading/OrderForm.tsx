@@ -18,7 +18,7 @@ interface OrderFormProps {
 
 export const OrderForm: FC<OrderFormProps> = ({ initialPrice }) => {
   const { connection } = useConnection()
-  const { publicKey, sendTransaction, connected } = useWallet()
+  const { publicKey, sendTransaction, signMessage, connected } = useWallet()
   const selectedMarket = useTradingStore((state) => state.selectedMarket)
 
   const [side, setSide] = useState<OrderSide>('buy')
@@ -41,6 +41,11 @@ export const OrderForm: FC<OrderFormProps> = ({ initialPrice }) => {
       return
     }
 
+    if (!signMessage) {
+      setError('Your wallet does not support message signing')
+      return
+    }
+
     if (!price || !size) {
       setError('Please enter price and size')
       return
@@ -107,7 +112,7 @@ export const OrderForm: FC<OrderFormProps> = ({ initialPrice }) => {
         wallet: publicKey.toBase58(),
         signature,
         order_id: orderId.toString() // Pass the generated order ID
-      })
+      }, { wallet: publicKey.toBase58(), signMessage })
 
       setPrice('')
       setSize('')
@@ -118,7 +123,7 @@ export const OrderForm: FC<OrderFormProps> = ({ initialPrice }) => {
     } finally {
       setIsSubmitting(false)
     }
-  }, [connected, publicKey, selectedMarket, price, size, side, connection, sendTransaction])
+  }, [connected, publicKey, selectedMarket, price, size, side, connection, sendTransaction, signMessage])
 
   return (
     <div className="bg-card rounded-2xl border border-white/5 p-5">
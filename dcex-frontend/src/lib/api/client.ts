@@ -1,18 +1,59 @@
-import type { Market, Order, OrderbookSnapshot, Trade, PlaceOrderRequest } from '@/types/trading'
+import { utils } from '@coral-xyz/anchor'
+import type { ApiKey, Candle, CreateApiKeyRequest, CreatedApiKey, CandleInterval, Delegate, Fill, Market, Order, OrderSide, OrderStatus, OrderbookSnapshot, Trade, PageParams, PlaceOrderRequest, Referral, ReferralSummary, Ticker, WalletFees } from '@/types/trading'
 
 const API_BASE = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:3001'
 
-async function fetchApi<T>(endpoint: string, options?: RequestInit): Promise<T> {
+/** Error body returned by the matching engine; `code` is stable, `error` is for display. */
+export class ApiError extends Error {
+  constructor(
+    message: string,
+    public status: number,
+    public code?: string,
+    public details?: Record<string, unknown>,
+    public requestId?: string,
+  ) {
+    super(message)
+    this.name = 'ApiError'
+  }
+}
+
+/** The connected wallet, which signs trading requests with its own key. */
+export interface RequestSigner {
+  wallet: string
+  signMessage: (message: Uint8Array) => Promise<Uint8Array>
+}
+
+/** Signs `timestamp + method + path + body`, as the engine requires on trading routes. */
+async function signedHeaders(signer: RequestSigner, method: string, path: string, body = ''): Promise<Record<string, string>> {
+  const timestamp = Date.now().toString()
+  const message = new TextEncoder().encode(`${timestamp}${method}${path}${body}`)
+  const signature = await signer.signMessage(message)
+  return {
+    'x-dcex-wallet': signer.wallet,
+    'x-dcex-timestamp': timestamp,
+    'x-dcex-signature': utils.bytes.bs58.encode(signature),
+  }
+}
+
+async function fetchApi<T>(endpoint: string, options: RequestInit = {}): Promise<T> {
+  const { headers, ...rest } = options
   const res = await fetch(`${API_BASE}${endpoint}`, {
     headers: {
       'Content-Type': 'application/json',
+      ...headers,
     },
-    ...options,
+    ...rest,
   })
 
   if (!res.ok) {
     const error = await res.json().catch(() => ({ error: 'Unknown error' }))
-    throw new Error(error.error || `API error: ${res.status}`)
+    throw new ApiError(
+      error.error || `API error: ${res.status}`,
+      res.status,
+      error.code,
+      error.details,
+      error.request_id ?? res.headers.get('x-request-id') ?? undefined,
+    )
   }
 
   return res.json()
@@ -29,22 +70,92 @@ export const api = {
   getTrades: (marketId: string, limit = 50) =>
     fetchApi<Trade[]>(`/api/markets/${marketId}/trades?limit=${limit}`),
 
-  placeOrder: (order: PlaceOrderRequest) =>
-    fetchApi<{ order: Order; trades: Array<{ maker_order_id: string; price: number; size: number }> }>(
+  getTickers: () => fetchApi<Ticker[]>('/api/tickers'),
+
+  getTicker: (marketId: string) => fetchApi<Ticker>(`/api/markets/${marketId}/ticker`),
+
+  getCandles: (marketId: string, interval: CandleInterval, from?: string, to?: string) => {
+    const params = new URLSearchParams({ interval })
+    if (from) params.set('from', from)
+    if (to) params.set('to', to)
+    return fetchApi<Candle[]>(`/api/markets/${marketId}/candles?${params}`)
+  },
+
+  placeOrder: async (order: PlaceOrderRequest, signer: RequestSigner) => {
+    const body = JSON.stringify(order)
+    return fetchApi<{ order: Order; trades: Array<{ maker_order_id: string; price: number; size: number }> }>(
       '/api/orders',
       {
         method: 'POST',
-        body: JSON.stringify(order),
+        headers: await signedHeaders(signer, 'POST', '/api/orders', body),
+        body,
       }
-    ),
+    )
+  },
 
-  cancelOrder: (orderId: string) =>
-    fetchApi<Order>(`/api/orders/${orderId}`, { method: 'DELETE' }),
+  cancelOrder: async (orderId: string, signer: RequestSigner) => {
+    const path = `/api/orders/${orderId}`
+    return fetchApi<Order>(path, {
+      method: 'DELETE',
+      headers: await signedHeaders(signer, 'DELETE', path),
+    })
+  },
 
   getOrder: (orderId: string) => fetchApi<Order>(`/api/orders/${orderId}`),
 
-  getUserOrders: (wallet: string, marketId?: string) => {
-    const params = marketId ? `?market_id=${marketId}` : ''
-    return fetchApi<Order[]>(`/api/users/${wallet}/orders${params}`)
-  },
+  getUserOrders: (
+    wallet: string,
+    marketId?: string,
+    page: PageParams & { status?: OrderStatus; side?: OrderSide } = {},
+  ) => fetchApi<Order[]>(`/api/users/${wallet}/orders${queryString({ market_id: marketId, ...page })}`),
+
+  getUserTrades: (wallet: string, marketId?: string, page: PageParams = {}) =>
+    fetchApi<Trade[]>(`/api/users/${wallet}/trades${queryString({ market_id: marketId, ...page })}`),
+
+  getUserFills: (wallet: string, marketId?: string, page: PageParams = {}) =>
+    fetchApi<Fill[]>(`/api/users/${wallet}/fills${queryString({ market_id: marketId, ...page })}`),
+
+  getUserFees: (wallet: string, marketId: string) =>
+    fetchApi<WalletFees>(`/api/users/${wallet}/fees${queryString({ market_id: marketId })}`),
+
+  getUserReferrals: (wallet: string) =>
+    fetchApi<ReferralSummary>(`/api/users/${wallet}/referrals`),
+
+  /** `signature` signs "DCEX referral\nwallet: <wallet>\nreferrer: <referrer>". */
+  registerReferral: (wallet: string, referrer: string, signature: string) =>
+    fetchApi<Referral>('/api/referrals', {
+      method: 'POST',
+      body: JSON.stringify({ wallet, referrer, signature }),
+    }),
+
+  /** `signature` signs the "DCEX API key" registration message. */
+  createApiKey: (req: CreateApiKeyRequest) =>
+    fetchApi<CreatedApiKey>('/api/api-keys', {
+      method: 'POST',
+      body: JSON.stringify(req),
+    }),
+
+  getApiKeys: (wallet: string) => fetchApi<ApiKey[]>(`/api/users/${wallet}/api-keys`),
+
+  /** `signature` signs "DCEX revoke API key\nwallet: <wallet>\nkey: <keyId>\ntimestamp: <timestamp>". */
+  revokeApiKey: (keyId: string, wallet: string, timestamp: number, signature: string) =>
+    fetchApi<ApiKey>(`/api/api-keys/${keyId}`, {
+      method: 'DELETE',
+      body: JSON.stringify({ wallet, timestamp, signature }),
+    }),
+
+  getDelegate: (marketId: string, wallet: string) =>
+    fetchApi<Delegate | null>(`/api/markets/${marketId}/delegates/${wallet}`),
+
+  userFillsCsvUrl: (wallet: string, marketId?: string, page: PageParams = {}) =>
+    `${API_BASE}/api/users/${wallet}/fills${queryString({ market_id: marketId, ...page, format: 'csv' })}`,
+}
+
+function queryString(params: Record<string, string | number | undefined>): string {
+  const search = new URLSearchParams()
+  for (const [key, value] of Object.entries(params)) {
+    if (value !== undefined) search.set(key, String(value))
+  }
+  const query = search.toString()
+  return query ? `?${query}` : ''
 }
@@ -1,11 +1,22 @@
-import type { OrderbookSnapshot, Trade, Order } from '@/types/trading'
+import type {
+  BalanceChange,
+  Fill,
+  OrderbookDelta,
+  OrderbookSnapshot,
+  PrivateChannel,
+  Trade,
+  TradeSettlement,
+  Order,
+} from '@/types/trading'
 import { useTradingStore } from '@/lib/stores/trading'
 
 type MessageHandler = {
   onOrderbookSnapshot?: (snapshot: OrderbookSnapshot) => void
-  onOrderbookUpdate?: (data: { market_id: string; bids: OrderbookSnapshot['bids']; asks: OrderbookSnapshot['asks'] }) => void
+  onOrderbookUpdate?: (delta: OrderbookDelta) => void
   onTrade?: (trade: Trade) => void
   onOrderUpdate?: (order: Order) => void
+  onFill?: (fill: Fill) => void
+  onBalanceUpdate?: (change: BalanceChange) => void
   onError?: (message: string) => void
 }
 
@@ -16,6 +27,8 @@ class WebSocketClient {
   private maxReconnectAttempts = 5
   private handlers: MessageHandler = {}
   private subscribedMarkets: Set<string> = new Set()
+  private nextRequestId = 0
+  private login: { wallet: string; sign: (message: string) => Promise<string> } | null = null
 
   constructor() {
     this.url = process.env.NEXT_PUBLIC_WS_URL || 'ws://localhost:3001/ws'
@@ -35,6 +48,9 @@ class WebSocketClient {
         this.subscribedMarkets.forEach((marketId) => {
           this.subscribe(marketId)
         })
+        if (this.login) {
+          this.send('auth_challenge', { wallet: this.login.wallet })
+        }
       }
 
       this.ws.onmessage = (event) => {
@@ -69,15 +85,17 @@ class WebSocketClient {
         useTradingStore.getState().setOrderbook(message.data as OrderbookSnapshot)
         break
 
-      case 'orderbook_update':
+      case 'orderbook_update': {
+        const delta = message.data as OrderbookDelta
         if (this.handlers.onOrderbookUpdate) {
-          this.handlers.onOrderbookUpdate(
-            message.data as { market_id: string; bids: OrderbookSnapshot['bids']; asks: OrderbookSnapshot['asks'] }
-          )
+          this.handlers.onOrderbookUpdate(delta)
+        }
+        if (!useTradingStore.getState().applyOrderbookDelta(delta)) {
+          // Missed a delta: resubscribing sends a fresh snapshot.
+          this.send('subscribe', { channel: 'book.L2', market_id: delta.market_id })
         }
-        const updateData = message.data as { bids: OrderbookSnapshot['bids']; asks: OrderbookSnapshot['asks'] }
-        useTradingStore.getState().updateOrderbook(updateData.bids, updateData.asks)
         break
+      }
 
       case 'trade':
         if (this.handlers.onTrade) {
@@ -86,6 +104,10 @@ class WebSocketClient {
         useTradingStore.getState().addTrade(message.data as Trade)
         break
 
+      case 'trade_settlement':
+        useTradingStore.getState().updateTradeSettlement(message.data as TradeSettlement)
+        break
+
       case 'order_update':
         if (this.handlers.onOrderUpdate) {
           this.handlers.onOrderUpdate(message.data as Order)
@@ -93,14 +115,61 @@ class WebSocketClient {
         useTradingStore.getState().updateOrder(message.data as Order)
         break
 
+      case 'fill':
+        if (this.handlers.onFill) {
+          this.handlers.onFill(message.data as Fill)
+        }
+        break
+
+      case 'balance_update':
+        if (this.handlers.onBalanceUpdate) {
+          this.handlers.onBalanceUpdate(message.data as BalanceChange)
+        }
+        break
+
+      case 'challenge':
+        this.answerChallenge((message.data as { message: string }).message)
+        break
+
+      case 'session': {
+        const channels: PrivateChannel[] = ['orders', 'fills', 'balances']
+        channels.forEach((channel) => this.send('subscribe', { channel }))
+        break
+      }
+
       case 'error':
         if (this.handlers.onError) {
-          this.handlers.onError((message.data as { message: string }).message)
+          this.handlers.onError((message.data as { id: string | null; message: string }).message)
         }
         break
     }
   }
 
+  private async answerChallenge(challenge: string) {
+    if (!this.login) return
+    try {
+      const signature = await this.login.sign(challenge)
+      this.send('login', { wallet: this.login.wallet, signature })
+    } catch (error) {
+      console.error('Failed to sign WebSocket login:', error)
+    }
+  }
+
+  private send(type: string, data?: unknown) {
+    if (this.ws?.readyState === WebSocket.OPEN) {
+      this.ws.send(JSON.stringify({ id: String(++this.nextRequestId), type, data }))
+    }
+  }
+
+  /**
+   * Logs in with the wallet to receive its private order, fill and balance
+   * channels. `sign` returns the base58 signature of the challenge text.
+   */
+  authenticate(wallet: string, sign: (message: string) => Promise<string>) {
+    this.login = { wallet, sign }
+    this.send('auth_challenge', { wallet })
+  }
+
   private attemptReconnect() {
     if (this.reconnectAttempts < this.maxReconnectAttempts) {
       this.reconnectAttempts++
@@ -112,16 +181,14 @@ class WebSocketClient {
 
   subscribe(marketId: string) {
     this.subscribedMarkets.add(marketId)
-    if (this.ws?.readyState === WebSocket.OPEN) {
-      this.ws.send(JSON.stringify({ type: 'subscribe', data: { market_id: marketId } }))
-    }
+    this.send('subscribe', { channel: 'book.L2', market_id: marketId })
+    this.send('subscribe', { channel: 'trades', market_id: marketId })
   }
 
   unsubscribe(marketId: string) {
     this.subscribedMarkets.delete(marketId)
-    if (this.ws?.readyState === WebSocket.OPEN) {
-      this.ws.send(JSON.stringify({ type: 'unsubscribe', data: { market_id: marketId } }))
-    }
+    this.send('unsubscribe', { channel: 'book.L2', market_id: marketId })
+    this.send('unsubscribe', { channel: 'trades', market_id: marketId })
   }
 
   disconnect() {
@@ -130,6 +197,7 @@ class WebSocketClient {
       this.ws = null
     }
     this.subscribedMarkets.clear()
+    this.login = null
   }
 }
 
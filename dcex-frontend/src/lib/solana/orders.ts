@@ -10,7 +10,8 @@ export async function createPlaceOrderTransaction(
   orderId: BN, // u128
   side: 'buy' | 'sell',
   price: BN,
-  size: BN
+  size: BN,
+  expiresAt: BN = new BN(0) // Unix seconds; 0 = good-till-cancel
 ): Promise<Transaction> {
   const client = new DcexClient(connection)
   const [marketPDA] = getMarketPDA(baseMint, quoteMint)
@@ -25,7 +26,8 @@ export async function createPlaceOrderTransaction(
     orderId,
     side,
     price,
-    size
+    size,
+    expiresAt
   )
   
   const transaction = new Transaction()
@@ -10,6 +10,15 @@ export const MARKET_SEED = Buffer.from('market')
 export const VAULT_SEED = Buffer.from('vault')
 export const ORDER_SEED = Buffer.from('order')
 export const ESCROW_SEED = Buffer.from('escrow')
+export const DELEGATE_SEED = Buffer.from('delegate')
+
+/** Anchor instruction discriminators: the first 8 bytes of sha256("global:<name>"). */
+export const INSTRUCTION_DISCRIMINATORS = {
+  deposit: Buffer.from([242, 35, 198, 137, 82, 225, 242, 182]),
+  withdraw: Buffer.from([183, 18, 70, 156, 148, 109, 161, 34]),
+  placeOrder: Buffer.from([51, 194, 155, 175, 109, 130, 96, 106]),
+  cancelOrder: Buffer.from([95, 129, 237, 240, 8, 49, 223, 132]),
+}
 
 export function getMarketPDA(baseMint: PublicKey, quoteMint: PublicKey): [PublicKey, number] {
   return PublicKey.findProgramAddressSync(
@@ -32,6 +41,13 @@ export function getOrderPDA(orderId: BN): [PublicKey, number] {
   )
 }
 
+export function getDelegatePDA(userVault: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync(
+    [DELEGATE_SEED, userVault.toBuffer()],
+    PROGRAM_ID
+  )
+}
+
 export function getEscrowPDA(market: PublicKey, tokenType: 'base' | 'quote'): [PublicKey, number] {
   return PublicKey.findProgramAddressSync(
     [ESCROW_SEED, market.toBuffer(), Buffer.from(tokenType)],
@@ -102,7 +118,7 @@ export class DcexClient {
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       data: Buffer.concat([
-        Buffer.from([1]),
+        INSTRUCTION_DISCRIMINATORS.deposit,
         amount.toArrayLike(Buffer, 'le', 8),
         Buffer.from([isBase ? 1 : 0]),
       ]),
@@ -130,7 +146,7 @@ export class DcexClient {
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
       ],
       data: Buffer.concat([
-        Buffer.from([2]),
+        INSTRUCTION_DISCRIMINATORS.withdraw,
         amount.toArrayLike(Buffer, 'le', 8),
         Buffer.from([isBase ? 1 : 0]),
       ]),
@@ -145,7 +161,8 @@ export class DcexClient {
     orderId: BN,
     side: 'buy' | 'sell',
     price: BN,
-    size: BN
+    size: BN,
+    expiresAt: BN = new BN(0) // Unix seconds; 0 = good-till-cancel
   ) {
     return {
       programId: this.programId,
@@ -157,11 +174,12 @@ export class DcexClient {
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       data: Buffer.concat([
-        Buffer.from([3]), // Instruction index for PlaceOrder
+        INSTRUCTION_DISCRIMINATORS.placeOrder,
         orderId.toArrayLike(Buffer, 'le', 16),
         Buffer.from([side === 'buy' ? 0 : 1]),
         price.toArrayLike(Buffer, 'le', 8),
         size.toArrayLike(Buffer, 'le', 8),
+        expiresAt.toTwos(64).toArrayLike(Buffer, 'le', 8),
       ]),
     }
   }
@@ -180,7 +198,7 @@ export class DcexClient {
         { pubkey: userVault, isSigner: false, isWritable: true },
         { pubkey: order, isSigner: false, isWritable: true },
       ],
-      data: Buffer.from([4]), // Instruction index for CancelOrder
+      data: INSTRUCTION_DISCRIMINATORS.cancelOrder,
     }
   }
 }
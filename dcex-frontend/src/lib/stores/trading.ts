@@ -1,5 +1,5 @@
 import { create } from 'zustand'
-import type { Market, Order, OrderbookSnapshot, Trade } from '@/types/trading'
+import type { Market, Order, OrderbookDelta, OrderbookLevel, OrderbookSnapshot, Trade, TradeSettlement } from '@/types/trading'
 
 interface TradingState {
   selectedMarket: Market | null
@@ -10,8 +10,10 @@ interface TradingState {
 
   setSelectedMarket: (market: Market | null) => void
   setOrderbook: (orderbook: OrderbookSnapshot | null) => void
-  updateOrderbook: (bids: OrderbookSnapshot['bids'], asks: OrderbookSnapshot['asks']) => void
+  /** Returns false when the delta does not follow the local book (a gap). */
+  applyOrderbookDelta: (delta: OrderbookDelta) => boolean
   addTrade: (trade: Trade) => void
+  updateTradeSettlement: (settlement: TradeSettlement) => void
   setTrades: (trades: Trade[]) => void
   setOpenOrders: (orders: Order[]) => void
   updateOrder: (order: Order) => void
@@ -29,18 +31,23 @@ export const useTradingStore = create<TradingState>((set, get) => ({
 
   setOrderbook: (orderbook) => set({ orderbook }),
 
-  updateOrderbook: (bids, asks) => {
+  applyOrderbookDelta: (delta) => {
     const current = get().orderbook
-    if (current) {
-      set({
-        orderbook: {
-          ...current,
-          bids,
-          asks,
-          timestamp: new Date().toISOString(),
-        },
-      })
-    }
+    if (!current || current.market_id !== delta.market_id) return true
+    if (delta.sequence <= current.sequence) return true
+    if (delta.prev_sequence !== current.sequence) return false
+
+    set({
+      orderbook: {
+        ...current,
+        sequence: delta.sequence,
+        bids: mergeLevels(current.bids, delta.bids, (a, b) => b - a),
+        asks: mergeLevels(current.asks, delta.asks, (a, b) => a - b),
+        last_price: delta.last_price,
+        timestamp: delta.timestamp,
+      },
+    })
+    return true
   },
 
   addTrade: (trade) => {
@@ -49,6 +56,16 @@ export const useTradingStore = create<TradingState>((set, get) => ({
     }))
   },
 
+  updateTradeSettlement: (settlement) => {
+    set((state) => ({
+      trades: state.trades.map((t) =>
+        t.id === settlement.trade_id
+          ? { ...t, settlement_status: settlement.status, settlement_signature: settlement.signature }
+          : t
+      ),
+    }))
+  },
+
   setTrades: (trades) => set({ trades }),
 
   setOpenOrders: (orders) => set({ openOrders: orders }),
@@ -63,3 +80,19 @@ export const useTradingStore = create<TradingState>((set, get) => ({
 
   setConnected: (connected) => set({ isConnected: connected }),
 }))
+
+function mergeLevels(
+  levels: OrderbookLevel[],
+  changes: OrderbookLevel[],
+  compare: (a: number, b: number) => number
+): OrderbookLevel[] {
+  const byPrice = new Map(levels.map((level) => [level.price, level]))
+  for (const change of changes) {
+    if (change.size === 0) {
+      byPrice.delete(change.price)
+    } else {
+      byPrice.set(change.price, change)
+    }
+  }
+  return Array.from(byPrice.values()).sort((a, b) => compare(a.price, b.price))
+}
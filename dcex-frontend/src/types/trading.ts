@@ -1,5 +1,8 @@
 export type OrderSide = 'buy' | 'sell'
-export type OrderStatus = 'pending' | 'partiallyfilled' | 'filled' | 'cancelled'
+export type OrderStatus = 'pending' | 'partiallyfilled' | 'filled' | 'cancelled' | 'expired'
+export type MatchingAlgorithm = 'fifo' | 'prorata' | 'hybrid'
+export type SettlementStatus = 'pending' | 'settled' | 'failed'
+export type OrderType = 'limit' | 'stopmarket' | 'stoplimit' | 'takeprofitmarket' | 'takeprofitlimit'
 
 export interface Market {
   id: string
@@ -12,6 +15,12 @@ export interface Market {
   maker_fee_bps: number
   taker_fee_bps: number
   is_active: boolean
+  matching_algorithm: MatchingAlgorithm
+  fifo_slice_bps: number
+  market_address: string | null
+  base_vault: string | null
+  quote_vault: string | null
+  fee_recipient: string | null
   created_at: string
 }
 
@@ -25,7 +34,12 @@ export interface Order {
   size: number
   filled: number
   status: OrderStatus
+  order_type: OrderType
+  trigger_price: number | null
+  triggered_at: string | null
+  display_size: number | null
   on_chain_signature: string | null
+  expires_at: string | null
   created_at: string
   updated_at: string
 }
@@ -39,8 +53,11 @@ export interface Trade {
   taker_wallet: string
   price: number
   size: number
+  taker_side: OrderSide
+  sequence: number
   maker_fee: number
   taker_fee: number
+  settlement_status: SettlementStatus
   settlement_signature: string | null
   created_at: string
 }
@@ -53,12 +70,168 @@ export interface OrderbookLevel {
 
 export interface OrderbookSnapshot {
   market_id: string
+  sequence: number
   bids: OrderbookLevel[]
   asks: OrderbookLevel[]
   last_price: number | null
   timestamp: string
 }
 
+export interface OrderbookDelta {
+  market_id: string
+  sequence: number
+  prev_sequence: number
+  bids: OrderbookLevel[]
+  asks: OrderbookLevel[]
+  last_price: number | null
+  timestamp: string
+}
+
+export interface TradeSettlement {
+  market_id: string
+  trade_id: number
+  sequence: number
+  status: SettlementStatus
+  signature: string | null
+}
+
+export type PrivateChannel = 'orders' | 'fills' | 'balances'
+
+export interface Fill {
+  trade_id: number
+  market_id: string
+  order_id: string
+  wallet: string
+  side: OrderSide
+  role: 'maker' | 'taker'
+  price: number
+  size: number
+  /** Negative for a maker rebate. */
+  fee: number
+  sequence: number
+  settlement_status: SettlementStatus
+  settlement_signature: string | null
+  created_at: string
+}
+
+export interface WalletFees {
+  wallet: string
+  market_id: string
+  volume_30d: number
+  /** Negative when the wallet earns a maker rebate. */
+  maker_fee_bps: number
+  taker_fee_bps: number
+  source: 'override' | 'tier' | 'market'
+  referrer: string | null
+}
+
+export interface Referral {
+  wallet: string
+  referrer: string
+  created_at: string
+}
+
+export interface ReferralEarnings {
+  market_id: string
+  settled: number
+  pending: number
+  trade_count: number
+}
+
+export interface ReferralSummary {
+  wallet: string
+  referrer: string | null
+  referee_count: number
+  earnings: ReferralEarnings[]
+}
+
+export type ApiKeyScope = 'read' | 'trade' | 'cancel_only'
+
+export interface ApiKey {
+  key_id: string
+  wallet: string
+  label: string
+  scopes: ApiKeyScope[]
+  /** IPs or CIDR ranges; empty allows any address. */
+  ip_allowlist: string[]
+  expires_at: string | null
+  revoked_at: string | null
+  created_at: string
+}
+
+/** Returned once on creation; the secret cannot be fetched again. */
+export interface CreatedApiKey extends ApiKey {
+  secret: string
+}
+
+export interface CreateApiKeyRequest {
+  wallet: string
+  label: string
+  scopes: ApiKeyScope[]
+  ip_allowlist?: string[]
+  expires_at?: string | null
+  /** Unix milliseconds, signed as part of the registration message. */
+  timestamp: number
+  signature: string
+}
+
+/** Session key allowed to place and cancel orders for a vault. Zero expiry and limits mean unlimited. */
+export interface Delegate {
+  wallet: string
+  delegate: string
+  expires_at: number
+  max_order_notional: number
+  max_total_notional: number
+  notional_used: number
+}
+
+export interface BalanceChange {
+  market_id: string
+  reason: 'deposit' | 'withdrawal' | 'trade'
+  base_delta: number
+  quote_delta: number
+  reference: string
+}
+
+export type CandleInterval = '1m' | '5m' | '15m' | '1h' | '4h' | '1d'
+
+export interface Candle {
+  market_id: string
+  interval: CandleInterval
+  open_time: string
+  open: number
+  high: number
+  low: number
+  close: number
+  volume: number
+  trade_count: number
+}
+
+export interface Ticker {
+  market_id: string
+  last_price: number | null
+  best_bid: number | null
+  best_ask: number | null
+  open_24h: number | null
+  high_24h: number | null
+  low_24h: number | null
+  price_change_24h: number | null
+  price_change_bps_24h: number | null
+  volume_24h: number
+  quote_volume_24h: number
+  trade_count_24h: number
+  timestamp: string
+}
+
+/** Keyset page: results are newest first; pass the last `id` as `before` for older rows. */
+export interface PageParams {
+  before?: number
+  after?: number
+  from?: string
+  to?: string
+  limit?: number
+}
+
 export interface UserVault {
   base_balance: number
   quote_balance: number
@@ -74,9 +247,47 @@ export interface PlaceOrderRequest {
   wallet: string
   signature: string
   order_id?: string
+  session_id?: string
+  expires_at?: string
+  order_type?: OrderType
+  trigger_price?: number
+  display_size?: number
+}
+
+export type WsChannel =
+  | 'book.L2'
+  | 'book.L1'
+  | 'trades'
+  | 'ticker'
+  | `candles.${CandleInterval}`
+  | PrivateChannel
+
+export interface SubscribeParams {
+  channel: WsChannel
+  market_id?: string
+  depth?: number
+  throttle_ms?: number
 }
 
 export interface WsMessage {
-  type: 'subscribe' | 'unsubscribe' | 'orderbook_snapshot' | 'orderbook_update' | 'trade' | 'order_update' | 'error'
+  id?: string
+  type:
+    | 'subscribe'
+    | 'unsubscribe'
+    | 'subscribed'
+    | 'unsubscribed'
+    | 'ping'
+    | 'pong'
+    | 'orderbook_snapshot'
+    | 'orderbook_update'
+    | 'book_top'
+    | 'trade'
+    | 'trade_settlement'
+    | 'order_update'
+    | 'fill'
+    | 'balance_update'
+    | 'ticker'
+    | 'candle'
+    | 'error'
   data?: unknown
 }
@@ -0,0 +1,111 @@
+//! Reference fills with their expected amounts. The engine's and the
+//! program's tests both check against these, so a change to either side's
+//! math that breaks agreement fails a test.
+
+use crate::FillAmounts;
+
+pub struct GoldenFill {
+    pub name: &'static str,
+    pub size: u64,
+    pub price: u64,
+    pub base_decimals: u8,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: u16,
+    pub expected: Option<FillAmounts>,
+}
+
+pub const FILLS: &[GoldenFill] = &[
+    GoldenFill {
+        name: "2.5 SOL at 150 USDC",
+        size: 2_500_000_000,
+        price: 150_000_000,
+        base_decimals: 9,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        expected: Some(FillAmounts {
+            base: 2_500_000_000,
+            quote: 375_000_000,
+            maker_fee: 187_500,
+            taker_fee: 375_000,
+        }),
+    },
+    GoldenFill {
+        name: "quote and fees round down",
+        size: 333,
+        price: 1_234_567,
+        base_decimals: 6,
+        maker_fee_bps: 5,
+        taker_fee_bps: 100,
+        expected: Some(FillAmounts {
+            base: 333,
+            quote: 411,
+            maker_fee: 0,
+            taker_fee: 4,
+        }),
+    },
+    GoldenFill {
+        name: "intermediate product above u64",
+        size: 10_000_000_000_000_000,
+        price: 100_000_000_000,
+        base_decimals: 9,
+        maker_fee_bps: 1,
+        taker_fee_bps: 10,
+        expected: Some(FillAmounts {
+            base: 10_000_000_000_000_000,
+            quote: 1_000_000_000_000_000_000,
+            maker_fee: 100_000_000_000_000,
+            taker_fee: 1_000_000_000_000_000,
+        }),
+    },
+    GoldenFill {
+        name: "zero-decimal base token",
+        size: 7,
+        price: 3,
+        base_decimals: 0,
+        maker_fee_bps: 0,
+        taker_fee_bps: 100,
+        expected: Some(FillAmounts {
+            base: 7,
+            quote: 21,
+            maker_fee: 0,
+            taker_fee: 0,
+        }),
+    },
+    GoldenFill {
+        name: "maker rebate",
+        size: 2_500_000_000,
+        price: 150_000_000,
+        base_decimals: 9,
+        maker_fee_bps: -2,
+        taker_fee_bps: 10,
+        expected: Some(FillAmounts {
+            base: 2_500_000_000,
+            quote: 375_000_000,
+            maker_fee: -75_000,
+            taker_fee: 375_000,
+        }),
+    },
+    GoldenFill {
+        name: "maker rebate capped at the taker fee",
+        size: 2_500_000_000,
+        price: 150_000_000,
+        base_decimals: 9,
+        maker_fee_bps: -20,
+        taker_fee_bps: 10,
+        expected: Some(FillAmounts {
+            base: 2_500_000_000,
+            quote: 375_000_000,
+            maker_fee: -375_000,
+            taker_fee: 375_000,
+        }),
+    },
+    GoldenFill {
+        name: "quote value above u64",
+        size: u64::MAX,
+        price: u64::MAX,
+        base_decimals: 9,
+        maker_fee_bps: 5,
+        taker_fee_bps: 10,
+        expected: None,
+    },
+];
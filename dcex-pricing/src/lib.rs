@@ -0,0 +1,157 @@
+//! Fixed-point price and fee math shared by the matching engine and the
+//! on-chain program, so both compute the same amounts for every fill.
+//!
+//! Sizes are in base atoms. Prices are in quote atoms per whole base unit,
+//! i.e. per `10^base_decimals` base atoms, so a fill is worth
+//! `size * price / 10^base_decimals` quote atoms. Fees are basis points of
+//! that value. Intermediates are `u128`, every division rounds down, and any
+//! result that does not fit in `u64` is reported as `None`.
+//!
+//! A negative maker rate is a rebate. Rebates are paid out of the taker fee
+//! of the same fill and are capped at it, so a fill never costs the fee
+//! recipient anything.
+//!
+//! A referred taker's referrer gets `share_bps` of the taker fee, out of
+//! what the fee recipient would otherwise keep.
+
+pub mod golden;
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Quote atoms exchanged for `size` base atoms at `price`.
+pub fn quote_amount(size: u64, price: u64, base_decimals: u8) -> Option<u64> {
+    let unit = 10u128.checked_pow(u32::from(base_decimals))?;
+    let amount = u128::from(size).checked_mul(u128::from(price))? / unit;
+    u64::try_from(amount).ok()
+}
+
+/// `fee_bps` of `quote_amount`, rounded down.
+pub fn fee(quote_amount: u64, fee_bps: u16) -> Option<u64> {
+    let fee = u128::from(quote_amount).checked_mul(u128::from(fee_bps))? / BPS_DENOMINATOR;
+    u64::try_from(fee).ok()
+}
+
+/// Maker fee for a fill at `maker_fee_bps`; negative is a rebate, capped at
+/// the fill's `taker_fee`.
+pub fn maker_fee(quote_amount: u64, maker_fee_bps: i16, taker_fee: u64) -> Option<i64> {
+    let amount = fee(quote_amount, maker_fee_bps.unsigned_abs())?;
+    if maker_fee_bps >= 0 {
+        i64::try_from(amount).ok()
+    } else {
+        let rebate = i64::try_from(amount.min(taker_fee)).ok()?;
+        Some(-rebate)
+    }
+}
+
+/// Everything `settle_trade` moves for one fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillAmounts {
+    pub base: u64,
+    pub quote: u64,
+    /// Negative when the maker earns a rebate.
+    pub maker_fee: i64,
+    pub taker_fee: u64,
+}
+
+impl FillAmounts {
+    /// What the fee recipient keeps: the taker fee plus the maker fee, or
+    /// minus the maker rebate.
+    pub fn net_fee(&self) -> Option<u64> {
+        self.taker_fee.checked_add_signed(self.maker_fee)
+    }
+
+    /// The referrer's cut of the taker fee, never more than the net fee left
+    /// after a maker rebate.
+    pub fn referrer_fee(&self, share_bps: u16) -> Option<u64> {
+        Some(fee(self.taker_fee, share_bps)?.min(self.net_fee()?))
+    }
+}
+
+pub fn fill_amounts(
+    size: u64,
+    price: u64,
+    base_decimals: u8,
+    maker_fee_bps: i16,
+    taker_fee_bps: u16,
+) -> Option<FillAmounts> {
+    let quote = quote_amount(size, price, base_decimals)?;
+    let taker_fee = fee(quote, taker_fee_bps)?;
+    Some(FillAmounts {
+        base: size,
+        quote,
+        maker_fee: maker_fee(quote, maker_fee_bps, taker_fee)?,
+        taker_fee,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_fills() {
+        for case in golden::FILLS {
+            assert_eq!(
+                fill_amounts(
+                    case.size,
+                    case.price,
+                    case.base_decimals,
+                    case.maker_fee_bps,
+                    case.taker_fee_bps,
+                ),
+                case.expected,
+                "{}",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_quote_amount_uses_base_decimals() {
+        // One whole base unit is worth exactly `price` whatever the decimals.
+        assert_eq!(quote_amount(1_000_000_000, 150_000_000, 9), Some(150_000_000));
+        assert_eq!(quote_amount(1_000_000, 150_000_000, 6), Some(150_000_000));
+        assert_eq!(quote_amount(1, 150_000_000, 0), Some(150_000_000));
+    }
+
+    #[test]
+    fn test_split_fills_never_exceed_whole_order() {
+        // Rounding down per fill can only leave dust behind, never pay out more.
+        let whole = quote_amount(1_000, 333_333, 3).unwrap();
+        let parts = quote_amount(333, 333_333, 3).unwrap()
+            + quote_amount(333, 333_333, 3).unwrap()
+            + quote_amount(334, 333_333, 3).unwrap();
+        assert!(parts <= whole);
+    }
+
+    #[test]
+    fn test_rebates_are_funded_by_the_taker_fee() {
+        for case in golden::FILLS {
+            if let Some(amounts) = case.expected {
+                assert!(amounts.net_fee().is_some(), "{}", case.name);
+            }
+        }
+        assert_eq!(maker_fee(1_000_000, -3, 1_000), Some(-300));
+        assert_eq!(maker_fee(1_000_000, -3, 100), Some(-100));
+        assert_eq!(maker_fee(1_000_000, 3, 0), Some(300));
+    }
+
+    #[test]
+    fn test_referrer_fee_comes_out_of_the_net_fee() {
+        let amounts = fill_amounts(2_500_000_000, 150_000_000, 9, 5, 10).unwrap();
+        assert_eq!(amounts.referrer_fee(2_000), Some(75_000));
+        assert_eq!(amounts.referrer_fee(0), Some(0));
+
+        let rebated = fill_amounts(2_500_000_000, 150_000_000, 9, -8, 10).unwrap();
+        assert_eq!(rebated.net_fee(), Some(75_000));
+        assert_eq!(rebated.referrer_fee(5_000), Some(75_000));
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        assert_eq!(quote_amount(u64::MAX, u64::MAX, 0), None);
+        assert_eq!(quote_amount(1, 1, 39), None);
+        assert_eq!(fee(u64::MAX, u16::MAX), None);
+        assert_eq!(fee(u64::MAX, 10_000), Some(u64::MAX));
+    }
+}
@@ -2,9 +2,14 @@ pub const MARKET_SEED: &[u8] = b"market";
 pub const VAULT_SEED: &[u8] = b"vault";
 pub const ORDER_SEED: &[u8] = b"order";
 pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const FEE_TIER_SEED: &[u8] = b"fee_tier";
+pub const REFERRAL_SEED: &[u8] = b"referral";
+pub const DELEGATE_SEED: &[u8] = b"delegate";
 
 pub const MAX_MAKER_FEE_BPS: u16 = 100;
 pub const MAX_TAKER_FEE_BPS: u16 = 100;
+pub const MAX_MAKER_REBATE_BPS: u16 = 100;
+pub const MAX_REFERRAL_SHARE_BPS: u16 = 5_000;
 
 pub const MIN_ORDER_SIZE: u64 = 1;
 pub const MAX_ORDERS_PER_USER: u64 = 100;
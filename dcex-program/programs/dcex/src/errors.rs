@@ -43,4 +43,31 @@ pub enum DcexError {
     
     #[msg("Invalid market configuration")]
     InvalidMarketConfiguration,
+    
+    #[msg("Order has expired")]
+    OrderExpired,
+    
+    #[msg("Order has not expired yet")]
+    OrderNotExpired,
+    
+    #[msg("Invalid order expiry")]
+    InvalidExpiry,
+    
+    #[msg("Fee tier does not belong to this market and wallet")]
+    InvalidFeeTier,
+    
+    #[msg("A wallet cannot refer itself")]
+    SelfReferral,
+    
+    #[msg("Referrer token account missing or not owned by the referrer")]
+    InvalidReferrerAccount,
+    
+    #[msg("Signer is not the vault's delegate")]
+    InvalidDelegate,
+    
+    #[msg("Delegate has expired")]
+    DelegateExpired,
+    
+    #[msg("Order exceeds the delegate's notional limit")]
+    DelegateNotionalExceeded,
 }
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::instructions::cancel_order::unlock_remaining;
+use crate::state::{Market, Order, UserVault};
+
+#[derive(Accounts)]
+pub struct AuthorityCancelOrder<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = market.authority == authority.key() @ DcexError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, order.user.as_ref(), market.key().as_ref()],
+        bump = user_vault.bump,
+        constraint = user_vault.user == order.user @ DcexError::Unauthorized
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_SEED, order.order_id.to_le_bytes().as_ref()],
+        bump = order.bump,
+        constraint = order.market == market.key() @ DcexError::InvalidMarketConfiguration
+    )]
+    pub order: Account<'info, Order>,
+}
+
+/// Lets the matching engine pull an order it cancelled off-chain (e.g. when the
+/// owner's cancel-on-disconnect session ends), releasing the locked funds.
+pub fn handler(ctx: Context<AuthorityCancelOrder>) -> Result<()> {
+    let market = &ctx.accounts.market;
+    let user_vault = &mut ctx.accounts.user_vault;
+    let order = &mut ctx.accounts.order;
+
+    unlock_remaining(market, user_vault, order)?;
+    order.cancel()?;
+
+    msg!("Order cancelled by market authority: id={}", order.order_id);
+
+    Ok(())
+}
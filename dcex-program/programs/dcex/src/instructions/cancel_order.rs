@@ -34,14 +34,26 @@ pub fn handler(ctx: Context<CancelOrder>) -> Result<()> {
     let user_vault = &mut ctx.accounts.user_vault;
     let order = &mut ctx.accounts.order;
 
+    unlock_remaining(market, user_vault, order)?;
+    order.cancel()?;
+
+    msg!("Order cancelled: id={}", order.order_id);
+
+    Ok(())
+}
+
+/// Unlocks whatever the order still holds in the vault. The caller is
+/// responsible for moving the order to its final status.
+pub(crate) fn unlock_remaining(
+    market: &Market,
+    user_vault: &mut UserVault,
+    order: &Order,
+) -> Result<()> {
     require!(order.is_active(), DcexError::InvalidOrderStatus);
 
     let remaining = order.remaining();
 
-    let quote_amount = remaining
-        .checked_mul(order.price)
-        .ok_or(DcexError::ArithmeticOverflow)?
-        .checked_div(10u64.pow(market.base_decimals as u32))
+    let quote_amount = dcex_pricing::quote_amount(remaining, order.price, market.base_decimals)
         .ok_or(DcexError::ArithmeticOverflow)?;
 
     match order.side {
@@ -53,9 +65,5 @@ pub fn handler(ctx: Context<CancelOrder>) -> Result<()> {
         }
     }
 
-    order.cancel()?;
-
-    msg!("Order cancelled: id={}", order.order_id);
-
     Ok(())
 }
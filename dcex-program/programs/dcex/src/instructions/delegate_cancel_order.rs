@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::instructions::cancel_order::unlock_remaining;
+use crate::state::{Delegate, Market, Order, UserVault};
+
+#[derive(Accounts)]
+pub struct DelegateCancelOrder<'info> {
+    pub signer: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, user_vault.user.as_ref(), market.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        seeds = [DELEGATE_SEED, user_vault.key().as_ref()],
+        bump = delegate.bump,
+        constraint = delegate.delegate == signer.key() @ DcexError::InvalidDelegate
+    )]
+    pub delegate: Account<'info, Delegate>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_SEED, order.order_id.to_le_bytes().as_ref()],
+        bump = order.bump,
+        constraint = order.user == user_vault.user @ DcexError::Unauthorized,
+        constraint = order.market == market.key() @ DcexError::InvalidMarketConfiguration
+    )]
+    pub order: Account<'info, Order>,
+}
+
+/// Cancels one of the vault owner's orders, signed by their delegate. Any
+/// of the owner's orders can be cancelled, not only the delegate's own.
+pub fn handler(ctx: Context<DelegateCancelOrder>) -> Result<()> {
+    require!(
+        !ctx.accounts.delegate.is_expired(Clock::get()?.unix_timestamp),
+        DcexError::DelegateExpired
+    );
+
+    let market = &ctx.accounts.market;
+    let user_vault = &mut ctx.accounts.user_vault;
+    let order = &mut ctx.accounts.order;
+
+    unlock_remaining(market, user_vault, order)?;
+    order.cancel()?;
+
+    msg!("Order cancelled by delegate: id={}", order.order_id);
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::instructions::place_order::{open_order, PlaceOrderParams};
+use crate::state::{Delegate, Market, Order, UserVault};
+
+#[derive(Accounts)]
+#[instruction(params: PlaceOrderParams)]
+pub struct DelegatePlaceOrder<'info> {
+    /// The session key; pays for the order account.
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(
+        constraint = market.is_active @ DcexError::MarketNotActive
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, user_vault.user.as_ref(), market.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        mut,
+        seeds = [DELEGATE_SEED, user_vault.key().as_ref()],
+        bump = delegate.bump,
+        constraint = delegate.delegate == signer.key() @ DcexError::InvalidDelegate
+    )]
+    pub delegate: Account<'info, Delegate>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = Order::LEN,
+        seeds = [ORDER_SEED, params.order_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Places an order for the vault owner, signed by their delegate. The
+/// order's quote value counts against the delegate's notional limits.
+pub fn handler(ctx: Context<DelegatePlaceOrder>, params: PlaceOrderParams) -> Result<()> {
+    let delegate = &mut ctx.accounts.delegate;
+    require!(
+        !delegate.is_expired(Clock::get()?.unix_timestamp),
+        DcexError::DelegateExpired
+    );
+
+    let quote_amount = open_order(
+        &ctx.accounts.market,
+        &mut ctx.accounts.user_vault,
+        &mut ctx.accounts.order,
+        ctx.bumps.order,
+        &params,
+    )?;
+    delegate.use_notional(quote_amount)?;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::instructions::cancel_order::unlock_remaining;
+use crate::state::{Market, Order, UserVault};
+
+#[derive(Accounts)]
+pub struct ExpireOrder<'info> {
+    pub caller: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, order.user.as_ref(), market.key().as_ref()],
+        bump = user_vault.bump,
+        constraint = user_vault.user == order.user @ DcexError::Unauthorized
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_SEED, order.order_id.to_le_bytes().as_ref()],
+        bump = order.bump,
+        constraint = order.market == market.key() @ DcexError::InvalidMarketConfiguration
+    )]
+    pub order: Account<'info, Order>,
+}
+
+/// Permissionless: once an order is past its expiry anyone can release the
+/// funds it still has locked back to the owner's available balance.
+pub fn handler(ctx: Context<ExpireOrder>) -> Result<()> {
+    let market = &ctx.accounts.market;
+    let user_vault = &mut ctx.accounts.user_vault;
+    let order = &mut ctx.accounts.order;
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(order.is_expired(now), DcexError::OrderNotExpired);
+
+    unlock_remaining(market, user_vault, order)?;
+    order.expire()?;
+
+    msg!("Order expired: id={}", order.order_id);
+
+    Ok(())
+}
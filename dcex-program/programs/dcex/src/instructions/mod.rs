@@ -4,6 +4,15 @@ pub mod withdraw;
 pub mod place_order;
 pub mod cancel_order;
 pub mod settle_trade;
+pub mod authority_cancel_order;
+pub mod expire_order;
+pub mod set_fee_tier;
+pub mod set_referral;
+pub mod set_referral_share;
+pub mod set_delegate;
+pub mod revoke_delegate;
+pub mod delegate_place_order;
+pub mod delegate_cancel_order;
 
 pub use initialize_market::*;
 pub use deposit::*;
@@ -11,3 +20,12 @@ pub use withdraw::*;
 pub use place_order::*;
 pub use cancel_order::*;
 pub use settle_trade::*;
+pub use authority_cancel_order::*;
+pub use expire_order::*;
+pub use set_fee_tier::*;
+pub use set_referral::*;
+pub use set_referral_share::*;
+pub use set_delegate::*;
+pub use revoke_delegate::*;
+pub use delegate_place_order::*;
+pub use delegate_cancel_order::*;
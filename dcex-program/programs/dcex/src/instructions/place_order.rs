@@ -41,13 +41,30 @@ pub struct PlaceOrderParams {
     pub side: OrderSide,
     pub price: u64,
     pub size: u64,
+    /// Unix timestamp after which the order stops trading; 0 = good-till-cancel.
+    pub expires_at: i64,
 }
 
 pub fn handler(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()> {
-    let market = &ctx.accounts.market;
-    let user_vault = &mut ctx.accounts.user_vault;
-    let order = &mut ctx.accounts.order;
+    open_order(
+        &ctx.accounts.market,
+        &mut ctx.accounts.user_vault,
+        &mut ctx.accounts.order,
+        ctx.bumps.order,
+        &params,
+    )?;
+    Ok(())
+}
 
+/// Validates the order, locks its funds in the vault and fills in the new
+/// order account for the vault owner. Returns the order's quote value.
+pub(crate) fn open_order(
+    market: &Account<Market>,
+    user_vault: &mut UserVault,
+    order: &mut Order,
+    bump: u8,
+    params: &PlaceOrderParams,
+) -> Result<u64> {
     require!(
         market.validate_order_size(params.size),
         DcexError::OrderSizeBelowMinimum
@@ -57,10 +74,13 @@ pub fn handler(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()>
         DcexError::PriceNotAlignedToTick
     );
 
-    let quote_amount = params.size
-        .checked_mul(params.price)
-        .ok_or(DcexError::ArithmeticOverflow)?
-        .checked_div(10u64.pow(market.base_decimals as u32))
+    let clock = Clock::get()?;
+    require!(
+        params.expires_at == 0 || params.expires_at > clock.unix_timestamp,
+        DcexError::InvalidExpiry
+    );
+
+    let quote_amount = dcex_pricing::quote_amount(params.size, params.price, market.base_decimals)
         .ok_or(DcexError::ArithmeticOverflow)?;
 
     match params.side {
@@ -72,10 +92,8 @@ pub fn handler(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()>
         }
     }
 
-    let clock = Clock::get()?;
-    
-    order.user = ctx.accounts.user.key();
-    order.market = ctx.accounts.market.key();
+    order.user = user_vault.user;
+    order.market = market.key();
     order.order_id = params.order_id;
     order.side = params.side;
     order.price = params.price;
@@ -84,7 +102,8 @@ pub fn handler(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()>
     order.status = OrderStatus::Pending;
     order.created_at = clock.unix_timestamp;
     order.updated_at = clock.unix_timestamp;
-    order.bump = ctx.bumps.order;
+    order.bump = bump;
+    order.expires_at = params.expires_at;
 
     msg!(
         "Order placed: id={}, side={:?}, price={}, size={}",
@@ -94,5 +113,5 @@ pub fn handler(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()>
         order.size
     );
 
-    Ok(())
+    Ok(quote_amount)
 }
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::state::{Delegate, Market, UserVault};
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [VAULT_SEED, user.key().as_ref(), market.key().as_ref()],
+        bump = user_vault.bump,
+        constraint = user_vault.user == user.key() @ DcexError::Unauthorized
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [DELEGATE_SEED, user_vault.key().as_ref()],
+        bump = delegate.bump
+    )]
+    pub delegate: Account<'info, Delegate>,
+}
+
+/// Removes the vault's delegate and returns the rent to the owner. Orders
+/// the delegate already placed stay on the book.
+pub fn handler(ctx: Context<RevokeDelegate>) -> Result<()> {
+    msg!(
+        "Delegate revoked: user={}, delegate={}",
+        ctx.accounts.user.key(),
+        ctx.accounts.delegate.delegate
+    );
+
+    Ok(())
+}
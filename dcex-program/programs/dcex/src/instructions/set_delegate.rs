@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::state::{Delegate, Market, UserVault};
+
+#[derive(Accounts)]
+pub struct SetDelegate<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [VAULT_SEED, user.key().as_ref(), market.key().as_ref()],
+        bump = user_vault.bump,
+        constraint = user_vault.user == user.key() @ DcexError::Unauthorized
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Delegate::LEN,
+        seeds = [DELEGATE_SEED, user_vault.key().as_ref()],
+        bump
+    )]
+    pub delegate: Account<'info, Delegate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetDelegateParams {
+    pub delegate: Pubkey,
+    /// Unix timestamp after which the delegate stops working; 0 = no expiry.
+    pub expires_at: i64,
+    /// 0 = unlimited.
+    pub max_order_notional: u64,
+    /// 0 = unlimited.
+    pub max_total_notional: u64,
+}
+
+/// Authorizes `params.delegate` to place and cancel orders against the
+/// vault, replacing any previous delegate and resetting its usage.
+pub fn handler(ctx: Context<SetDelegate>, params: SetDelegateParams) -> Result<()> {
+    require!(
+        params.delegate != ctx.accounts.user.key(),
+        DcexError::InvalidDelegate
+    );
+    let clock = Clock::get()?;
+    require!(
+        params.expires_at == 0 || params.expires_at > clock.unix_timestamp,
+        DcexError::InvalidExpiry
+    );
+
+    let delegate = &mut ctx.accounts.delegate;
+    delegate.user_vault = ctx.accounts.user_vault.key();
+    delegate.user = ctx.accounts.user.key();
+    delegate.delegate = params.delegate;
+    delegate.expires_at = params.expires_at;
+    delegate.max_order_notional = params.max_order_notional;
+    delegate.max_total_notional = params.max_total_notional;
+    delegate.notional_used = 0;
+    delegate.bump = ctx.bumps.delegate;
+
+    msg!(
+        "Delegate set: user={}, delegate={}, expires_at={}",
+        delegate.user,
+        params.delegate,
+        params.expires_at
+    );
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::state::{FeeTier, Market};
+
+#[derive(Accounts)]
+#[instruction(params: SetFeeTierParams)]
+pub struct SetFeeTier<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = market.authority == authority.key() @ DcexError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = FeeTier::LEN,
+        seeds = [FEE_TIER_SEED, market.key().as_ref(), params.wallet.as_ref()],
+        bump
+    )]
+    pub fee_tier: Account<'info, FeeTier>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetFeeTierParams {
+    pub wallet: Pubkey,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: u16,
+}
+
+/// Records the rates the engine charges `wallet` on this market, so
+/// `settle_trade` can prove the rate it applies.
+pub fn handler(ctx: Context<SetFeeTier>, params: SetFeeTierParams) -> Result<()> {
+    let maker_fee_ok = if params.maker_fee_bps < 0 {
+        params.maker_fee_bps.unsigned_abs() <= MAX_MAKER_REBATE_BPS
+    } else {
+        params.maker_fee_bps.unsigned_abs() <= MAX_MAKER_FEE_BPS
+    };
+    require!(maker_fee_ok, DcexError::InvalidFeeConfiguration);
+    require!(
+        params.taker_fee_bps <= MAX_TAKER_FEE_BPS,
+        DcexError::InvalidFeeConfiguration
+    );
+
+    let fee_tier = &mut ctx.accounts.fee_tier;
+    fee_tier.market = ctx.accounts.market.key();
+    fee_tier.wallet = params.wallet;
+    fee_tier.maker_fee_bps = params.maker_fee_bps;
+    fee_tier.taker_fee_bps = params.taker_fee_bps;
+    fee_tier.bump = ctx.bumps.fee_tier;
+
+    msg!(
+        "Fee tier set: wallet={}, maker_fee_bps={}, taker_fee_bps={}",
+        params.wallet,
+        params.maker_fee_bps,
+        params.taker_fee_bps
+    );
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::state::{Market, Referral};
+
+#[derive(Accounts)]
+#[instruction(params: SetReferralParams)]
+pub struct SetReferral<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = market.authority == authority.key() @ DcexError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Referral::LEN,
+        seeds = [REFERRAL_SEED, market.key().as_ref(), params.wallet.as_ref()],
+        bump
+    )]
+    pub referral: Account<'info, Referral>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetReferralParams {
+    pub wallet: Pubkey,
+    pub referrer: Pubkey,
+}
+
+/// Records the referrer `wallet` registered. A referral is set once and
+/// cannot be changed afterwards.
+pub fn handler(ctx: Context<SetReferral>, params: SetReferralParams) -> Result<()> {
+    require!(params.wallet != params.referrer, DcexError::SelfReferral);
+
+    let referral = &mut ctx.accounts.referral;
+    referral.market = ctx.accounts.market.key();
+    referral.wallet = params.wallet;
+    referral.referrer = params.referrer;
+    referral.bump = ctx.bumps.referral;
+
+    msg!("Referral set: wallet={}, referrer={}", params.wallet, params.referrer);
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::*;
+use crate::errors::DcexError;
+use crate::state::Market;
+
+#[derive(Accounts)]
+pub struct SetReferralShare<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.authority == authority.key() @ DcexError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetReferralShareParams {
+    pub referral_share_bps: u16,
+}
+
+pub fn handler(ctx: Context<SetReferralShare>, params: SetReferralShareParams) -> Result<()> {
+    require!(
+        params.referral_share_bps <= MAX_REFERRAL_SHARE_BPS,
+        DcexError::InvalidFeeConfiguration
+    );
+
+    ctx.accounts.market.referral_share_bps = params.referral_share_bps;
+
+    msg!("Referral share set: {} bps", params.referral_share_bps);
+
+    Ok(())
+}
@@ -3,7 +3,7 @@ use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 use crate::constants::*;
 use crate::errors::DcexError;
-use crate::state::{Market, Order, OrderSide, UserVault};
+use crate::state::{FeeTier, Market, Order, OrderSide, Referral, UserVault};
 
 #[derive(Accounts)]
 pub struct SettleTrade<'info> {
@@ -60,6 +60,34 @@ pub struct SettleTrade<'info> {
     pub fee_recipient: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
+
+    /// Rates of the maker's wallet; the market defaults apply when omitted.
+    #[account(
+        seeds = [FEE_TIER_SEED, market.key().as_ref(), maker_order.user.as_ref()],
+        bump = maker_fee_tier.bump,
+        constraint = maker_fee_tier.wallet == maker_order.user @ DcexError::InvalidFeeTier
+    )]
+    pub maker_fee_tier: Option<Account<'info, FeeTier>>,
+
+    /// Rates of the taker's wallet; the market defaults apply when omitted.
+    #[account(
+        seeds = [FEE_TIER_SEED, market.key().as_ref(), taker_order.user.as_ref()],
+        bump = taker_fee_tier.bump,
+        constraint = taker_fee_tier.wallet == taker_order.user @ DcexError::InvalidFeeTier
+    )]
+    pub taker_fee_tier: Option<Account<'info, FeeTier>>,
+
+    /// The taker's referral; its referrer gets `referral_share_bps` of the
+    /// taker fee.
+    #[account(
+        seeds = [REFERRAL_SEED, market.key().as_ref(), taker_order.user.as_ref()],
+        bump = taker_referral.bump
+    )]
+    pub taker_referral: Option<Account<'info, Referral>>,
+
+    /// Quote token account of the referrer, required with `taker_referral`.
+    #[account(mut)]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -77,6 +105,10 @@ pub fn handler(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<(
 
     require!(maker_order.is_active(), DcexError::InvalidOrderStatus);
     require!(taker_order.is_active(), DcexError::InvalidOrderStatus);
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(!maker_order.is_expired(now), DcexError::OrderExpired);
+    require!(!taker_order.is_expired(now), DcexError::OrderExpired);
     require!(
         maker_order.remaining() >= params.fill_size,
         DcexError::SettlementAmountMismatch
@@ -86,20 +118,34 @@ pub fn handler(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<(
         DcexError::SettlementAmountMismatch
     );
 
-    let base_amount = params.fill_size;
-    let quote_amount = params.fill_size
-        .checked_mul(params.fill_price)
-        .ok_or(DcexError::ArithmeticOverflow)?
-        .checked_div(10u64.pow(market.base_decimals as u32))
+    // The engine records fees with the same function, so they always agree.
+    let amounts = market
+        .fill_amounts(
+            params.fill_size,
+            params.fill_price,
+            ctx.accounts.maker_fee_tier.as_deref(),
+            ctx.accounts.taker_fee_tier.as_deref(),
+        )
         .ok_or(DcexError::ArithmeticOverflow)?;
-
-    let maker_fee = market.calculate_maker_fee(quote_amount)
-        .ok_or(DcexError::ArithmeticOverflow)?;
-    let taker_fee = market.calculate_taker_fee(quote_amount)
-        .ok_or(DcexError::ArithmeticOverflow)?;
-    let total_fees = maker_fee
-        .checked_add(taker_fee)
+    let dcex_pricing::FillAmounts {
+        base: base_amount,
+        quote: quote_amount,
+        maker_fee,
+        taker_fee,
+    } = amounts;
+    // A maker rebate is paid out of the taker fee, so only the rest leaves
+    // the quote vault.
+    let net_fees = amounts.net_fee().ok_or(DcexError::ArithmeticOverflow)?;
+    let referrer_fee = match &ctx.accounts.taker_referral {
+        Some(_) => amounts
+            .referrer_fee(market.referral_share_bps)
+            .ok_or(DcexError::ArithmeticOverflow)?,
+        None => 0,
+    };
+    let total_fees = net_fees
+        .checked_sub(referrer_fee)
         .ok_or(DcexError::ArithmeticOverflow)?;
+    let maker_rebate = maker_fee.checked_neg().ok_or(DcexError::ArithmeticOverflow)?;
 
     let seeds = &[
         MARKET_SEED,
@@ -116,7 +162,7 @@ pub fn handler(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<(
                 .checked_sub(base_amount)
                 .ok_or(DcexError::ArithmeticOverflow)?;
             let maker_quote_received = quote_amount
-                .checked_sub(maker_fee)
+                .checked_add_signed(maker_rebate)
                 .ok_or(DcexError::ArithmeticOverflow)?;
             maker_vault.quote_balance = maker_vault.quote_balance
                 .checked_add(maker_quote_received)
@@ -136,7 +182,7 @@ pub fn handler(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<(
         OrderSide::Buy => {
             maker_vault.unlock_quote(quote_amount)?;
             let maker_quote_paid = quote_amount
-                .checked_add(maker_fee)
+                .checked_add_signed(maker_fee)
                 .ok_or(DcexError::ArithmeticOverflow)?;
             maker_vault.quote_balance = maker_vault.quote_balance
                 .checked_sub(maker_quote_paid)
@@ -176,6 +222,31 @@ pub fn handler(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<(
         token::transfer(fee_cpi_ctx, total_fees)?;
     }
 
+    if referrer_fee > 0 {
+        let referrer = ctx.accounts.taker_referral.as_ref().map(|referral| referral.referrer);
+        let referrer_token_account = ctx
+            .accounts
+            .referrer_token_account
+            .as_ref()
+            .ok_or(DcexError::InvalidReferrerAccount)?;
+        require!(
+            Some(referrer_token_account.owner) == referrer
+                && referrer_token_account.mint == market.quote_mint,
+            DcexError::InvalidReferrerAccount
+        );
+        let referrer_cpi_accounts = Transfer {
+            from: ctx.accounts.quote_vault.to_account_info(),
+            to: referrer_token_account.to_account_info(),
+            authority: ctx.accounts.market.to_account_info(),
+        };
+        let referrer_cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            referrer_cpi_accounts,
+            signer_seeds,
+        );
+        token::transfer(referrer_cpi_ctx, referrer_fee)?;
+    }
+
     maker_order.fill(params.fill_size)?;
     taker_order.fill(params.fill_size)?;
 
@@ -39,4 +39,46 @@ pub mod dcex {
     pub fn settle_trade(ctx: Context<SettleTrade>, params: SettleTradeParams) -> Result<()> {
         instructions::settle_trade::handler(ctx, params)
     }
+
+    pub fn authority_cancel_order(ctx: Context<AuthorityCancelOrder>) -> Result<()> {
+        instructions::authority_cancel_order::handler(ctx)
+    }
+
+    pub fn expire_order(ctx: Context<ExpireOrder>) -> Result<()> {
+        instructions::expire_order::handler(ctx)
+    }
+
+    pub fn set_fee_tier(ctx: Context<SetFeeTier>, params: SetFeeTierParams) -> Result<()> {
+        instructions::set_fee_tier::handler(ctx, params)
+    }
+
+    pub fn set_referral(ctx: Context<SetReferral>, params: SetReferralParams) -> Result<()> {
+        instructions::set_referral::handler(ctx, params)
+    }
+
+    pub fn set_referral_share(
+        ctx: Context<SetReferralShare>,
+        params: SetReferralShareParams,
+    ) -> Result<()> {
+        instructions::set_referral_share::handler(ctx, params)
+    }
+
+    pub fn set_delegate(ctx: Context<SetDelegate>, params: SetDelegateParams) -> Result<()> {
+        instructions::set_delegate::handler(ctx, params)
+    }
+
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        instructions::revoke_delegate::handler(ctx)
+    }
+
+    pub fn delegate_place_order(
+        ctx: Context<DelegatePlaceOrder>,
+        params: PlaceOrderParams,
+    ) -> Result<()> {
+        instructions::delegate_place_order::handler(ctx, params)
+    }
+
+    pub fn delegate_cancel_order(ctx: Context<DelegateCancelOrder>) -> Result<()> {
+        instructions::delegate_cancel_order::handler(ctx)
+    }
 }
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+/// A session key the vault owner authorized to place and cancel orders on
+/// their behalf. Delegates can never withdraw. One per `UserVault`.
+#[account]
+#[derive(Default)]
+pub struct Delegate {
+    pub user_vault: Pubkey,
+    pub user: Pubkey,
+    pub delegate: Pubkey,
+    /// Unix timestamp after which the delegate stops working; 0 = no expiry.
+    pub expires_at: i64,
+    /// Largest quote value of a single order; 0 = unlimited.
+    pub max_order_notional: u64,
+    /// Total quote value the delegate may place; 0 = unlimited.
+    pub max_total_notional: u64,
+    pub notional_used: u64,
+    pub bump: u8,
+}
+
+impl Delegate {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // user_vault
+        32 + // user
+        32 + // delegate
+        8 +  // expires_at
+        8 +  // max_order_notional
+        8 +  // max_total_notional
+        8 +  // notional_used
+        1 +  // bump
+        32;  // padding
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    /// Charges an order's quote value against the limits.
+    pub fn use_notional(&mut self, notional: u64) -> Result<()> {
+        require!(
+            self.max_order_notional == 0 || notional <= self.max_order_notional,
+            crate::errors::DcexError::DelegateNotionalExceeded
+        );
+        let used = self
+            .notional_used
+            .checked_add(notional)
+            .ok_or(crate::errors::DcexError::ArithmeticOverflow)?;
+        require!(
+            self.max_total_notional == 0 || used <= self.max_total_notional,
+            crate::errors::DcexError::DelegateNotionalExceeded
+        );
+        self.notional_used = used;
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+/// Rates the market authority granted one wallet, replacing the market
+/// defaults in `settle_trade`. A negative maker rate is a rebate.
+#[account]
+#[derive(Default)]
+pub struct FeeTier {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: u16,
+    pub bump: u8,
+}
+
+impl FeeTier {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // market
+        32 + // wallet
+        2 +  // maker_fee_bps
+        2 +  // taker_fee_bps
+        1 +  // bump
+        32;  // padding
+}
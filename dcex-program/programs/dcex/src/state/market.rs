@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use super::FeeTier;
+
 #[account]
 #[derive(Default)]
 pub struct Market {
@@ -19,6 +21,8 @@ pub struct Market {
     pub total_base_deposited: u64,
     pub total_quote_deposited: u64,
     pub bump: u8,
+    /// Share of a referred taker's fee paid to the referrer.
+    pub referral_share_bps: u16,
 }
 
 impl Market {
@@ -39,7 +43,8 @@ impl Market {
         8 +  // total_base_deposited
         8 +  // total_quote_deposited
         1 +  // bump
-        64;  // padding for future fields
+        2 +  // referral_share_bps
+        62;  // padding for future fields
 
     pub fn validate_order_size(&self, size: u64) -> bool {
         size >= self.min_order_size
@@ -49,11 +54,45 @@ impl Market {
         price > 0 && price % self.tick_size == 0
     }
 
-    pub fn calculate_maker_fee(&self, amount: u64) -> Option<u64> {
-        amount.checked_mul(self.maker_fee_bps as u64)?.checked_div(10000)
+    /// Quote value and fees of a fill; see `dcex_pricing` for the units.
+    /// A wallet's fee tier, when given, replaces the market default rate.
+    pub fn fill_amounts(
+        &self,
+        size: u64,
+        price: u64,
+        maker_tier: Option<&FeeTier>,
+        taker_tier: Option<&FeeTier>,
+    ) -> Option<dcex_pricing::FillAmounts> {
+        let maker_fee_bps = match maker_tier {
+            Some(tier) => tier.maker_fee_bps,
+            None => i16::try_from(self.maker_fee_bps).ok()?,
+        };
+        let taker_fee_bps = taker_tier.map_or(self.taker_fee_bps, |tier| tier.taker_fee_bps);
+        dcex_pricing::fill_amounts(size, price, self.base_decimals, maker_fee_bps, taker_fee_bps)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn calculate_taker_fee(&self, amount: u64) -> Option<u64> {
-        amount.checked_mul(self.taker_fee_bps as u64)?.checked_div(10000)
+    #[test]
+    fn test_fill_amounts_match_golden_fills() {
+        for case in dcex_pricing::golden::FILLS {
+            let market = Market {
+                base_decimals: case.base_decimals,
+                ..Market::default()
+            };
+            let maker_tier = FeeTier {
+                maker_fee_bps: case.maker_fee_bps,
+                ..FeeTier::default()
+            };
+            let taker_tier = FeeTier {
+                taker_fee_bps: case.taker_fee_bps,
+                ..FeeTier::default()
+            };
+            let amounts = market.fill_amounts(case.size, case.price, Some(&maker_tier), Some(&taker_tier));
+            assert_eq!(amounts, case.expected, "{}", case.name);
+        }
     }
 }
@@ -1,7 +1,13 @@
 pub mod market;
 pub mod user_vault;
 pub mod order;
+pub mod fee_tier;
+pub mod referral;
+pub mod delegate;
 
 pub use market::*;
 pub use user_vault::*;
 pub use order::*;
+pub use fee_tier::*;
+pub use referral::*;
+pub use delegate::*;
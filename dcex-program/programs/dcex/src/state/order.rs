@@ -18,6 +18,7 @@ pub enum OrderStatus {
     PartiallyFilled,
     Filled,
     Cancelled,
+    Expired,
 }
 
 impl Default for OrderStatus {
@@ -40,6 +41,8 @@ pub struct Order {
     pub created_at: i64,
     pub updated_at: i64,
     pub bump: u8,
+    /// Unix timestamp after which the order can no longer trade; 0 = good-till-cancel.
+    pub expires_at: i64,
 }
 
 impl Order {
@@ -55,7 +58,8 @@ impl Order {
         8 +  // created_at
         8 +  // updated_at
         1 +  // bump
-        32;  // padding
+        8 +  // expires_at
+        24;  // padding
 
     pub fn remaining(&self) -> u64 {
         self.size.saturating_sub(self.filled)
@@ -65,6 +69,10 @@ impl Order {
         matches!(self.status, OrderStatus::Pending | OrderStatus::PartiallyFilled)
     }
 
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at > 0 && now >= self.expires_at
+    }
+
     pub fn fill(&mut self, amount: u64) -> Result<()> {
         self.filled = self.filled.checked_add(amount)
             .ok_or(crate::errors::DcexError::ArithmeticOverflow)?;
@@ -89,7 +97,17 @@ impl Order {
         Ok(())
     }
 
-    pub fn quote_amount(&self) -> Option<u64> {
-        self.size.checked_mul(self.price)?.checked_div(1_000_000_000) // Assuming 9 decimals for price
+    pub fn expire(&mut self) -> Result<()> {
+        require!(
+            self.is_active(),
+            crate::errors::DcexError::InvalidOrderStatus
+        );
+        self.status = OrderStatus::Expired;
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn quote_amount(&self, base_decimals: u8) -> Option<u64> {
+        dcex_pricing::quote_amount(self.size, self.price, base_decimals)
     }
 }
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+/// The referrer a wallet registered, recorded per market by the market
+/// authority so `settle_trade` can pay the referrer's cut.
+#[account]
+#[derive(Default)]
+pub struct Referral {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub referrer: Pubkey,
+    pub bump: u8,
+}
+
+impl Referral {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // market
+        32 + // wallet
+        32 + // referrer
+        1 +  // bump
+        32;  // padding
+}
@@ -0,0 +1,7 @@
+ALTER TABLE orders ADD COLUMN expires_at TIMESTAMPTZ;
+
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check
+    CHECK (status IN ('pending', 'partiallyfilled', 'filled', 'cancelled', 'expired'));
+
+CREATE INDEX idx_orders_expires_at ON orders(expires_at) WHERE expires_at IS NOT NULL;
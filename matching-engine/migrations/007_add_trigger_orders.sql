@@ -0,0 +1,7 @@
+ALTER TABLE orders ADD COLUMN order_type VARCHAR(20) NOT NULL DEFAULT 'limit'
+    CHECK (order_type IN ('limit', 'stopmarket', 'stoplimit', 'takeprofitmarket', 'takeprofitlimit'));
+ALTER TABLE orders ADD COLUMN trigger_price BIGINT;
+ALTER TABLE orders ADD COLUMN triggered_at TIMESTAMPTZ;
+
+CREATE INDEX idx_orders_untriggered ON orders(market_id)
+    WHERE trigger_price IS NOT NULL AND triggered_at IS NULL;
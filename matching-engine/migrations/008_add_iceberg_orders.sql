@@ -0,0 +1 @@
+ALTER TABLE orders ADD COLUMN display_size BIGINT CHECK (display_size > 0);
@@ -0,0 +1,5 @@
+ALTER TABLE markets
+    ADD COLUMN matching_algorithm VARCHAR(20) NOT NULL DEFAULT 'fifo'
+        CHECK (matching_algorithm IN ('fifo', 'prorata', 'hybrid')),
+    ADD COLUMN fifo_slice_bps SMALLINT NOT NULL DEFAULT 0
+        CHECK (fifo_slice_bps BETWEEN 0 AND 10000);
@@ -0,0 +1,26 @@
+ALTER TABLE trades
+    ADD COLUMN taker_side VARCHAR(4) CHECK (taker_side IN ('buy', 'sell')),
+    ADD COLUMN sequence BIGINT,
+    ADD COLUMN settlement_status VARCHAR(10) NOT NULL DEFAULT 'pending'
+        CHECK (settlement_status IN ('pending', 'settled', 'failed'));
+
+UPDATE trades SET settlement_status = 'settled' WHERE settlement_signature IS NOT NULL;
+
+UPDATE trades t
+SET taker_side = o.side
+FROM orders o
+WHERE o.order_id = t.taker_order_id;
+
+UPDATE trades t
+SET sequence = numbered.sequence
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY market_id ORDER BY id) AS sequence
+    FROM trades
+) numbered
+WHERE numbered.id = t.id;
+
+ALTER TABLE trades
+    ALTER COLUMN taker_side SET NOT NULL,
+    ALTER COLUMN sequence SET NOT NULL;
+
+CREATE UNIQUE INDEX idx_trades_market_sequence ON trades(market_id, sequence);
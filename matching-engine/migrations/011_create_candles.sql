@@ -0,0 +1,12 @@
+CREATE TABLE candles (
+    market_id UUID NOT NULL REFERENCES markets(id),
+    timeframe VARCHAR(3) NOT NULL CHECK (timeframe IN ('1m', '5m', '15m', '1h', '4h', '1d')),
+    open_time TIMESTAMPTZ NOT NULL,
+    open BIGINT NOT NULL,
+    high BIGINT NOT NULL,
+    low BIGINT NOT NULL,
+    close BIGINT NOT NULL,
+    volume BIGINT NOT NULL,
+    trade_count BIGINT NOT NULL,
+    PRIMARY KEY (market_id, timeframe, open_time)
+);
@@ -0,0 +1,7 @@
+-- On-chain accounts of each market. NULL for markets that were seeded
+-- directly and have not been imported from chain yet.
+ALTER TABLE markets
+    ADD COLUMN market_address VARCHAR(44) UNIQUE,
+    ADD COLUMN base_vault VARCHAR(44),
+    ADD COLUMN quote_vault VARCHAR(44),
+    ADD COLUMN fee_recipient VARCHAR(44);
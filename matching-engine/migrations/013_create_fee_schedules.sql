@@ -0,0 +1,24 @@
+-- Volume tiers of a market: a wallet pays the rates of the highest tier
+-- whose min_volume (quote atoms traded over 30 days) it has reached.
+-- A negative maker_fee_bps is a rebate.
+CREATE TABLE fee_tiers (
+    market_id UUID NOT NULL REFERENCES markets(id),
+    min_volume BIGINT NOT NULL CHECK (min_volume >= 0),
+    maker_fee_bps SMALLINT NOT NULL,
+    taker_fee_bps SMALLINT NOT NULL CHECK (taker_fee_bps >= 0),
+    PRIMARY KEY (market_id, min_volume)
+);
+
+-- Per-wallet rates, e.g. for market-maker programs. Take precedence over tiers.
+CREATE TABLE fee_overrides (
+    wallet VARCHAR(44) NOT NULL,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    maker_fee_bps SMALLINT NOT NULL,
+    taker_fee_bps SMALLINT NOT NULL CHECK (taker_fee_bps >= 0),
+    label VARCHAR(64),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (wallet, market_id)
+);
+
+CREATE INDEX idx_trades_maker_wallet_time ON trades(maker_wallet, market_id, created_at);
+CREATE INDEX idx_trades_taker_wallet_time ON trades(taker_wallet, market_id, created_at);
@@ -0,0 +1,23 @@
+-- Each wallet may register one referrer, once.
+CREATE TABLE referrals (
+    wallet VARCHAR(44) PRIMARY KEY,
+    referrer VARCHAR(44) NOT NULL CHECK (referrer <> wallet),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_referrals_referrer ON referrals(referrer);
+
+-- Share of a referred taker's fee paid to the referrer, mirrored from chain.
+ALTER TABLE markets ADD COLUMN referral_share_bps SMALLINT NOT NULL DEFAULT 0;
+
+-- The referrer's cut of each trade by a referred taker.
+CREATE TABLE referral_earnings (
+    trade_id BIGINT PRIMARY KEY REFERENCES trades(id),
+    market_id UUID NOT NULL REFERENCES markets(id),
+    referrer VARCHAR(44) NOT NULL,
+    wallet VARCHAR(44) NOT NULL,
+    amount BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_referral_earnings_referrer ON referral_earnings(referrer, market_id);
@@ -0,0 +1,42 @@
+-- Fees collected per market, settlement day (UTC) and liquidity role, in
+-- quote atoms. Maker fees are negative where rebates were paid; referrer_fees
+-- is the part of taker fees paid out to referrers.
+CREATE TABLE fee_ledger (
+    market_id UUID NOT NULL REFERENCES markets(id),
+    day DATE NOT NULL,
+    role VARCHAR(5) NOT NULL CHECK (role IN ('maker', 'taker')),
+    fees BIGINT NOT NULL DEFAULT 0,
+    referrer_fees BIGINT NOT NULL DEFAULT 0,
+    trade_count BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (market_id, day, role)
+);
+
+-- Set once a trade's fees are in the ledger, so a settlement is never
+-- counted twice.
+ALTER TABLE trades ADD COLUMN fees_recorded BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Trades settled before the ledger existed are booked on their trade day.
+INSERT INTO fee_ledger (market_id, day, role, fees, referrer_fees, trade_count)
+SELECT t.market_id, (t.created_at AT TIME ZONE 'UTC')::DATE, r.role,
+    SUM(CASE WHEN r.role = 'maker' THEN t.maker_fee ELSE t.taker_fee END),
+    SUM(CASE WHEN r.role = 'taker' THEN COALESCE(e.amount, 0) ELSE 0 END),
+    COUNT(*)
+FROM trades t
+CROSS JOIN (VALUES ('maker'), ('taker')) AS r(role)
+LEFT JOIN referral_earnings e ON e.trade_id = t.id
+WHERE t.settlement_status = 'settled'
+GROUP BY t.market_id, (t.created_at AT TIME ZONE 'UTC')::DATE, r.role;
+
+UPDATE trades SET fees_recorded = TRUE WHERE settlement_status = 'settled';
+
+-- Fee recipient token balances compared against the ledger.
+CREATE TABLE fee_reconciliations (
+    id BIGSERIAL PRIMARY KEY,
+    fee_recipient VARCHAR(44) NOT NULL,
+    observed_balance BIGINT NOT NULL,
+    ledger_total BIGINT NOT NULL,
+    difference BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_fee_reconciliations_recipient ON fee_reconciliations(fee_recipient, created_at);
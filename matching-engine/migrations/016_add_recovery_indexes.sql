@@ -0,0 +1,7 @@
+-- Open orders are reloaded into the books on startup.
+CREATE INDEX idx_orders_open ON orders(created_at)
+    WHERE status IN ('pending', 'partiallyfilled');
+
+-- The readiness check reports the age of the oldest unsettled trade.
+CREATE INDEX idx_trades_pending_settlement ON trades(created_at)
+    WHERE settlement_status = 'pending';
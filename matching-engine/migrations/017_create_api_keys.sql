@@ -0,0 +1,19 @@
+-- Keys for programmatic trading. The secret is never stored: it is derived
+-- from the key id with the engine's API_KEY_SECRET, and only its SHA-256
+-- is kept to recognise it.
+CREATE TABLE api_keys (
+    key_id VARCHAR(40) PRIMARY KEY,
+    wallet VARCHAR(44) NOT NULL,
+    label VARCHAR(64) NOT NULL,
+    secret_hash CHAR(64) NOT NULL,
+    scopes TEXT[] NOT NULL,
+    -- IPs or CIDR ranges; empty allows any address.
+    ip_allowlist TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    -- Wallet signature that registered the key; each is accepted once.
+    registration_signature VARCHAR(88) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_wallet ON api_keys(wallet);
@@ -0,0 +1,481 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use uuid::Uuid;
+
+use super::csv::{self, ExportFormat};
+use crate::error::{AppError, Result};
+use crate::settlement::solana::{market_address, InitializeMarketParams};
+use crate::types::{
+    FeeOverride, FeeReconciliation, FeeTier, Market, MatchingAlgorithm, OnChainMarket, RevenueTotals,
+};
+use crate::AppState;
+use crate::db;
+
+// Mirrors the program's MAX_MAKER_FEE_BPS / MAX_TAKER_FEE_BPS.
+const MAX_FEE_BPS: u16 = 100;
+// Mirrors the program's MAX_MAKER_REBATE_BPS.
+const MAX_MAKER_REBATE_BPS: u16 = 100;
+const MAX_FEE_TIERS: usize = 32;
+// Mirrors the program's MAX_REFERRAL_SHARE_BPS.
+const MAX_REFERRAL_SHARE_BPS: u16 = 5_000;
+
+/// Admin routes require `Authorization: Bearer <ADMIN_TOKEN>` and are closed
+/// when no token is configured.
+pub async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(AppError::Unauthorized);
+    };
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(AppError::Unauthorized),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+pub struct CreateMarketRequest {
+    pub base_mint: String,
+    pub quote_mint: String,
+    /// Quote token account that receives trading fees.
+    pub fee_recipient: String,
+    pub min_order_size: u64,
+    pub tick_size: u64,
+    pub maker_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    #[serde(default)]
+    pub matching_algorithm: MatchingAlgorithm,
+    #[serde(default)]
+    pub fifo_slice_bps: i16,
+}
+
+#[derive(Serialize)]
+pub struct CreateMarketResponse {
+    pub market: Market,
+    pub signature: String,
+}
+
+/// Submits `initialize_market` and records the market as read back from chain.
+pub async fn create_market(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateMarketRequest>,
+) -> Result<Json<CreateMarketResponse>> {
+    if req.maker_fee_bps > MAX_FEE_BPS || req.taker_fee_bps > MAX_FEE_BPS {
+        return Err(AppError::InvalidRequest(format!("Fees are capped at {} bps", MAX_FEE_BPS)));
+    }
+    if req.min_order_size == 0 || req.tick_size == 0 {
+        return Err(AppError::InvalidRequest(
+            "Minimum order size and tick size must be positive".to_string(),
+        ));
+    }
+    validate_fifo_slice(req.fifo_slice_bps)?;
+
+    let base_mint = parse_pubkey("base_mint", &req.base_mint)?;
+    let quote_mint = parse_pubkey("quote_mint", &req.quote_mint)?;
+    let fee_recipient = parse_pubkey("fee_recipient", &req.fee_recipient)?;
+    if base_mint == quote_mint {
+        return Err(AppError::InvalidRequest("Base and quote mints must differ".to_string()));
+    }
+
+    let solana = state.settlement_queue.solana_client();
+    if solana.authority_pubkey().is_none() {
+        return Err(AppError::AuthorityUnavailable);
+    }
+    let params = InitializeMarketParams {
+        min_order_size: req.min_order_size,
+        tick_size: req.tick_size,
+        maker_fee_bps: req.maker_fee_bps,
+        taker_fee_bps: req.taker_fee_bps,
+    };
+    let (signature, address) = solana
+        .initialize_market(&base_mint, &quote_mint, &fee_recipient, &params)
+        .await?;
+    tracing::info!("Initialized market {} in {}", address, signature);
+
+    let on_chain = solana.fetch_market(&address).await?;
+    let market = db::upsert_market(
+        &state.db_pool,
+        &on_chain,
+        req.matching_algorithm,
+        req.fifo_slice_bps,
+    ).await?;
+
+    Ok(Json(CreateMarketResponse { market, signature }))
+}
+
+#[derive(Deserialize)]
+pub struct ImportMarketRequest {
+    pub market_address: String,
+    #[serde(default)]
+    pub matching_algorithm: MatchingAlgorithm,
+    #[serde(default)]
+    pub fifo_slice_bps: i16,
+}
+
+/// Records an existing on-chain market, or refreshes its row from chain.
+pub async fn import_market(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ImportMarketRequest>,
+) -> Result<Json<Market>> {
+    validate_fifo_slice(req.fifo_slice_bps)?;
+    let address = parse_pubkey("market_address", &req.market_address)?;
+
+    let solana = state.settlement_queue.solana_client();
+    let authority = solana.authority_pubkey().ok_or(AppError::AuthorityUnavailable)?;
+    let on_chain = solana.fetch_market(&address).await?;
+    verify_market_address(solana.program_id(), &address, &on_chain)?;
+    verify_market_authority(&authority, &on_chain)?;
+
+    let market = db::upsert_market(
+        &state.db_pool,
+        &on_chain,
+        req.matching_algorithm,
+        req.fifo_slice_bps,
+    ).await?;
+
+    Ok(Json(market))
+}
+
+#[derive(Deserialize)]
+pub struct SetReferralShareRequest {
+    pub referral_share_bps: u16,
+}
+
+/// Sets on chain the share of referred takers' fees paid to referrers, then
+/// refreshes the market row from chain.
+pub async fn set_referral_share(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+    Json(req): Json<SetReferralShareRequest>,
+) -> Result<Json<Market>> {
+    if req.referral_share_bps > MAX_REFERRAL_SHARE_BPS {
+        return Err(AppError::InvalidRequest(format!(
+            "Referral share is capped at {} bps",
+            MAX_REFERRAL_SHARE_BPS
+        )));
+    }
+    let market = db::get_market(&state.db_pool, market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+    let address = market.market_address.as_deref().ok_or_else(|| {
+        AppError::InvalidRequest("Market has not been imported from chain".to_string())
+    })?;
+    let address = parse_pubkey("market_address", address)?;
+
+    let solana = state.settlement_queue.solana_client();
+    let signature = solana.set_referral_share(&address, req.referral_share_bps).await?;
+    tracing::info!("Set referral share of {} in {}", address, signature);
+
+    let on_chain = solana.fetch_market(&address).await?;
+    let market = db::upsert_market(
+        &state.db_pool,
+        &on_chain,
+        market.matching_algorithm,
+        market.fifo_slice_bps,
+    ).await?;
+    state.fee_manager.invalidate_market(market_id).await;
+    Ok(Json(market))
+}
+
+pub async fn get_fee_tiers(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<Vec<FeeTier>>> {
+    let tiers = db::get_fee_tiers(&state.db_pool, market_id).await?;
+    Ok(Json(tiers))
+}
+
+/// Replaces the market's volume tier schedule. An empty list leaves every
+/// wallet without an override on the market default rates.
+pub async fn set_fee_tiers(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+    Json(tiers): Json<Vec<FeeTier>>,
+) -> Result<Json<Vec<FeeTier>>> {
+    db::get_market(&state.db_pool, market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+    if tiers.len() > MAX_FEE_TIERS {
+        return Err(AppError::InvalidRequest(format!("At most {} fee tiers", MAX_FEE_TIERS)));
+    }
+    for (i, tier) in tiers.iter().enumerate() {
+        if tier.min_volume < 0 {
+            return Err(AppError::InvalidRequest("min_volume must not be negative".to_string()));
+        }
+        if tiers[..i].iter().any(|other| other.min_volume == tier.min_volume) {
+            return Err(AppError::InvalidRequest(format!("Duplicate tier at {}", tier.min_volume)));
+        }
+        validate_fee_rates(tier.maker_fee_bps, tier.taker_fee_bps)?;
+    }
+
+    let tiers = db::replace_fee_tiers(&state.db_pool, market_id, &tiers).await?;
+    state.fee_manager.invalidate_market(market_id).await;
+    Ok(Json(tiers))
+}
+
+#[derive(Deserialize)]
+pub struct FeeOverridesQuery {
+    pub market_id: Option<Uuid>,
+}
+
+pub async fn get_fee_overrides(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FeeOverridesQuery>,
+) -> Result<Json<Vec<FeeOverride>>> {
+    let overrides = db::get_fee_overrides(&state.db_pool, query.market_id).await?;
+    Ok(Json(overrides))
+}
+
+#[derive(Deserialize)]
+pub struct SetFeeOverrideRequest {
+    pub wallet: String,
+    pub market_id: Uuid,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    /// Why the wallet has its own rates, e.g. the market-maker program.
+    pub label: Option<String>,
+}
+
+pub async fn set_fee_override(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetFeeOverrideRequest>,
+) -> Result<Json<FeeOverride>> {
+    parse_pubkey("wallet", &req.wallet)?;
+    validate_fee_rates(req.maker_fee_bps, req.taker_fee_bps)?;
+    db::get_market(&state.db_pool, req.market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+
+    let fee_override = db::upsert_fee_override(
+        &state.db_pool,
+        &req.wallet,
+        req.market_id,
+        req.maker_fee_bps,
+        req.taker_fee_bps,
+        req.label.as_deref(),
+    ).await?;
+    state.fee_manager.invalidate_market(req.market_id).await;
+    Ok(Json(fee_override))
+}
+
+pub async fn delete_fee_override(
+    State(state): State<Arc<AppState>>,
+    Path((market_id, wallet)): Path<(Uuid, String)>,
+) -> Result<Json<FeeOverride>> {
+    let fee_override = db::delete_fee_override(&state.db_pool, &wallet, market_id)
+        .await?
+        .ok_or_else(|| AppError::InvalidRequest(format!("No fee override for {}", wallet)))?;
+    state.fee_manager.invalidate_market(market_id).await;
+    Ok(Json(fee_override))
+}
+
+/// Rates must be accepted by the program's `set_fee_tier`; a negative maker
+/// rate is a rebate.
+fn validate_fee_rates(maker_fee_bps: i16, taker_fee_bps: i16) -> Result<()> {
+    let maker_limit = if maker_fee_bps < 0 { MAX_MAKER_REBATE_BPS } else { MAX_FEE_BPS };
+    if maker_fee_bps.unsigned_abs() > maker_limit {
+        return Err(AppError::InvalidRequest(format!(
+            "maker_fee_bps must be between -{} and {}",
+            MAX_MAKER_REBATE_BPS, MAX_FEE_BPS
+        )));
+    }
+    if !(0..=MAX_FEE_BPS as i16).contains(&taker_fee_bps) {
+        return Err(AppError::InvalidRequest(format!(
+            "taker_fee_bps must be between 0 and {}",
+            MAX_FEE_BPS
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct RevenueQuery {
+    pub market_id: Option<Uuid>,
+    /// First UTC day included.
+    pub from: Option<NaiveDate>,
+    /// Last UTC day included.
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+impl RevenueQuery {
+    fn validate(&self) -> Result<()> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(AppError::InvalidRequest("`from` must not be after `to`".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fee ledger by day, market and role. `format=csv` returns the same
+/// rows as a CSV download.
+pub async fn get_fee_ledger(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RevenueQuery>,
+) -> Result<Response> {
+    query.validate()?;
+    let entries = db::get_fee_ledger(&state.db_pool, query.market_id, query.from, query.to).await?;
+
+    Ok(match query.format {
+        ExportFormat::Json => Json(entries).into_response(),
+        ExportFormat::Csv => csv::attachment("fee-ledger.csv", &entries),
+    })
+}
+
+/// Protocol revenue per market over the requested days.
+pub async fn get_revenue_totals(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RevenueQuery>,
+) -> Result<Json<Vec<RevenueTotals>>> {
+    query.validate()?;
+    let totals = db::get_revenue_totals(&state.db_pool, query.market_id, query.from, query.to).await?;
+    Ok(Json(totals))
+}
+
+/// Compares every fee recipient's token balance with the net fees booked for
+/// its markets and records the result.
+pub async fn reconcile_fees(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<FeeReconciliation>>> {
+    let solana = state.settlement_queue.solana_client();
+    let mut reconciliations = Vec::new();
+
+    for fee_recipient in db::get_fee_recipients(&state.db_pool).await? {
+        let address = parse_pubkey("fee_recipient", &fee_recipient)?;
+        let balance = solana.token_balance(&address).await?;
+        let observed_balance = i64::try_from(balance).map_err(|_| {
+            AppError::Internal(anyhow::anyhow!("Balance of {} is out of range", fee_recipient))
+        })?;
+        let ledger_total = db::get_ledger_total(&state.db_pool, &fee_recipient).await?;
+
+        let reconciliation = db::create_fee_reconciliation(
+            &state.db_pool,
+            &fee_recipient,
+            observed_balance,
+            ledger_total,
+        ).await?;
+        if reconciliation.difference != 0 {
+            tracing::warn!(
+                "Fee recipient {} holds {} but the ledger booked {}",
+                fee_recipient, observed_balance, ledger_total
+            );
+        }
+        reconciliations.push(reconciliation);
+    }
+
+    Ok(Json(reconciliations))
+}
+
+/// The engine finds markets by their mints, so only the canonical PDA for a
+/// mint pair may be imported.
+fn verify_market_address(program_id: &Pubkey, address: &Pubkey, on_chain: &OnChainMarket) -> Result<()> {
+    let base_mint = parse_pubkey("base_mint", &on_chain.base_mint)?;
+    let quote_mint = parse_pubkey("quote_mint", &on_chain.quote_mint)?;
+    if market_address(program_id, &base_mint, &quote_mint) != *address {
+        return Err(AppError::InvalidRequest(format!(
+            "{} is not the market PDA for {}/{}",
+            address, on_chain.base_mint, on_chain.quote_mint
+        )));
+    }
+    Ok(())
+}
+
+/// Only the engine's authority can settle, cancel and configure a market, so
+/// markets under another authority cannot be traded here.
+fn verify_market_authority(authority: &Pubkey, on_chain: &OnChainMarket) -> Result<()> {
+    if on_chain.authority != authority.to_string() {
+        return Err(AppError::InvalidRequest(format!(
+            "Market {} is controlled by {}, not the engine authority {}",
+            on_chain.address, on_chain.authority, authority
+        )));
+    }
+    Ok(())
+}
+
+fn validate_fifo_slice(fifo_slice_bps: i16) -> Result<()> {
+    if !(0..=10_000).contains(&fifo_slice_bps) {
+        return Err(AppError::InvalidRequest(
+            "fifo_slice_bps must be between 0 and 10000".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_pubkey(field: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value)
+        .map_err(|_| AppError::InvalidRequest(format!("Invalid {} '{}'", field, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_validate_fee_rates() {
+        assert!(validate_fee_rates(-100, 0).is_ok());
+        assert!(validate_fee_rates(100, 100).is_ok());
+        assert!(validate_fee_rates(-101, 10).is_err());
+        assert!(validate_fee_rates(5, -1).is_err());
+        assert!(validate_fee_rates(5, 101).is_err());
+    }
+
+    #[test]
+    fn test_verify_market_authority() {
+        let authority = Pubkey::new_unique();
+        let mut on_chain = OnChainMarket {
+            address: Pubkey::new_unique().to_string(),
+            authority: authority.to_string(),
+            base_mint: Pubkey::new_unique().to_string(),
+            quote_mint: Pubkey::new_unique().to_string(),
+            base_vault: Pubkey::new_unique().to_string(),
+            quote_vault: Pubkey::new_unique().to_string(),
+            base_decimals: 9,
+            quote_decimals: 6,
+            min_order_size: 1,
+            tick_size: 1,
+            maker_fee_bps: 0,
+            taker_fee_bps: 0,
+            fee_recipient: Pubkey::new_unique().to_string(),
+            is_active: true,
+            referral_share_bps: 0,
+        };
+
+        assert!(verify_market_authority(&authority, &on_chain).is_ok());
+        on_chain.authority = Pubkey::new_unique().to_string();
+        assert!(verify_market_authority(&authority, &on_chain).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Path, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::signatures::SignedRequest;
+use crate::auth;
+use crate::error::{AppError, Result};
+use crate::ratelimit::Budget;
+use crate::types::{ApiKey, CreateApiKeyRequest, CreatedApiKey, RevokeApiKeyRequest};
+use crate::AppState;
+use crate::db;
+
+pub const KEY_HEADER: &str = "x-dcex-key";
+pub const TIMESTAMP_HEADER: &str = "x-dcex-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-dcex-signature";
+
+const MAX_ACTIVE_KEYS: usize = 20;
+const MAX_LABEL_LEN: usize = 64;
+const MAX_ALLOWLIST_ENTRIES: usize = 20;
+/// Signed request bodies are buffered; orders are far smaller than this.
+pub(super) const MAX_SIGNED_BODY: usize = 64 * 1024;
+
+/// The API key a request was signed with, for handlers to check scopes and
+/// wallet against.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedKey(pub ApiKey);
+
+/// Authenticates requests carrying API key headers and attaches the key.
+/// Requests without them pass through unchanged; trading routes then need a
+/// delegate or wallet signature instead.
+pub async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if !request.headers().contains_key(KEY_HEADER) {
+        return Ok(next.run(request).await);
+    }
+    let request = verify_request(&state, request).await?;
+    Ok(next.run(request).await)
+}
+
+/// Charges the wallet of the request's API key against `budget`, so a
+/// wallet's keys share one budget whichever addresses they call from. Runs
+/// inside `authenticate`; keyless requests only pay the IP budget.
+pub async fn limit_wallet(
+    State((state, budget)): State<(Arc<AppState>, Budget)>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if let Some(AuthenticatedKey(key)) = request.extensions().get::<AuthenticatedKey>() {
+        state.rate_limiter.check_wallet(budget, &key.wallet).await?;
+    }
+    Ok(next.run(request).await)
+}
+
+async fn verify_request(state: &AppState, request: Request) -> Result<Request> {
+    let master_secret = state.api_key_secret
+        .as_deref()
+        .ok_or(AppError::InvalidApiKey("API keys are not enabled"))?;
+    let headers = request.headers();
+    let key_id = header(headers, KEY_HEADER)?.to_string();
+    let timestamp = header(headers, TIMESTAMP_HEADER)?.to_string();
+    let signature = header(headers, SIGNATURE_HEADER)?.to_string();
+
+    let timestamp_ms: i64 = timestamp
+        .parse()
+        .map_err(|_| AppError::InvalidApiKey("Invalid timestamp"))?;
+    if !auth::is_fresh(timestamp_ms, Utc::now().timestamp_millis()) {
+        return Err(AppError::InvalidApiKey("Timestamp outside the allowed window"));
+    }
+
+    let key = db::get_api_key(&state.db_pool, &key_id)
+        .await?
+        .ok_or(AppError::InvalidApiKey("Unknown API key"))?;
+    if !key.is_active(Utc::now()) {
+        return Err(AppError::InvalidApiKey("API key is revoked or expired"));
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer);
+    let ip = state.rate_limiter.client_ip(headers, peer);
+    let allowed = match ip {
+        Some(ip) => auth::ip_allowed(&key.ip_allowlist, ip),
+        None => key.ip_allowlist.is_empty(),
+    };
+    if !allowed {
+        return Err(AppError::InvalidApiKey("Address not in the key's IP allowlist"));
+    }
+
+    // Keys issued under a different master secret no longer verify.
+    let secret = auth::api_key_secret(master_secret, &key.key_id);
+    if auth::secret_hash(&secret) != key.secret_hash {
+        return Err(AppError::InvalidApiKey("API key is no longer valid"));
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_SIGNED_BODY)
+        .await
+        .map_err(|_| AppError::InvalidRequest("Request body too large".to_string()))?;
+    let path_and_query = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+    if !auth::verify_request_signature(
+        &secret,
+        &timestamp,
+        parts.method.as_str(),
+        path_and_query,
+        &body,
+        &signature,
+    ) {
+        return Err(AppError::InvalidApiKey("Invalid request signature"));
+    }
+
+    let mut request = Request::from_parts(parts, Body::from(body));
+    request.extensions_mut().insert(SignedRequest {
+        signer: key.key_id.clone(),
+        timestamp,
+        signature,
+    });
+    request.extensions_mut().insert(AuthenticatedKey(key));
+    Ok(request)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::InvalidApiKey("Missing API key headers"))
+}
+
+/// Creates an API key for a wallet that signed `auth::api_key_message`.
+/// The secret is only returned here.
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKey>> {
+    let master_secret = state.api_key_secret
+        .as_deref()
+        .ok_or_else(|| AppError::InvalidRequest("API keys are not enabled".to_string()))?;
+
+    let label = req.label.trim();
+    if label.is_empty() || label.len() > MAX_LABEL_LEN {
+        return Err(AppError::InvalidRequest(format!(
+            "Label must be 1 to {} characters",
+            MAX_LABEL_LEN
+        )));
+    }
+    if req.scopes.is_empty() {
+        return Err(AppError::InvalidRequest("At least one scope is required".to_string()));
+    }
+    if req.scopes.iter().collect::<HashSet<_>>().len() != req.scopes.len() {
+        return Err(AppError::InvalidRequest("Duplicate scope".to_string()));
+    }
+    if req.ip_allowlist.len() > MAX_ALLOWLIST_ENTRIES {
+        return Err(AppError::InvalidRequest(format!(
+            "At most {} IP allowlist entries",
+            MAX_ALLOWLIST_ENTRIES
+        )));
+    }
+    if let Some(entry) = req.ip_allowlist.iter().find(|entry| auth::parse_ip_range(entry).is_none()) {
+        return Err(AppError::InvalidRequest(format!("Invalid IP allowlist entry '{}'", entry)));
+    }
+    if req.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AppError::InvalidRequest("Expiry must be in the future".to_string()));
+    }
+    if !auth::is_fresh(req.timestamp, Utc::now().timestamp_millis()) {
+        return Err(AppError::InvalidRequest("Timestamp outside the allowed window".to_string()));
+    }
+
+    let message = auth::api_key_message(
+        &req.wallet,
+        &req.label,
+        &req.scopes,
+        &req.ip_allowlist,
+        req.expires_at,
+        req.timestamp,
+    );
+    if !auth::verify_wallet_signature(&req.wallet, &message, &req.signature) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let active = db::get_wallet_api_keys(&state.db_pool, &req.wallet)
+        .await?
+        .iter()
+        .filter(|key| key.is_active(Utc::now()))
+        .count();
+    if active >= MAX_ACTIVE_KEYS {
+        return Err(AppError::InvalidRequest(format!(
+            "A wallet can have at most {} active API keys",
+            MAX_ACTIVE_KEYS
+        )));
+    }
+
+    let key_id = format!("dk_{}", Uuid::new_v4().simple());
+    let secret = auth::api_key_secret(master_secret, &key_id);
+    let key = db::create_api_key(
+        &state.db_pool,
+        &key_id,
+        &req.wallet,
+        label,
+        &auth::secret_hash(&secret),
+        &req.scopes,
+        &req.ip_allowlist,
+        req.expires_at,
+        &req.signature,
+    ).await?;
+
+    Ok(Json(CreatedApiKey { key, secret }))
+}
+
+pub async fn get_wallet_api_keys(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+) -> Result<Json<Vec<ApiKey>>> {
+    let keys = db::get_wallet_api_keys(&state.db_pool, &wallet).await?;
+    Ok(Json(keys))
+}
+
+/// Revokes a key; the owning wallet signs `auth::revoke_api_key_message`.
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    Path(key_id): Path<String>,
+    Json(req): Json<RevokeApiKeyRequest>,
+) -> Result<Json<ApiKey>> {
+    if !auth::is_fresh(req.timestamp, Utc::now().timestamp_millis()) {
+        return Err(AppError::InvalidRequest("Timestamp outside the allowed window".to_string()));
+    }
+    let message = auth::revoke_api_key_message(&req.wallet, &key_id, req.timestamp);
+    if !auth::verify_wallet_signature(&req.wallet, &message, &req.signature) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let key = db::revoke_api_key(&state.db_pool, &req.wallet, &key_id)
+        .await?
+        .ok_or_else(|| AppError::InvalidRequest(format!("No API key {} for {}", key_id, req.wallet)))?;
+    Ok(Json(key))
+}
@@ -0,0 +1,172 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::types::{FeeLedgerEntry, Fill, LiquidityRole, OrderSide, SettlementStatus};
+
+/// `format` query parameter of endpoints that can also export CSV.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// A record that can be exported as one CSV row.
+pub trait CsvRow {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+pub fn render<T: CsvRow>(rows: &[T]) -> String {
+    let mut out = String::new();
+    write_line(&mut out, T::HEADER.iter().map(|name| name.to_string()));
+    for row in rows {
+        write_line(&mut out, row.fields());
+    }
+    out
+}
+
+/// Serves `rows` as a CSV download named `filename`.
+pub fn attachment<T: CsvRow>(filename: &str, rows: &[T]) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        render(rows),
+    )
+        .into_response()
+}
+
+fn write_line(out: &mut String, fields: impl IntoIterator<Item = String>) {
+    let escaped: Vec<String> = fields.into_iter().map(|field| escape(&field)).collect();
+    out.push_str(&escaped.join(","));
+    out.push_str("\r\n");
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl CsvRow for Fill {
+    const HEADER: &'static [&'static str] = &[
+        "trade_id",
+        "created_at",
+        "market_id",
+        "order_id",
+        "side",
+        "role",
+        "price",
+        "size",
+        "fee",
+        "settlement_status",
+        "settlement_signature",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let side = match self.side {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        };
+        let role = role_name(self.role);
+        let settlement_status = match self.settlement_status {
+            SettlementStatus::Pending => "pending",
+            SettlementStatus::Settled => "settled",
+            SettlementStatus::Failed => "failed",
+        };
+
+        vec![
+            self.trade_id.to_string(),
+            self.created_at.to_rfc3339(),
+            self.market_id.to_string(),
+            self.order_id.clone(),
+            side.to_string(),
+            role.to_string(),
+            self.price.to_string(),
+            self.size.to_string(),
+            self.fee.to_string(),
+            settlement_status.to_string(),
+            self.settlement_signature.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRow for FeeLedgerEntry {
+    const HEADER: &'static [&'static str] = &[
+        "day",
+        "market_id",
+        "role",
+        "fees",
+        "referrer_fees",
+        "net_fees",
+        "trade_count",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.day.to_string(),
+            self.market_id.to_string(),
+            role_name(self.role).to_string(),
+            self.fees.to_string(),
+            self.referrer_fees.to_string(),
+            self.net_fees.to_string(),
+            self.trade_count.to_string(),
+        ]
+    }
+}
+
+fn role_name(role: LiquidityRole) -> &'static str {
+    match role {
+        LiquidityRole::Maker => "maker",
+        LiquidityRole::Taker => "taker",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row(&'static str, &'static str);
+
+    impl CsvRow for Row {
+        const HEADER: &'static [&'static str] = &["a", "b"];
+
+        fn fields(&self) -> Vec<String> {
+            vec![self.0.to_string(), self.1.to_string()]
+        }
+    }
+
+    #[test]
+    fn test_render_escapes_special_characters() {
+        let csv = render(&[Row("plain", "with,comma"), Row("say \"hi\"", "line\nbreak")]);
+        assert_eq!(
+            csv,
+            "a,b\r\nplain,\"with,comma\"\r\n\"say \"\"hi\"\"\",\"line\nbreak\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_fee_ledger_rows_match_header() {
+        let entry = FeeLedgerEntry {
+            market_id: uuid::Uuid::nil(),
+            day: chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            role: LiquidityRole::Maker,
+            fees: -75_000,
+            referrer_fees: 0,
+            net_fees: -75_000,
+            trade_count: 3,
+        };
+        let fields = entry.fields();
+        assert_eq!(fields.len(), FeeLedgerEntry::HEADER.len());
+        assert_eq!(fields[..3], ["2024-03-01", "00000000-0000-0000-0000-000000000000", "maker"]);
+    }
+}
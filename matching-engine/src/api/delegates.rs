@@ -0,0 +1,182 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use solana_sdk::pubkey::Pubkey;
+use uuid::Uuid;
+
+use super::api_keys::KEY_HEADER;
+use super::signatures;
+use crate::error::{AppError, Result};
+use crate::types::{Market, OnChainDelegate};
+use crate::AppState;
+use crate::db;
+
+/// Session key that signed the request, in place of the wallet.
+pub const DELEGATE_HEADER: &str = "x-dcex-delegate";
+
+/// The session key a request was signed with. Handlers still have to check
+/// it is a delegate of the wallet they act for, with `authorize`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedDelegate(pub String);
+
+/// Verifies requests signed by a delegate's session key and attaches it.
+/// Requests without the delegate header pass through unchanged.
+pub async fn authenticate(request: Request, next: Next) -> Result<Response> {
+    if !request.headers().contains_key(DELEGATE_HEADER) {
+        return Ok(next.run(request).await);
+    }
+    if request.headers().contains_key(KEY_HEADER) {
+        return Err(AppError::InvalidRequest(
+            "Sign with either an API key or a delegate, not both".to_string(),
+        ));
+    }
+    let (mut request, delegate) = signatures::verify_ed25519(request, DELEGATE_HEADER).await?;
+    request.extensions_mut().insert(AuthenticatedDelegate(delegate));
+    Ok(next.run(request).await)
+}
+
+/// The delegate `wallet` authorized on the market, read from chain.
+pub async fn get_delegate(
+    State(state): State<Arc<AppState>>,
+    Path((market_id, wallet)): Path<(Uuid, String)>,
+) -> Result<Json<Option<OnChainDelegate>>> {
+    let market = db::get_market(&state.db_pool, market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+    Ok(Json(fetch(&state, &market, &wallet).await?))
+}
+
+async fn fetch(state: &AppState, market: &Market, wallet: &str) -> Result<Option<OnChainDelegate>> {
+    let market_address = market
+        .market_address
+        .as_deref()
+        .ok_or_else(|| AppError::InvalidRequest("Market is not on chain".to_string()))?;
+    let market_address = Pubkey::from_str(market_address).map_err(anyhow::Error::from)?;
+    let wallet = Pubkey::from_str(wallet)
+        .map_err(|_| AppError::InvalidRequest(format!("Invalid wallet {}", wallet)))?;
+
+    let delegate = state.settlement_queue
+        .solana_client()
+        .fetch_delegate(&market_address, &wallet)
+        .await?;
+    Ok(delegate)
+}
+
+/// Checks on chain that `delegate` may act for `wallet` on `market`. Places
+/// pass the order's quote value so it can be held to the notional limits;
+/// cancels pass `None`.
+pub async fn authorize(
+    state: &AppState,
+    delegate: &str,
+    market: &Market,
+    wallet: &str,
+    notional: Option<u64>,
+) -> Result<()> {
+    let on_chain = fetch(state, market, wallet).await?;
+    check(on_chain.as_ref(), delegate, Utc::now(), notional)
+}
+
+fn check(
+    on_chain: Option<&OnChainDelegate>,
+    delegate: &str,
+    now: DateTime<Utc>,
+    notional: Option<u64>,
+) -> Result<()> {
+    let on_chain = on_chain
+        .filter(|on_chain| on_chain.delegate == delegate)
+        .ok_or_else(|| AppError::InvalidDelegate(format!("{} is not a delegate of this wallet", delegate)))?;
+    if on_chain.is_expired(now) {
+        return Err(AppError::InvalidDelegate("Delegate has expired".to_string()));
+    }
+    if notional.is_some_and(|notional| !on_chain.allows_notional(notional)) {
+        return Err(AppError::InvalidDelegate(
+            "Order exceeds the delegate's notional limit".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware, routing::delete, Extension, Router};
+    use solana_sdk::signature::{Keypair, Signer};
+    use tower::ServiceExt;
+    use super::super::api_keys::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+    fn delegate() -> OnChainDelegate {
+        OnChainDelegate {
+            wallet: "wallet".to_string(),
+            delegate: "session".to_string(),
+            expires_at: 1_700_000_000,
+            max_order_notional: 500,
+            max_total_notional: 1_000,
+            notional_used: 600,
+        }
+    }
+
+    #[test]
+    fn test_check_delegate() {
+        let on_chain = delegate();
+        let before = DateTime::from_timestamp(1_699_999_999, 0).unwrap();
+        let after = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        assert!(check(Some(&on_chain), "session", before, Some(400)).is_ok());
+        assert!(check(Some(&on_chain), "session", before, None).is_ok());
+        assert!(check(None, "session", before, None).is_err());
+        assert!(check(Some(&on_chain), "other", before, None).is_err());
+        assert!(check(Some(&on_chain), "session", after, None).is_err());
+        // Over the per-order cap, then over what is left of the total.
+        assert!(check(Some(&on_chain), "session", before, Some(501)).is_err());
+        assert!(check(Some(&on_chain), "session", before, Some(401)).is_err());
+    }
+
+    #[test]
+    fn test_zero_limits_are_unlimited() {
+        let on_chain = OnChainDelegate {
+            expires_at: 0,
+            max_order_notional: 0,
+            max_total_notional: 0,
+            ..delegate()
+        };
+        let now = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+
+        assert!(check(Some(&on_chain), "session", now, Some(u64::MAX)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delegate_signed_request_is_authenticated() {
+        async fn session_key(Extension(AuthenticatedDelegate(delegate)): Extension<AuthenticatedDelegate>) -> String {
+            delegate
+        }
+        let app = Router::new()
+            .route("/api/orders/:order_id", delete(session_key))
+            .route_layer(middleware::from_fn(signatures::require_signed))
+            .route_layer(middleware::from_fn(authenticate));
+
+        let session = Keypair::new();
+        let timestamp = Utc::now().timestamp_millis().to_string();
+        let message = format!("{}DELETE/api/orders/42", timestamp);
+        let request = || {
+            Request::delete("/api/orders/42")
+                .header(DELEGATE_HEADER, session.pubkey().to_string())
+                .header(TIMESTAMP_HEADER, &timestamp)
+                .header(SIGNATURE_HEADER, session.sign_message(message.as_bytes()).to_string())
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut both = request();
+        both.headers_mut().insert(KEY_HEADER, "dk_1".parse().unwrap());
+        assert_eq!(app.oneshot(both).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::types::{CancelReason, OrderStatus};
+use crate::AppState;
+use crate::db;
+use super::handlers;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Background task that pulls good-till-time orders from the book once their
+/// deadline passes and marks them `Expired`.
+pub async fn run_expiry_sweeper(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let expired = state
+            .orderbook_manager
+            .write()
+            .await
+            .take_expired(chrono::Utc::now());
+
+        for entry in expired {
+            let order = match db::get_order(&state.db_pool, &entry.order_id).await {
+                Ok(Some(order)) => order,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::error!("Failed to load expiring order {}: {:?}", entry.order_id, e);
+                    continue;
+                }
+            };
+
+            if !matches!(order.status, OrderStatus::Pending | OrderStatus::PartiallyFilled) {
+                continue;
+            }
+
+            match handlers::cancel_open_order(&state, order, CancelReason::Expired).await {
+                Ok(_) => tracing::info!("Order {} expired", entry.order_id),
+                Err(e) => tracing::error!("Failed to expire order {}: {}", entry.order_id, e),
+            }
+        }
+    }
+}
@@ -1,19 +1,33 @@
 use std::sync::Arc;
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 use uuid::Uuid;
 
+use super::api_keys::AuthenticatedKey;
+use super::delegates::{self, AuthenticatedDelegate};
+use super::csv::{self, ExportFormat};
+use super::signatures::AuthenticatedWallet;
+use crate::auth;
 use crate::error::{AppError, Result};
 use crate::types::{
-    Market, Order, OrderSide, OrderStatus, OrderbookSnapshot, PlaceOrderRequest, Trade,
+    BalanceChange, BalanceChangeReason, CancelReason, Candle, CandleInterval, Fill, LiquidityRole,
+    Market, Order, Referral, ReferralSummary, RegisterReferralRequest, WalletFees, OrderSide, OrderStatus, OrderbookSnapshot, PageParams, PlaceOrderRequest, Ticker, Trade,
     Deposit, Withdrawal, DepositRequest, WithdrawalRequest,
 };
-use crate::orderbook::{MatchingEngine, MatchResult};
-use crate::settlement::SettlementTask;
+use crate::metrics::METRICS;
+use crate::ratelimit::Budget;
+use crate::orderbook::{Allocation, Execution, MatchingEngine, TriggerDirection};
+use crate::settlement::{self, SettlementTask, UnlockTask};
 use crate::AppState;
+use crate::websocket::WsStats;
 use crate::db;
 
 #[derive(Serialize)]
@@ -22,6 +36,7 @@ pub struct HealthResponse {
     pub version: String,
 }
 
+/// Liveness: the process is up and serving requests.
 pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "healthy".to_string(),
@@ -29,6 +44,42 @@ pub async fn health_check() -> Json<HealthResponse> {
     })
 }
 
+/// Readiness: every dependency is reachable and the books are recovered.
+/// Responds 503 with the same report otherwise.
+pub async fn readiness(
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let report = state.health.check(&state).await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// Prometheus scrape endpoint. Book, queue and pool gauges are sampled here
+/// rather than on every change.
+pub async fn metrics(
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    METRICS.observe_books(&*state.orderbook_manager.read().await);
+    METRICS.observe_db_pool(&state.db_pool);
+    METRICS.settlement_queue_depth.set(state.settlement_queue.queue_depth() as i64);
+
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        METRICS.render(),
+    )
+        .into_response()
+}
+
+pub async fn get_ws_stats(
+    State(state): State<Arc<AppState>>,
+) -> Json<WsStats> {
+    Json(state.ws_manager.stats().await)
+}
+
 pub async fn get_markets(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<Market>>> {
@@ -64,6 +115,7 @@ pub async fn get_orderbook(
         .map(|ob| ob.snapshot(depth))
         .unwrap_or_else(|| OrderbookSnapshot {
             market_id,
+            sequence: 0,
             bids: vec![],
             asks: vec![],
             last_price: None,
@@ -73,18 +125,14 @@ pub async fn get_orderbook(
     Ok(Json(snapshot))
 }
 
-#[derive(Deserialize)]
-pub struct TradesQuery {
-    pub limit: Option<i64>,
-}
-
+/// History endpoints page with `before`/`after`/`from`/`to`/`limit`; see `PageParams`.
 pub async fn get_trades(
     State(state): State<Arc<AppState>>,
     Path(market_id): Path<Uuid>,
-    Query(query): Query<TradesQuery>,
+    Query(page): Query<PageParams>,
 ) -> Result<Json<Vec<Trade>>> {
-    let limit = query.limit.unwrap_or(50);
-    let trades = db::get_recent_trades(&state.db_pool, market_id, limit).await?;
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let trades = db::get_recent_trades(&state.db_pool, market_id, &page).await?;
     Ok(Json(trades))
 }
 
@@ -96,37 +144,150 @@ pub struct PlaceOrderResponse {
 
 pub async fn place_order(
     State(state): State<Arc<AppState>>,
+    api_key: Option<Extension<AuthenticatedKey>>,
+    delegate: Option<Extension<AuthenticatedDelegate>>,
+    wallet: Option<Extension<AuthenticatedWallet>>,
     Json(req): Json<PlaceOrderRequest>,
 ) -> Result<Json<PlaceOrderResponse>> {
+    if !state.health.is_recovered() {
+        return Err(AppError::RecoveryInProgress);
+    }
+    // Every trade settles on chain, signed by the market authority.
+    if state.settlement_queue.solana_client().authority_pubkey().is_none() {
+        return Err(AppError::AuthorityUnavailable);
+    }
+    if let Some(Extension(AuthenticatedKey(key))) = &api_key {
+        if !key.can_place() {
+            return Err(AppError::Forbidden("API key lacks the trade scope".to_string()));
+        }
+        if key.wallet != req.wallet {
+            return Err(AppError::Forbidden("API key belongs to another wallet".to_string()));
+        }
+    }
+    if let Some(Extension(AuthenticatedWallet(signer))) = &wallet {
+        if *signer != req.wallet {
+            return Err(AppError::Forbidden("Request is signed by another wallet".to_string()));
+        }
+    }
+
     let market = db::get_market(&state.db_pool, req.market_id)
         .await?
         .ok_or(AppError::MarketNotFound)?;
+    let market_label = market.id.to_string();
+    let _timer = METRICS.order_latency
+        .with_label_values(&[&market_label, "place"])
+        .start_timer();
 
     if !market.is_active {
-        return Err(AppError::InvalidOrder("Market is not active".to_string()));
+        return Err(AppError::MarketInactive);
     }
 
     if req.size < market.min_order_size {
-        return Err(AppError::InvalidOrder(format!(
-            "Order size {} is below minimum {}",
-            req.size, market.min_order_size
-        )));
+        return Err(AppError::OrderBelowMinSize {
+            size: req.size,
+            min_order_size: market.min_order_size,
+        });
     }
 
     if req.price % market.tick_size != 0 {
-        return Err(AppError::InvalidOrder(format!(
-            "Price {} is not aligned to tick size {}",
-            req.price, market.tick_size
-        )));
+        return Err(AppError::OrderTickMisaligned {
+            field: "price",
+            value: req.price,
+            tick_size: market.tick_size,
+        });
+    }
+
+    let Some(notional) = settlement::quote_amount(req.size, req.price, market.base_decimals) else {
+        return Err(AppError::OrderValueOutOfRange {
+            size: req.size,
+            price: req.price,
+        });
+    };
+    if let Some(Extension(AuthenticatedDelegate(delegate))) = &delegate {
+        delegates::authorize(&state, delegate, &market, &req.wallet, Some(notional.unsigned_abs())).await?;
+    }
+    if let Some(session_id) = req.session_id {
+        state.session_manager
+            .validate(session_id, &req.wallet)
+            .await
+            .map_err(|_| AppError::Unauthorized)?;
+    }
+    state.rate_limiter.check_wallet(Budget::Place, &req.wallet).await?;
+    state.rate_limiter.check_restriction(&req.wallet).await?;
+
+    if let Some(expires_at) = req.expires_at {
+        if expires_at <= chrono::Utc::now() {
+            return Err(AppError::OrderExpiryInPast);
+        }
+    }
+
+    if let Some(display_size) = req.display_size {
+        if !req.order_type.rests() {
+            return Err(AppError::InvalidOrder(
+                "Display size is not allowed on market orders".to_string(),
+            ));
+        }
+        if display_size < market.min_order_size || display_size >= req.size {
+            return Err(AppError::InvalidOrder(format!(
+                "Display size {} must be at least {} and below the order size {}",
+                display_size, market.min_order_size, req.size
+            )));
+        }
+    }
+
+    let trigger_direction = TriggerDirection::of(req.order_type, req.side);
+    match (trigger_direction, req.trigger_price) {
+        (None, Some(_)) => {
+            return Err(AppError::InvalidOrder(
+                "Trigger price is only allowed on stop and take-profit orders".to_string(),
+            ));
+        }
+        (Some(_), None) => {
+            return Err(AppError::InvalidOrder(
+                "Stop and take-profit orders require a trigger price".to_string(),
+            ));
+        }
+        (Some(direction), Some(trigger_price)) => {
+            if trigger_price <= 0 || trigger_price % market.tick_size != 0 {
+                return Err(AppError::OrderTickMisaligned {
+                    field: "trigger_price",
+                    value: trigger_price,
+                    tick_size: market.tick_size,
+                });
+            }
+
+            let last_price = state.orderbook_manager
+                .read()
+                .await
+                .get(&req.market_id)
+                .and_then(|ob| ob.last_price);
+            if let Some(last_price) = last_price {
+                if direction.is_crossed(trigger_price, last_price) {
+                    return Err(AppError::TriggerAlreadyCrossed {
+                        trigger_price,
+                        last_price,
+                    });
+                }
+            }
+        }
+        (None, None) => {}
+    }
+
+    // Orders on on-chain markets lock funds through `place_order` first, so
+    // the engine needs that order's id to check it and settle against it.
+    if market.market_address.is_some() {
+        let Some(order_id) = &req.order_id else {
+            return Err(AppError::InvalidOrder(
+                "Orders on on-chain markets require the order_id of the placed order".to_string(),
+            ));
+        };
+        verify_on_chain_expiry(&state, order_id, req.expires_at).await?;
     }
 
     // Use provided order_id or generate one if missing (though frontend should provide it)
     let order_id = req.order_id.clone().unwrap_or_else(|| {
         chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0).to_string()
     });
-    
-    // TODO: Verify on-chain signature if needed
-    // For now we assume the signature provided is valid or verified elsewhere
 
     let order = db::create_order(
         &state.db_pool,
@@ -136,61 +297,288 @@ pub async fn place_order(
         req.side,
         req.price,
         req.size,
+        req.order_type,
+        req.trigger_price,
+        req.display_size,
+        req.expires_at,
     ).await?;
+    METRICS.orders_placed.with_label_values(&[&market_label]).inc();
+    state.rate_limiter.record_order(&req.wallet).await;
 
     let mut orderbook_manager = state.orderbook_manager.write().await;
     let orderbook = orderbook_manager.get_or_create(req.market_id);
-    
-    let match_result = MatchingEngine::match_order(orderbook, &order);
-    
+    orderbook.allocation = Allocation::for_market(&market);
+
+    if orderbook.triggers.insert(&order) {
+        orderbook_manager.schedule_expiry(&order);
+        drop(orderbook_manager);
+
+        track_session_order(&state, &req, &order).await;
+        state.ws_manager.send_order_update(order.clone()).await;
+
+        return Ok(Json(PlaceOrderResponse {
+            order,
+            trades: Vec::new(),
+        }));
+    }
+
+    let executions = MatchingEngine::execute(orderbook, &order);
+
+    let mut updated_order = order.clone();
     let mut trade_infos = Vec::new();
-    for trade_match in &match_result.trades {
-        db::update_order_status(
+    let mut triggered_orders = Vec::new();
+    for execution in &executions {
+        let persisted = persist_execution(&state, &market, execution).await?;
+
+        if execution.triggered {
+            triggered_orders.push(persisted);
+        } else {
+            trade_infos = execution.result.trades
+                .iter()
+                .map(|trade_match| TradeInfo {
+                    maker_order_id: trade_match.maker_order_id.clone(),
+                    price: trade_match.price,
+                    size: trade_match.size,
+                })
+                .collect();
+            updated_order = persisted;
+        }
+    }
+
+    let last_price = orderbook.last_price;
+    if let Some(delta) = orderbook.take_delta() {
+        state.ws_manager.publish_book(orderbook, delta).await;
+    }
+    let traded = executions.iter().any(|execution| !execution.result.trades.is_empty());
+    state.ticker_manager.refresh(&state.ws_manager, orderbook, traded).await;
+    if executions[0].rested {
+        orderbook_manager.schedule_expiry(&updated_order);
+    }
+    drop(orderbook_manager);
+
+    let traders: Vec<&str> = executions
+        .iter()
+        .flat_map(|execution| &execution.result.trades)
+        .flat_map(|trade_match| [trade_match.maker_wallet.as_str(), trade_match.taker_wallet.as_str()])
+        .collect();
+    state.rate_limiter.record_trades(&traders).await;
+
+    track_session_order(&state, &req, &updated_order).await;
+
+    state.ws_manager.send_order_update(updated_order.clone()).await;
+    for triggered in triggered_orders {
+        if let (Some(trigger_price), Some(last_price)) = (triggered.trigger_price, last_price) {
+            state.ws_manager
+                .send_order_triggered(&triggered, trigger_price, last_price)
+                .await;
+        }
+        state.ws_manager.send_order_update(triggered).await;
+    }
+
+    Ok(Json(PlaceOrderResponse {
+        order: updated_order,
+        trades: trade_infos,
+    }))
+}
+
+/// The book expires orders on the engine clock and `expire_order` unlocks
+/// them on chain, so both must carry the same deadline. Clients submit once
+/// their `place_order` transaction is `confirmed`, the level the order is read
+/// at, so a single read finds it.
+async fn verify_on_chain_expiry(
+    state: &AppState,
+    order_id: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let on_chain_id: u128 = order_id
+        .parse()
+        .map_err(|_| AppError::InvalidOrder(format!("Order id {} is not a valid on-chain id", order_id)))?;
+    let on_chain = state.settlement_queue
+        .solana_client()
+        .fetch_order(on_chain_id)
+        .await?
+        .ok_or_else(|| AppError::InvalidOrder(format!("Order {} is not on chain", order_id)))?
+        .expires_at;
+
+    // The chain keeps whole seconds; a fractional expiry would outlive it.
+    if expires_at.is_some_and(|expires_at| expires_at.timestamp_subsec_nanos() != 0) {
+        return Err(AppError::InvalidOrder("Expiry must be a whole second".to_string()));
+    }
+    let requested = expires_at.map_or(0, |expires_at| expires_at.timestamp());
+    if on_chain != requested {
+        return Err(AppError::InvalidOrder(format!(
+            "Expiry {} does not match the on-chain order's {}",
+            requested, on_chain
+        )));
+    }
+    Ok(())
+}
+
+/// Writes one execution back to Postgres: maker fills, settlement tasks and
+/// the taker's own status. Returns the taker as stored.
+async fn persist_execution(
+    state: &Arc<AppState>,
+    market: &Market,
+    execution: &Execution,
+) -> Result<Order> {
+    let order = &execution.order;
+
+    if execution.triggered {
+        db::mark_order_triggered(&state.db_pool, &order.order_id).await?;
+    }
+
+    for trade_match in &execution.result.trades {
+        if db::fill_order(&state.db_pool, &trade_match.maker_order_id, trade_match.size).await? {
+            state.session_manager.untrack_order(&trade_match.maker_order_id).await;
+        }
+
+        let maker_rates = state.fee_manager.rates(&state.db_pool, market, &trade_match.maker_wallet).await?;
+        let taker_fees = state.fee_manager.wallet_fees(&state.db_pool, market, &trade_match.taker_wallet).await?;
+        let taker_rates = taker_fees.rates;
+        let referrer = taker_fees.referrer;
+        // Fills are never worth more than the order, which was checked to fit.
+        let value = settlement::fill_value(
+            trade_match,
+            market,
+            &maker_rates,
+            &taker_rates,
+            referrer.is_some(),
+        ).ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("Fill value of {} overflows", trade_match.taker_order_id))
+        })?;
+        let trade = db::create_trade(
             &state.db_pool,
+            market.id,
             &trade_match.maker_order_id,
-            OrderStatus::PartiallyFilled,
+            &trade_match.taker_order_id,
+            &trade_match.maker_wallet,
+            &trade_match.taker_wallet,
+            trade_match.price,
             trade_match.size,
+            order.side,
+            value.maker_fee,
+            value.taker_fee,
         ).await?;
+        if let Some(referrer) = referrer.as_deref().filter(|_| value.referrer_fee > 0) {
+            db::create_referral_earning(
+                &state.db_pool,
+                trade.id,
+                market.id,
+                referrer,
+                &trade_match.taker_wallet,
+                value.referrer_fee,
+            ).await?;
+        }
 
         let task = SettlementTask {
+            trade_id: trade.id,
+            sequence: trade.sequence,
             trade_match: trade_match.clone(),
-            market_id: req.market_id,
-            maker_fee_bps: market.maker_fee_bps,
-            taker_fee_bps: market.taker_fee_bps,
+            market_id: market.id,
+            taker_side: trade.taker_side,
+            quote_amount: value.quote_amount,
+            maker_fee: value.maker_fee,
+            taker_fee: value.taker_fee,
+            maker_rates,
+            taker_rates,
+            referrer,
+            referrer_fee: value.referrer_fee,
         };
+        state.ws_manager.send_fill(trade.fill_for(LiquidityRole::Maker)).await;
+        state.ws_manager.send_fill(trade.fill_for(LiquidityRole::Taker)).await;
+        state.ticker_manager.record_trade(&trade, value.quote_amount).await;
+        state.candle_aggregator.queue(trade.clone());
+        state.ws_manager.broadcast_trade(trade).await;
         state.settlement_queue.queue_settlement(task).await
-            .map_err(|e| AppError::Internal(e))?;
-
-        trade_infos.push(TradeInfo {
-            maker_order_id: trade_match.maker_order_id.clone(),
-            price: trade_match.price,
-            size: trade_match.size,
-        });
+            .map_err(AppError::Internal)?;
     }
 
-    let total_filled: i64 = match_result.trades.iter().map(|t| t.size).sum();
-    let updated_order = if total_filled > 0 {
-        let status = if total_filled >= order.size {
-            OrderStatus::Filled
-        } else {
-            OrderStatus::PartiallyFilled
-        };
-        db::update_order_status(&state.db_pool, &order_id, status, total_filled).await?
+    let status = if order.remaining() <= 0 {
+        OrderStatus::Filled
+    } else if !execution.rested {
+        // Market remainder the book could not absorb.
+        OrderStatus::Cancelled
+    } else if order.filled > 0 {
+        OrderStatus::PartiallyFilled
     } else {
-        orderbook.add_order(&order);
-        order
+        OrderStatus::Pending
     };
 
-    let snapshot = orderbook.snapshot(20);
-    drop(orderbook_manager);
-    
-    state.ws_manager.broadcast_orderbook_snapshot(snapshot).await;
-    state.ws_manager.broadcast_order_update(updated_order.clone()).await;
+    if !matches!(status, OrderStatus::Pending | OrderStatus::PartiallyFilled) {
+        state.session_manager.untrack_order(&order.order_id).await;
+    }
+    db::update_order_status(&state.db_pool, &order.order_id, status, order.filled).await
+}
 
-    Ok(Json(PlaceOrderResponse {
-        order: updated_order,
-        trades: trade_infos,
-    }))
+/// Attaches a still-open order to the caller's cancel-on-disconnect session.
+async fn track_session_order(state: &Arc<AppState>, req: &PlaceOrderRequest, order: &Order) {
+    let Some(session_id) = req.session_id else {
+        return;
+    };
+    if !matches!(order.status, OrderStatus::Pending | OrderStatus::PartiallyFilled) {
+        return;
+    }
+
+    // The session may have ended while matching; the order simply stays untracked.
+    if let Err(e) = state.session_manager
+        .track_order(session_id, &req.wallet, &order.order_id)
+        .await
+    {
+        tracing::warn!("Order {} not attached to session {}: {}", order.order_id, session_id, e);
+    }
+}
+
+pub async fn get_tickers(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Ticker>>> {
+    let markets = db::get_active_markets(&state.db_pool).await?;
+
+    let mut tickers = Vec::with_capacity(markets.len());
+    for market in markets {
+        tickers.push(state.ticker_manager.ticker(market.id).await);
+    }
+    Ok(Json(tickers))
+}
+
+pub async fn get_ticker(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<Ticker>> {
+    db::get_market(&state.db_pool, market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+
+    Ok(Json(state.ticker_manager.ticker(market_id).await))
+}
+
+#[derive(Deserialize)]
+pub struct CandlesQuery {
+    pub interval: CandleInterval,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+const MAX_CANDLES: i64 = 1000;
+
+/// Candles in `[from, to)`, oldest first. Without `from`, the window reaches
+/// back `limit` intervals from `to` (default: now).
+pub async fn get_candles(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+    Query(query): Query<CandlesQuery>,
+) -> Result<Json<Vec<Candle>>> {
+    let limit = query.limit.unwrap_or(500).clamp(1, MAX_CANDLES);
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query
+        .from
+        .unwrap_or_else(|| to - chrono::Duration::seconds(query.interval.seconds() * limit));
+    if from >= to {
+        return Err(AppError::InvalidRequest("`from` must be before `to`".to_string()));
+    }
+
+    let candles = db::get_candles(&state.db_pool, market_id, query.interval, from, to, limit).await?;
+    Ok(Json(candles))
 }
 
 #[derive(Serialize)]
@@ -202,36 +590,99 @@ pub struct TradeInfo {
 
 pub async fn cancel_order(
     State(state): State<Arc<AppState>>,
+    api_key: Option<Extension<AuthenticatedKey>>,
+    delegate: Option<Extension<AuthenticatedDelegate>>,
+    wallet: Option<Extension<AuthenticatedWallet>>,
     Path(order_id): Path<String>,
 ) -> Result<Json<Order>> {
     let order = db::get_order(&state.db_pool, &order_id)
         .await?
         .ok_or(AppError::OrderNotFound)?;
+    if let Some(Extension(AuthenticatedKey(key))) = &api_key {
+        if !key.can_cancel() {
+            return Err(AppError::Forbidden("API key lacks the trade or cancel_only scope".to_string()));
+        }
+        if key.wallet != order.user_wallet {
+            return Err(AppError::Forbidden("API key belongs to another wallet".to_string()));
+        }
+    }
+    if let Some(Extension(AuthenticatedWallet(signer))) = &wallet {
+        if *signer != order.user_wallet {
+            return Err(AppError::Forbidden("Request is signed by another wallet".to_string()));
+        }
+    }
+    if let Some(Extension(AuthenticatedDelegate(delegate))) = &delegate {
+        let market = db::get_market(&state.db_pool, order.market_id)
+            .await?
+            .ok_or(AppError::MarketNotFound)?;
+        delegates::authorize(&state, delegate, &market, &order.user_wallet, None).await?;
+    }
+    state.rate_limiter.check_wallet(Budget::Cancel, &order.user_wallet).await?;
+    let _timer = METRICS.order_latency
+        .with_label_values(&[&order.market_id.to_string(), "cancel"])
+        .start_timer();
+
+    let updated_order = cancel_open_order(&state, order, CancelReason::UserRequested).await?;
+
+    Ok(Json(updated_order))
+}
 
+/// Cancels a resting order: marks it cancelled (or expired), pulls it from the
+/// book and notifies subscribers. Engine-initiated cancels also queue the
+/// on-chain unlock.
+pub(crate) async fn cancel_open_order(
+    state: &Arc<AppState>,
+    order: Order,
+    reason: CancelReason,
+) -> Result<Order> {
+    if !state.health.is_recovered() {
+        return Err(AppError::RecoveryInProgress);
+    }
     if !matches!(order.status, OrderStatus::Pending | OrderStatus::PartiallyFilled) {
-        return Err(AppError::InvalidOrder("Order cannot be cancelled".to_string()));
+        return Err(AppError::OrderNotCancellable {
+            order_id: order.order_id,
+        });
     }
 
     let updated_order = db::update_order_status(
         &state.db_pool,
-        &order_id,
-        OrderStatus::Cancelled,
+        &order.order_id,
+        reason.final_status(),
         order.filled,
     ).await?;
+    METRICS.orders_cancelled
+        .with_label_values(&[&order.market_id.to_string(), reason.label()])
+        .inc();
+    state.session_manager.untrack_order(&order.order_id).await;
 
     let mut orderbook_manager = state.orderbook_manager.write().await;
     if let Some(orderbook) = orderbook_manager.get_mut(&order.market_id) {
-        orderbook.remove_order(&order_id);
-        
-        let snapshot = orderbook.snapshot(20);
-        drop(orderbook_manager);
-        
-        state.ws_manager.broadcast_orderbook_snapshot(snapshot).await;
+        orderbook.remove_order(&order.order_id);
+        orderbook.triggers.remove(&order.order_id);
+
+        if let Some(delta) = orderbook.take_delta() {
+            state.ws_manager.publish_book(orderbook, delta).await;
+        }
+        state.ticker_manager.refresh(&state.ws_manager, orderbook, false).await;
     }
+    drop(orderbook_manager);
 
-    state.ws_manager.broadcast_order_update(updated_order.clone()).await;
+    if reason.requires_on_chain_unlock() {
+        state.settlement_queue
+            .queue_unlock(UnlockTask {
+                order_id: order.order_id.clone(),
+                user_wallet: order.user_wallet.clone(),
+                market_id: order.market_id,
+                reason,
+                attempt: 0,
+            })
+            .await
+            .map_err(AppError::Internal)?;
+    }
 
-    Ok(Json(updated_order))
+    state.ws_manager.send_order_update(updated_order.clone()).await;
+
+    Ok(updated_order)
 }
 
 pub async fn get_order(
@@ -247,17 +698,135 @@ pub async fn get_order(
 #[derive(Deserialize)]
 pub struct UserOrdersQuery {
     pub market_id: Option<Uuid>,
+    pub status: Option<OrderStatus>,
+    pub side: Option<OrderSide>,
 }
 
 pub async fn get_user_orders(
     State(state): State<Arc<AppState>>,
     Path(wallet): Path<String>,
     Query(query): Query<UserOrdersQuery>,
+    Query(page): Query<PageParams>,
 ) -> Result<Json<Vec<Order>>> {
-    let orders = db::get_user_orders(&state.db_pool, &wallet, query.market_id).await?;
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let orders = db::get_user_orders(
+        &state.db_pool,
+        &wallet,
+        query.market_id,
+        query.status,
+        query.side,
+        &page,
+    ).await?;
     Ok(Json(orders))
 }
 
+#[derive(Deserialize)]
+pub struct UserTradesQuery {
+    pub market_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct UserFillsQuery {
+    pub market_id: Option<Uuid>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// The wallet's side of each of its trades, paged by trade id. `format=csv`
+/// returns the same page as a CSV download.
+pub async fn get_user_fills(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<UserFillsQuery>,
+    Query(page): Query<PageParams>,
+) -> Result<Response> {
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let trades = db::get_user_trades(&state.db_pool, &wallet, query.market_id, &page).await?;
+    let fills: Vec<Fill> = trades
+        .iter()
+        .flat_map(|trade| trade.fills_for_wallet(&wallet))
+        .collect();
+
+    Ok(match query.format {
+        ExportFormat::Json => Json(fills).into_response(),
+        ExportFormat::Csv => csv::attachment(&format!("fills-{}.csv", wallet), &fills),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct UserFeesQuery {
+    pub market_id: Uuid,
+}
+
+/// The rates the wallet currently pays on a market and why.
+pub async fn get_user_fees(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<UserFeesQuery>,
+) -> Result<Json<WalletFees>> {
+    let market = db::get_market(&state.db_pool, query.market_id)
+        .await?
+        .ok_or(AppError::MarketNotFound)?;
+    let fees = state.fee_manager.wallet_fees(&state.db_pool, &market, &wallet).await?;
+    Ok(Json(fees))
+}
+
+/// Registers the wallet's referrer. The wallet signs
+/// `auth::referral_message`, and a referrer can only be set once.
+pub async fn register_referral(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RegisterReferralRequest>,
+) -> Result<Json<Referral>> {
+    for (field, value) in [("wallet", &req.wallet), ("referrer", &req.referrer)] {
+        if Pubkey::from_str(value).is_err() {
+            return Err(AppError::InvalidRequest(format!("Invalid {} '{}'", field, value)));
+        }
+    }
+    if req.wallet == req.referrer {
+        return Err(AppError::InvalidRequest("A wallet cannot refer itself".to_string()));
+    }
+    let message = auth::referral_message(&req.wallet, &req.referrer);
+    if !auth::verify_wallet_signature(&req.wallet, &message, &req.signature) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let referral = db::create_referral(&state.db_pool, &req.wallet, &req.referrer)
+        .await?
+        .ok_or_else(|| AppError::ReferralExists {
+            wallet: req.wallet.clone(),
+        })?;
+    state.fee_manager.invalidate_wallet(&req.wallet).await;
+    Ok(Json(referral))
+}
+
+/// Who referred the wallet, how many wallets it referred and what it earned.
+pub async fn get_user_referrals(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+) -> Result<Json<ReferralSummary>> {
+    let referral = db::get_referral(&state.db_pool, &wallet).await?;
+    let referee_count = db::count_referees(&state.db_pool, &wallet).await?;
+    let earnings = db::get_referral_earnings(&state.db_pool, &wallet).await?;
+
+    Ok(Json(ReferralSummary {
+        wallet,
+        referrer: referral.map(|referral| referral.referrer),
+        referee_count,
+        earnings,
+    }))
+}
+
+pub async fn get_user_trades(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<UserTradesQuery>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Vec<Trade>>> {
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let trades = db::get_user_trades(&state.db_pool, &wallet, query.market_id, &page).await?;
+    Ok(Json(trades))
+}
+
 pub async fn record_deposit(
     State(state): State<Arc<AppState>>,
     Json(req): Json<DepositRequest>,
@@ -267,11 +836,11 @@ pub async fn record_deposit(
         .ok_or(AppError::MarketNotFound)?;
 
     if !market.is_active {
-        return Err(AppError::InvalidOrder("Market is not active".to_string()));
+        return Err(AppError::MarketInactive);
     }
 
     if req.amount <= 0 {
-        return Err(AppError::InvalidOrder("Amount must be positive".to_string()));
+        return Err(AppError::InvalidRequest("Amount must be positive".to_string()));
     }
 
     let deposit = db::create_deposit(
@@ -283,6 +852,17 @@ pub async fn record_deposit(
         &req.signature,
     ).await?;
 
+    let (base_delta, quote_delta) = if req.is_base { (req.amount, 0) } else { (0, req.amount) };
+    state.ws_manager
+        .send_balance_change(&req.wallet, BalanceChange {
+            market_id: req.market_id,
+            reason: BalanceChangeReason::Deposit,
+            base_delta,
+            quote_delta,
+            reference: req.signature.clone(),
+        })
+        .await;
+
     Ok(Json(deposit))
 }
 
@@ -295,11 +875,11 @@ pub async fn record_withdrawal(
         .ok_or(AppError::MarketNotFound)?;
 
     if !market.is_active {
-        return Err(AppError::InvalidOrder("Market is not active".to_string()));
+        return Err(AppError::MarketInactive);
     }
 
     if req.amount <= 0 {
-        return Err(AppError::InvalidOrder("Amount must be positive".to_string()));
+        return Err(AppError::InvalidRequest("Amount must be positive".to_string()));
     }
 
     let withdrawal = db::create_withdrawal(
@@ -311,6 +891,17 @@ pub async fn record_withdrawal(
         &req.signature,
     ).await?;
 
+    let (base_delta, quote_delta) = if req.is_base { (-req.amount, 0) } else { (0, -req.amount) };
+    state.ws_manager
+        .send_balance_change(&req.wallet, BalanceChange {
+            market_id: req.market_id,
+            reason: BalanceChangeReason::Withdrawal,
+            base_delta,
+            quote_delta,
+            reference: req.signature.clone(),
+        })
+        .await;
+
     Ok(Json(withdrawal))
 }
 
@@ -323,8 +914,10 @@ pub async fn get_user_deposits(
     State(state): State<Arc<AppState>>,
     Path(wallet): Path<String>,
     Query(query): Query<UserDepositsQuery>,
+    Query(page): Query<PageParams>,
 ) -> Result<Json<Vec<Deposit>>> {
-    let deposits = db::get_user_deposits(&state.db_pool, &wallet, query.market_id).await?;
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let deposits = db::get_user_deposits(&state.db_pool, &wallet, query.market_id, &page).await?;
     Ok(Json(deposits))
 }
 
@@ -337,7 +930,9 @@ pub async fn get_user_withdrawals(
     State(state): State<Arc<AppState>>,
     Path(wallet): Path<String>,
     Query(query): Query<UserWithdrawalsQuery>,
+    Query(page): Query<PageParams>,
 ) -> Result<Json<Vec<Withdrawal>>> {
-    let withdrawals = db::get_user_withdrawals(&state.db_pool, &wallet, query.market_id).await?;
+    page.validate().map_err(AppError::InvalidRequest)?;
+    let withdrawals = db::get_user_withdrawals(&state.db_pool, &wallet, query.market_id, &page).await?;
     Ok(Json(withdrawals))
 }
@@ -1,5 +1,13 @@
 mod routes;
+mod admin;
+mod api_keys;
+mod delegates;
+mod signatures;
+mod csv;
 mod handlers;
 mod ws_handler;
+mod expiry;
+pub mod request_id;
 
 pub use routes::create_router;
+pub use expiry::run_expiry_sweeper;
@@ -1,13 +1,20 @@
 use std::sync::Arc;
 use axum::{
-    routing::{get, post, delete},
+    middleware,
+    routing::{get, post, put, delete},
     Router,
 };
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
+use crate::ratelimit::{self, Budget};
 use crate::AppState;
+use super::admin;
+use super::api_keys;
+use super::delegates;
 use super::handlers;
+use super::request_id;
+use super::signatures;
 use super::ws_handler;
 
 pub fn create_router(state: Arc<AppState>) -> Router {
@@ -16,21 +23,87 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .allow_methods(Any)
         .allow_headers(Any);
 
-    Router::new()
-        .route("/health", get(handlers::health_check))
+    let admin_routes = Router::new()
+        .route("/markets", post(admin::create_market))
+        .route("/markets/import", post(admin::import_market))
+        .route("/markets/:market_id/fee-tiers", get(admin::get_fee_tiers).put(admin::set_fee_tiers))
+        .route("/markets/:market_id/referral-share", put(admin::set_referral_share))
+        .route("/fee-overrides", get(admin::get_fee_overrides).put(admin::set_fee_override))
+        .route("/fee-overrides/:market_id/:wallet", delete(admin::delete_fee_override))
+        .route("/revenue", get(admin::get_revenue_totals))
+        .route("/revenue/ledger", get(admin::get_fee_ledger))
+        .route("/revenue/reconcile", post(admin::reconcile_fees))
+        .route_layer(middleware::from_fn_with_state(state.clone(), admin::require_admin));
+
+    // The IP limit runs first so floods are turned away before key lookups.
+    // Trading routes then need an API key, delegate or wallet signature, and
+    // each signed request is accepted once.
+    let limit = |budget| middleware::from_fn_with_state((state.clone(), budget), ratelimit::limit_ip);
+    let authenticate = || middleware::from_fn_with_state(state.clone(), api_keys::authenticate);
+    let reject_replays = || middleware::from_fn_with_state(state.clone(), signatures::reject_replays);
+
+    let place_routes = Router::new()
+        .route("/api/orders", post(handlers::place_order))
+        .route_layer(reject_replays())
+        .route_layer(middleware::from_fn(signatures::require_signed))
+        .route_layer(middleware::from_fn(signatures::authenticate))
+        .route_layer(middleware::from_fn(delegates::authenticate))
+        .route_layer(authenticate())
+        .route_layer(limit(Budget::Place));
+
+    let cancel_routes = Router::new()
+        .route("/api/orders/:order_id", delete(handlers::cancel_order))
+        .route_layer(reject_replays())
+        .route_layer(middleware::from_fn(signatures::require_signed))
+        .route_layer(middleware::from_fn(signatures::authenticate))
+        .route_layer(middleware::from_fn(delegates::authenticate))
+        .route_layer(authenticate())
+        .route_layer(limit(Budget::Cancel));
+
+    let query_routes = Router::new()
         .route("/api/markets", get(handlers::get_markets))
         .route("/api/markets/:market_id", get(handlers::get_market))
         .route("/api/markets/:market_id/orderbook", get(handlers::get_orderbook))
         .route("/api/markets/:market_id/trades", get(handlers::get_trades))
-        .route("/api/orders", post(handlers::place_order))
-        .route("/api/orders/:order_id", delete(handlers::cancel_order))
+        .route("/api/markets/:market_id/candles", get(handlers::get_candles))
+        .route("/api/markets/:market_id/ticker", get(handlers::get_ticker))
+        .route("/api/tickers", get(handlers::get_tickers))
         .route("/api/orders/:order_id", get(handlers::get_order))
         .route("/api/users/:wallet/orders", get(handlers::get_user_orders))
+        .route("/api/markets/:market_id/delegates/:wallet", get(delegates::get_delegate))
+        .route("/api/users/:wallet/trades", get(handlers::get_user_trades))
+        .route("/api/users/:wallet/fills", get(handlers::get_user_fills))
+        .route("/api/users/:wallet/fees", get(handlers::get_user_fees))
+        .route("/api/users/:wallet/referrals", get(handlers::get_user_referrals))
+        .route("/api/referrals", post(handlers::register_referral))
         .route("/api/deposits", post(handlers::record_deposit))
         .route("/api/withdrawals", post(handlers::record_withdrawal))
         .route("/api/users/:wallet/deposits", get(handlers::get_user_deposits))
         .route("/api/users/:wallet/withdrawals", get(handlers::get_user_withdrawals))
+        .route("/api/ws/stats", get(handlers::get_ws_stats))
+        .route_layer(middleware::from_fn_with_state((state.clone(), Budget::Query), api_keys::limit_wallet))
+        .route_layer(authenticate())
+        .route_layer(limit(Budget::Query));
+
+    // Key management is authenticated by wallet signature, not by API key.
+    let key_routes = Router::new()
+        .route("/api/api-keys", post(api_keys::create_api_key))
+        .route("/api/api-keys/:key_id", delete(api_keys::revoke_api_key))
+        .route("/api/users/:wallet/api-keys", get(api_keys::get_wallet_api_keys))
+        .route_layer(limit(Budget::Query));
+
+    Router::new()
+        .route("/health", get(handlers::health_check))
+        .route("/health/live", get(handlers::health_check))
+        .route("/health/ready", get(handlers::readiness))
+        .route("/metrics", get(handlers::metrics))
         .route("/ws", get(ws_handler::websocket_handler))
+        .merge(place_routes)
+        .merge(cancel_routes)
+        .merge(query_routes)
+        .merge(key_routes)
+        .nest("/api/admin", admin_routes)
+        .layer(middleware::from_fn(request_id::assign_request_id))
         .layer(cors)
         .layer(TraceLayer::new_for_http())
         .with_state(state)
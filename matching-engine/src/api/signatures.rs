@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+
+use super::api_keys::{KEY_HEADER, MAX_SIGNED_BODY, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use super::delegates::DELEGATE_HEADER;
+use crate::auth;
+use crate::error::{AppError, Result};
+use crate::AppState;
+
+/// Wallet that signed the request with its own key.
+pub const WALLET_HEADER: &str = "x-dcex-wallet";
+
+/// The wallet a request was signed by. Handlers still have to check it is
+/// the wallet they act for.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedWallet(pub String);
+
+/// Attached by every authenticator once a request's signature checks out,
+/// so trading routes can insist on one and turn away replays.
+#[derive(Debug, Clone)]
+pub struct SignedRequest {
+    pub signer: String,
+    pub timestamp: String,
+    pub signature: String,
+}
+
+impl SignedRequest {
+    fn replay_key(&self) -> String {
+        format!("replay:{}:{}:{}", self.signer, self.timestamp, self.signature)
+    }
+}
+
+/// Verifies requests signed by the wallet itself and attaches it. Requests
+/// without the wallet header pass through unchanged.
+pub async fn authenticate(request: Request, next: Next) -> Result<Response> {
+    let headers = request.headers();
+    if !headers.contains_key(WALLET_HEADER) {
+        return Ok(next.run(request).await);
+    }
+    if headers.contains_key(KEY_HEADER) || headers.contains_key(DELEGATE_HEADER) {
+        return Err(AppError::InvalidRequest(
+            "Sign with one of an API key, a delegate or the wallet".to_string(),
+        ));
+    }
+    let (mut request, wallet) = verify_ed25519(request, WALLET_HEADER).await?;
+    request.extensions_mut().insert(AuthenticatedWallet(wallet));
+    Ok(next.run(request).await)
+}
+
+/// Checks an ed25519 signature over `timestamp + method + path + body` by the
+/// key named in `signer_header`. Returns the request, with its body restored
+/// and a `SignedRequest` attached, and the signer.
+pub(super) async fn verify_ed25519(request: Request, signer_header: &str) -> Result<(Request, String)> {
+    let headers = request.headers();
+    let signer = header(headers, signer_header)?.to_string();
+    let timestamp = header(headers, TIMESTAMP_HEADER)?.to_string();
+    let signature = header(headers, SIGNATURE_HEADER)?.to_string();
+
+    let timestamp_ms: i64 = timestamp.parse().map_err(|_| AppError::Unauthorized)?;
+    if !auth::is_fresh(timestamp_ms, Utc::now().timestamp_millis()) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_SIGNED_BODY)
+        .await
+        .map_err(|_| AppError::InvalidRequest("Request body too large".to_string()))?;
+    let path_and_query = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+    if !auth::verify_signed_request(
+        &signer,
+        &timestamp,
+        parts.method.as_str(),
+        path_and_query,
+        &body,
+        &signature,
+    ) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let mut request = Request::from_parts(parts, Body::from(body));
+    request.extensions_mut().insert(SignedRequest {
+        signer: signer.clone(),
+        timestamp,
+        signature,
+    });
+    Ok((request, signer))
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::Unauthorized)
+}
+
+/// Turns away requests no authenticator vouched for: trading routes need an
+/// API key, a delegate or the wallet's own signature.
+pub async fn require_signed(request: Request, next: Next) -> Result<Response> {
+    if request.extensions().get::<SignedRequest>().is_none() {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Accepts each signed request once. A timestamp is fresh for the signature
+/// window either side of now, so the marker is kept for twice that. Redis
+/// outages fail open like the rate limits; freshness still bounds replays.
+pub async fn reject_replays(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if let Some(signed) = request.extensions().get::<SignedRequest>() {
+        let key = signed.replay_key();
+        let claimed: redis::RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(2 * auth::SIGNATURE_WINDOW_MS)
+            .query_async(&mut state.redis.clone())
+            .await;
+        match claimed {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                tracing::warn!("Rejected replayed request from {}", signed.signer);
+                return Err(AppError::Unauthorized);
+            }
+            Err(e) => tracing::warn!("Replay check for {} failed, allowing: {}", key, e),
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware, routing::post, Extension, Router};
+    use solana_sdk::signature::{Keypair, Signer};
+    use tower::ServiceExt;
+
+    async fn signer(wallet: Option<Extension<AuthenticatedWallet>>) -> String {
+        wallet.map_or_else(String::new, |Extension(AuthenticatedWallet(wallet))| wallet)
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/api/orders", post(signer))
+            .route_layer(middleware::from_fn(require_signed))
+            .route_layer(middleware::from_fn(authenticate))
+    }
+
+    fn signed(keypair: &Keypair, timestamp: i64, body: &str) -> Request {
+        let message = format!("{}POST/api/orders{}", timestamp, body);
+        Request::post("/api/orders")
+            .header(WALLET_HEADER, keypair.pubkey().to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, keypair.sign_message(message.as_bytes()).to_string())
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_request_is_rejected() {
+        let request = Request::post("/api/orders").body(Body::from("{}")).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_wallet_signed_request_is_accepted() {
+        let wallet = Keypair::new();
+        let request = signed(&wallet, Utc::now().timestamp_millis(), "{}");
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), MAX_SIGNED_BODY).await.unwrap();
+        assert_eq!(body, wallet.pubkey().to_string().as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_bad_wallet_signatures_are_rejected() {
+        let wallet = Keypair::new();
+        let now = Utc::now().timestamp_millis();
+
+        // Body changed after signing.
+        let mut request = signed(&wallet, now, "{}");
+        *request.body_mut() = Body::from("{\"size\":1}");
+        assert_eq!(app().oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Signed by another key.
+        let mut request = signed(&Keypair::new(), now, "{}");
+        request.headers_mut().insert(WALLET_HEADER, wallet.pubkey().to_string().parse().unwrap());
+        assert_eq!(app().oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Outside the freshness window.
+        let request = signed(&wallet, now - 2 * auth::SIGNATURE_WINDOW_MS, "{}");
+        assert_eq!(app().oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Wallet and delegate headers together.
+        let mut request = signed(&wallet, now, "{}");
+        request.headers_mut().insert(DELEGATE_HEADER, wallet.pubkey().to_string().parse().unwrap());
+        assert_eq!(app().oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_replay_key_covers_signer_timestamp_and_signature() {
+        let signed = SignedRequest {
+            signer: "dk_1".to_string(),
+            timestamp: "1700000000000".to_string(),
+            signature: "abc".to_string(),
+        };
+
+        assert_eq!(signed.replay_key(), "replay:dk_1:1700000000000:abc");
+    }
+}
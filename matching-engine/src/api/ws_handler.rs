@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
@@ -8,8 +9,17 @@ use axum::{
 };
 use futures_util::{SinkExt, StreamExt};
 
-use crate::types::WsMessage;
+use uuid::Uuid;
+
+use crate::orderbook::Orderbook;
+use crate::types::{CancelReason, Channel, SubscribeParams, WsMessage};
+use crate::websocket::subscription::{BookView, MAX_BOOK_DEPTH, MAX_THROTTLE, MIN_THROTTLE};
+use crate::websocket::{book_top, Session, Subscription, Topic};
 use crate::AppState;
+use crate::db;
+use super::handlers;
+
+const HEARTBEAT_CHECK_INTERVAL: Duration = Duration::from_millis(250);
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
@@ -20,29 +30,38 @@ pub async fn websocket_handler(
 
 async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let (mut sender, mut receiver) = socket.split();
-    
+
     let (client_id, mut ws_rx) = state.ws_manager.add_client().await;
-    
+
     tracing::info!("WebSocket client connected: {}", client_id);
 
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = ws_rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json)).await.is_err() {
-                    break;
-                }
+    let mut send_task = tokio::spawn(async move {
+        while let Some(frame) = ws_rx.recv().await {
+            if sender.send(Message::Text(frame.to_string())).await.is_err() {
+                break;
             }
         }
     });
 
     let state_clone = state.clone();
-    let recv_task = tokio::spawn(async move {
+    let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
-                Message::Text(text) => {
-                    if let Ok(ws_msg) = serde_json::from_str::<WsMessage>(&text) {
-                        handle_client_message(&state_clone, client_id, ws_msg).await;
+                Message::Text(text) => match parse_client_frame(&text) {
+                    Ok((id, ws_msg)) => {
+                        handle_client_message(&state_clone, client_id, id, ws_msg).await;
                     }
+                    Err((id, message)) => {
+                        let error = WsMessage::Error { id, message };
+                        state_clone.ws_manager.send_to_client(client_id, error).await;
+                    }
+                },
+                Message::Binary(_) => {
+                    let error = WsMessage::Error {
+                        id: None,
+                        message: "Binary frames are not supported".to_string(),
+                    };
+                    state_clone.ws_manager.send_to_client(client_id, error).await;
                 }
                 Message::Close(_) => break,
                 _ => {}
@@ -50,35 +69,314 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
         }
     });
 
+    let watchdog_state = state.clone();
+    let mut heartbeat_task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEARTBEAT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if watchdog_state.session_manager.heartbeat_expired(client_id).await {
+                break;
+            }
+        }
+    });
+
     tokio::select! {
-        _ = send_task => {},
-        _ = recv_task => {},
+        _ = &mut send_task => {},
+        _ = &mut recv_task => {},
+        _ = &mut heartbeat_task => {
+            tracing::warn!("WebSocket client {} missed its heartbeat", client_id);
+        },
     }
 
+    send_task.abort();
+    recv_task.abort();
+    heartbeat_task.abort();
+
     state.ws_manager.remove_client(client_id).await;
+    if let Some(session) = state.session_manager.end_session(client_id).await {
+        if session.is_armed() {
+            cancel_session_orders(&state, session).await;
+        }
+    }
     tracing::info!("WebSocket client disconnected: {}", client_id);
 }
 
-async fn handle_client_message(state: &Arc<AppState>, client_id: u64, msg: WsMessage) {
-    match msg {
-        WsMessage::Subscribe { market_id } => {
-            state.ws_manager.subscribe(client_id, market_id).await;
-            
-            let orderbook_manager = state.orderbook_manager.read().await;
-            if let Some(orderbook) = orderbook_manager.get(&market_id) {
-                let snapshot = orderbook.snapshot(20);
-                state.ws_manager.send_to_client(
+/// Dead man's switch: pulls every order of the session that is still resting.
+async fn cancel_session_orders(state: &Arc<AppState>, session: Session) {
+    for order_id in &session.order_ids {
+        let order = match db::get_order(&state.db_pool, order_id).await {
+            Ok(Some(order)) if order.remaining() > 0 => order,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::error!("Failed to load order {} for session cancel: {:?}", order_id, e);
+                continue;
+            }
+        };
+
+        match handlers::cancel_open_order(state, order, CancelReason::SessionDisconnect).await {
+            Ok(_) => tracing::info!(
+                "Cancelled order {} of session {} on disconnect",
+                order_id,
+                session.session_id
+            ),
+            Err(e) => tracing::debug!("Order {} not cancelled on disconnect: {}", order_id, e),
+        }
+    }
+}
+
+/// Splits a client frame into its optional request `id` and the message, or
+/// returns the `id` (if readable) and reason for an error frame.
+fn parse_client_frame(text: &str) -> Result<(Option<String>, WsMessage), (Option<String>, String)> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| (None, format!("Malformed JSON: {}", e)))?;
+
+    let id = match value.get("id") {
+        Some(serde_json::Value::String(id)) => Some(id.clone()),
+        Some(serde_json::Value::Number(id)) => Some(id.to_string()),
+        _ => None,
+    };
+
+    match serde_json::from_value::<WsMessage>(value) {
+        Ok(message) => Ok((id, message)),
+        Err(e) => Err((id, format!("Invalid message: {}", e))),
+    }
+}
+
+async fn handle_client_message(
+    state: &Arc<AppState>,
+    client_id: u64,
+    id: Option<String>,
+    msg: WsMessage,
+) {
+    let reply = match msg {
+        WsMessage::Subscribe(params) => {
+            match subscription_for(state, client_id, &params).await {
+                Ok((topic, subscription)) => {
+                    state.ws_manager
+                        .send_to_client(client_id, WsMessage::Subscribed { id, params: params.clone() })
+                        .await;
+                    start_subscription(state, client_id, &params, topic, subscription).await;
+                    tracing::debug!("Client {} subscribed to {:?}", client_id, params);
+                    return;
+                }
+                Err(message) => WsMessage::Error { id, message },
+            }
+        }
+        WsMessage::Unsubscribe { channel, market_id } => {
+            match topic_for(state, client_id, channel, market_id).await {
+                Ok(topic) if state.ws_manager.unsubscribe(client_id, &topic).await => {
+                    WsMessage::Unsubscribed { id, channel, market_id }
+                }
+                Ok(_) => WsMessage::Error { id, message: "Not subscribed".to_string() },
+                Err(message) => WsMessage::Error { id, message },
+            }
+        }
+        WsMessage::Ping => {
+            // A ping also keeps a heartbeat-armed session alive.
+            let _ = state.session_manager.heartbeat(client_id).await;
+            WsMessage::Pong { id, timestamp: chrono::Utc::now() }
+        }
+        WsMessage::AuthChallenge { wallet } => {
+            let message = state.session_manager.issue_challenge(client_id, &wallet).await;
+            WsMessage::Challenge { id, message }
+        }
+        WsMessage::Login { wallet, signature } => {
+            match state.session_manager.login(client_id, &wallet, &signature).await {
+                Ok(session) => {
+                    // Wallet channel subscriptions never carry over to another wallet.
+                    state.ws_manager.unsubscribe_wallet_topics(client_id).await;
+                    tracing::info!("Client {} logged in as {}", client_id, session.wallet);
+                    session_message(&session)
+                }
+                Err(e) => WsMessage::Error { id, message: e.to_string() },
+            }
+        }
+        WsMessage::SessionOptions { cancel_on_disconnect, heartbeat_timeout_ms } => {
+            match state.session_manager
+                .configure(
                     client_id,
-                    WsMessage::OrderbookSnapshot(snapshot),
-                ).await;
+                    cancel_on_disconnect,
+                    heartbeat_timeout_ms.map(Duration::from_millis),
+                )
+                .await
+            {
+                Ok(session) => session_message(&session),
+                Err(e) => WsMessage::Error { id, message: e.to_string() },
             }
-            
-            tracing::debug!("Client {} subscribed to market {}", client_id, market_id);
         }
-        WsMessage::Unsubscribe { market_id } => {
-            state.ws_manager.unsubscribe(client_id, market_id).await;
-            tracing::debug!("Client {} unsubscribed from market {}", client_id, market_id);
+        WsMessage::Heartbeat => match state.session_manager.heartbeat(client_id).await {
+            Ok(()) => return,
+            Err(e) => WsMessage::Error { id, message: e.to_string() },
+        },
+        _ => WsMessage::Error { id, message: "Unsupported message type".to_string() },
+    };
+
+    state.ws_manager.send_to_client(client_id, reply).await;
+}
+
+/// Resolves the topic of a channel: the market for public channels, the
+/// logged-in wallet for private ones.
+async fn topic_for(
+    state: &Arc<AppState>,
+    client_id: u64,
+    channel: Channel,
+    market_id: Option<Uuid>,
+) -> Result<Topic, String> {
+    if channel.is_private() {
+        let wallet = state.session_manager
+            .wallet_for_client(client_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(Topic::Wallet(wallet, channel));
+    }
+
+    let market_id = market_id.ok_or_else(|| "market_id is required for this channel".to_string())?;
+    Ok(Topic::Market(market_id, channel))
+}
+
+async fn subscription_for(
+    state: &Arc<AppState>,
+    client_id: u64,
+    params: &SubscribeParams,
+) -> Result<(Topic, Subscription), String> {
+    if let Some(depth) = params.depth {
+        if params.channel != Channel::BookL2 {
+            return Err("depth is only supported on book.L2".to_string());
+        }
+        if !(1..=MAX_BOOK_DEPTH).contains(&depth) {
+            return Err(format!("depth must be between 1 and {}", MAX_BOOK_DEPTH));
+        }
+    }
+
+    let throttle = params.throttle_ms.map(Duration::from_millis);
+    if let Some(throttle) = throttle {
+        if !params.channel.supports_throttle() {
+            return Err("throttle_ms is not supported on this channel".to_string());
+        }
+        if !(MIN_THROTTLE..=MAX_THROTTLE).contains(&throttle) {
+            return Err(format!(
+                "throttle_ms must be between {} and {}",
+                MIN_THROTTLE.as_millis(),
+                MAX_THROTTLE.as_millis()
+            ));
+        }
+    }
+
+    let topic = topic_for(state, client_id, params.channel, params.market_id).await?;
+    if let Topic::Market(market_id, _) = &topic {
+        match db::get_market(&state.db_pool, *market_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err("Market not found".to_string()),
+            Err(e) => {
+                tracing::error!("Failed to load market {}: {:?}", market_id, e);
+                return Err("Internal error".to_string());
+            }
+        }
+    }
+
+    Ok((topic, Subscription::new(params.depth, throttle)))
+}
+
+/// Registers the subscription and sends the channel's initial state.
+///
+/// Book channels subscribe and read their snapshot under the book lock, so
+/// every later update reaches the client after the snapshot. A book nobody
+/// has traded yet is sent empty at sequence 0.
+async fn start_subscription(
+    state: &Arc<AppState>,
+    client_id: u64,
+    params: &SubscribeParams,
+    topic: Topic,
+    subscription: Subscription,
+) {
+    if let Topic::Market(market_id, Channel::Ticker) = topic {
+        state.ws_manager.subscribe(client_id, topic, subscription).await;
+        let ticker = state.ticker_manager.ticker(market_id).await;
+        state.ws_manager.send_to_client(client_id, WsMessage::Ticker(ticker)).await;
+        return;
+    }
+
+    let Topic::Market(market_id, channel @ (Channel::BookL2 | Channel::BookL1)) = topic else {
+        state.ws_manager.subscribe(client_id, topic, subscription).await;
+        return;
+    };
+
+    let orderbook_manager = state.orderbook_manager.read().await;
+    let empty;
+    let orderbook = match orderbook_manager.get(&market_id) {
+        Some(orderbook) => orderbook,
+        None => {
+            empty = Orderbook::new(market_id);
+            &empty
         }
-        _ => {}
+    };
+
+    let depth = match channel {
+        Channel::BookL1 => Some(1),
+        _ => params.depth,
+    };
+    if let Some(depth) = depth {
+        state.ws_manager.init_book_view(orderbook, depth).await;
+    }
+    state.ws_manager.subscribe(client_id, topic, subscription).await;
+
+    let message = match channel {
+        Channel::BookL1 => {
+            let view = BookView {
+                sequence: orderbook.sequence,
+                bids: orderbook.get_bids(1),
+                asks: orderbook.get_asks(1),
+            };
+            WsMessage::BookTop(book_top(market_id, &view))
+        }
+        _ => WsMessage::OrderbookSnapshot(orderbook.snapshot(params.depth.unwrap_or(usize::MAX))),
+    };
+    state.ws_manager.send_to_client(client_id, message).await;
+}
+
+fn session_message(session: &Session) -> WsMessage {
+    WsMessage::Session {
+        session_id: session.session_id,
+        wallet: session.wallet.clone(),
+        cancel_on_disconnect: session.cancel_on_disconnect,
+        heartbeat_timeout_ms: session.heartbeat_timeout.map(|t| t.as_millis() as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_client_frame_keeps_request_id() {
+        let market_id = Uuid::new_v4();
+        let text = format!(
+            r#"{{"id":"7","type":"subscribe","data":{{"channel":"candles.1m","market_id":"{}","throttle_ms":250}}}}"#,
+            market_id
+        );
+        let (id, message) = parse_client_frame(&text).unwrap();
+        assert_eq!(id.as_deref(), Some("7"));
+        let WsMessage::Subscribe(params) = message else {
+            panic!("expected subscribe");
+        };
+        assert_eq!(params.channel, Channel::Candles(crate::types::CandleInterval::M1));
+        assert_eq!(params.market_id, Some(market_id));
+        assert_eq!(params.throttle_ms, Some(250));
+
+        let (id, message) = parse_client_frame(r#"{"id":8,"type":"ping"}"#).unwrap();
+        assert_eq!(id.as_deref(), Some("8"));
+        assert!(matches!(message, WsMessage::Ping));
+    }
+
+    #[test]
+    fn test_parse_client_frame_reports_errors() {
+        let (id, message) = parse_client_frame("{not json").unwrap_err();
+        assert_eq!(id, None);
+        assert!(message.starts_with("Malformed JSON"));
+
+        let text = r#"{"id":"9","type":"subscribe","data":{"channel":"book.L3"}}"#;
+        let (id, message) = parse_client_frame(text).unwrap_err();
+        assert_eq!(id.as_deref(), Some("9"));
+        assert!(message.contains("Unknown channel"));
     }
 }
@@ -0,0 +1,193 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::types::ApiKeyScope;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verifies a base58 ed25519 `signature` of `message` made by the `wallet` key.
+pub fn verify_wallet_signature(wallet: &str, message: &str, signature: &str) -> bool {
+    let Ok(pubkey) = Pubkey::from_str(wallet) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_str(signature) else {
+        return false;
+    };
+    signature.verify(pubkey.as_ref(), message.as_bytes())
+}
+
+/// What a wallet signs to register `referrer` as its referrer.
+pub fn referral_message(wallet: &str, referrer: &str) -> String {
+    format!("DCEX referral\nwallet: {}\nreferrer: {}", wallet, referrer)
+}
+
+/// How far a signed timestamp may be from the engine clock, either way.
+pub const SIGNATURE_WINDOW_MS: i64 = 30_000;
+
+pub fn is_fresh(timestamp_ms: i64, now_ms: i64) -> bool {
+    (now_ms - timestamp_ms).abs() <= SIGNATURE_WINDOW_MS
+}
+
+/// What a wallet signs to create an API key with these settings.
+pub fn api_key_message(
+    wallet: &str,
+    label: &str,
+    scopes: &[ApiKeyScope],
+    ip_allowlist: &[String],
+    expires_at: Option<DateTime<Utc>>,
+    timestamp: i64,
+) -> String {
+    let scopes: Vec<_> = scopes.iter().map(ApiKeyScope::as_str).collect();
+    let expires_at = expires_at.map_or_else(|| "never".to_string(), |expires_at| expires_at.to_rfc3339());
+    format!(
+        "DCEX API key\nwallet: {}\nlabel: {}\nscopes: {}\nip_allowlist: {}\nexpires_at: {}\ntimestamp: {}",
+        wallet,
+        label,
+        scopes.join(","),
+        ip_allowlist.join(","),
+        expires_at,
+        timestamp
+    )
+}
+
+/// What a wallet signs to revoke one of its API keys.
+pub fn revoke_api_key_message(wallet: &str, key_id: &str, timestamp: i64) -> String {
+    format!("DCEX revoke API key\nwallet: {}\nkey: {}\ntimestamp: {}", wallet, key_id, timestamp)
+}
+
+/// The secret of `key_id`, derived from the engine's master secret so that
+/// Postgres only ever holds its hash.
+pub fn api_key_secret(master_secret: &str, key_id: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(master_secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(key_id.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn secret_hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Checks a hex HMAC-SHA256 over `timestamp + method + path + body`, keyed
+/// with the API key secret.
+pub fn verify_request_signature(
+    secret: &str,
+    timestamp: &str,
+    method: &str,
+    path_and_query: &str,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(method.as_bytes());
+    mac.update(path_and_query.as_bytes());
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Checks a base58 ed25519 signature over `timestamp + method + path + body`
+/// made by a wallet or a delegate's session key.
+pub fn verify_signed_request(
+    signer: &str,
+    timestamp: &str,
+    method: &str,
+    path_and_query: &str,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let (Ok(pubkey), Ok(signature)) = (Pubkey::from_str(signer), Signature::from_str(signature)) else {
+        return false;
+    };
+    let mut message = Vec::with_capacity(timestamp.len() + method.len() + path_and_query.len() + body.len());
+    message.extend_from_slice(timestamp.as_bytes());
+    message.extend_from_slice(method.as_bytes());
+    message.extend_from_slice(path_and_query.as_bytes());
+    message.extend_from_slice(body);
+    signature.verify(pubkey.as_ref(), &message)
+}
+
+/// An allowlist entry: a single address or a CIDR range.
+pub fn parse_ip_range(entry: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match entry.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+        None => (entry, None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+    (prefix <= max_prefix).then_some((address, prefix))
+}
+
+/// Whether `ip` matches an entry of `allowlist`. An empty list allows all.
+pub fn ip_allowed(allowlist: &[String], ip: IpAddr) -> bool {
+    allowlist.is_empty()
+        || allowlist
+            .iter()
+            .filter_map(|entry| parse_ip_range(entry))
+            .any(|(network, prefix)| in_range(network, prefix, ip))
+}
+
+fn in_range(network: IpAddr, prefix: u8, ip: IpAddr) -> bool {
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_signature_round_trip() {
+        let secret = api_key_secret("master", "dk_1");
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(b"1700000000000POST/api/orders{}");
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_request_signature(&secret, "1700000000000", "POST", "/api/orders", b"{}", &signature));
+        assert!(!verify_request_signature(&secret, "1700000000001", "POST", "/api/orders", b"{}", &signature));
+        assert!(!verify_request_signature(&api_key_secret("other", "dk_1"), "1700000000000", "POST", "/api/orders", b"{}", &signature));
+    }
+
+    #[test]
+    fn test_signed_request_round_trip() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let session_key = Keypair::new();
+        let delegate = session_key.pubkey().to_string();
+        let signature = session_key.sign_message(b"1700000000000DELETE/api/orders/42").to_string();
+
+        assert!(verify_signed_request(&delegate, "1700000000000", "DELETE", "/api/orders/42", b"", &signature));
+        assert!(!verify_signed_request(&delegate, "1700000000000", "DELETE", "/api/orders/43", b"", &signature));
+        let other = Keypair::new().pubkey().to_string();
+        assert!(!verify_signed_request(&other, "1700000000000", "DELETE", "/api/orders/42", b"", &signature));
+    }
+
+    #[test]
+    fn test_ip_allowlist() {
+        let allowlist = vec!["10.0.0.0/8".to_string(), "2001:db8::1".to_string()];
+
+        assert!(ip_allowed(&allowlist, "10.20.30.40".parse().unwrap()));
+        assert!(!ip_allowed(&allowlist, "11.0.0.1".parse().unwrap()));
+        assert!(ip_allowed(&allowlist, "2001:db8::1".parse().unwrap()));
+        assert!(!ip_allowed(&allowlist, "2001:db8::2".parse().unwrap()));
+        assert!(ip_allowed(&[], "11.0.0.1".parse().unwrap()));
+        assert!(ip_allowed(&["0.0.0.0/0".to_string()], "11.0.0.1".parse().unwrap()));
+        assert_eq!(parse_ip_range("10.0.0.0/33"), None);
+    }
+}
@@ -1,5 +1,9 @@
+use std::str::FromStr;
+use std::time::Duration;
 use anyhow::Result;
 
+use crate::ratelimit::{BucketLimit, OrderToTradePolicy, RateLimitConfig};
+
 #[derive(Clone)]
 pub struct Config {
     pub server_addr: String,
@@ -7,6 +11,19 @@ pub struct Config {
     pub redis_url: String,
     pub solana_rpc_url: String,
     pub program_id: String,
+    /// Keypair file of the markets' authority. Market creation and on-chain
+    /// settlement are refused when unset.
+    pub authority_keypair_path: Option<String>,
+    /// Bearer token for `/api/admin`; admin routes are closed when unset.
+    pub admin_token: Option<String>,
+    /// Master secret API key secrets are derived from; API keys are
+    /// disabled when unset.
+    pub api_key_secret: Option<String>,
+    /// Readiness fails once the RPC node trails the cluster by more slots.
+    pub max_slot_lag: u64,
+    /// Readiness fails once a trade has waited this long for settlement.
+    pub max_settlement_age_secs: i64,
+    pub rate_limits: RateLimitConfig,
 }
 
 impl Config {
@@ -21,6 +38,38 @@ impl Config {
                 .unwrap_or_else(|_| "http://localhost:8899".to_string()),
             program_id: std::env::var("PROGRAM_ID")
                 .unwrap_or_else(|_| "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS".to_string()),
+            authority_keypair_path: std::env::var("AUTHORITY_KEYPAIR_PATH").ok().filter(|path| !path.is_empty()),
+            admin_token: std::env::var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty()),
+            api_key_secret: std::env::var("API_KEY_SECRET").ok().filter(|secret| !secret.is_empty()),
+            max_slot_lag: env_or("MAX_SLOT_LAG", 150),
+            max_settlement_age_secs: env_or("MAX_SETTLEMENT_AGE_SECS", 300),
+            rate_limits: RateLimitConfig {
+                place: bucket_limit("PLACE", 20, 10.0),
+                cancel: bucket_limit("CANCEL", 40, 20.0),
+                query: bucket_limit("QUERY", 60, 30.0),
+                order_to_trade: OrderToTradePolicy {
+                    window: Duration::from_secs(env_or("OTR_WINDOW_SECS", 300)),
+                    min_orders: env_or("OTR_MIN_ORDERS", 500),
+                    max_ratio: env_or("OTR_MAX_RATIO", 50),
+                    restriction: Duration::from_secs(env_or("OTR_RESTRICTION_SECS", 600)),
+                },
+                trust_forwarded_for: env_or("TRUST_FORWARDED_FOR", false),
+            },
         })
     }
 }
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// `RATE_LIMIT_<BUDGET>_BURST` and `RATE_LIMIT_<BUDGET>_PER_SEC`.
+fn bucket_limit(budget: &str, burst: u32, per_second: f64) -> BucketLimit {
+    BucketLimit {
+        burst: env_or(&format!("RATE_LIMIT_{}_BURST", budget), burst),
+        per_second: env_or(&format!("RATE_LIMIT_{}_PER_SEC", budget), per_second),
+    }
+}
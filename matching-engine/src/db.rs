@@ -1,8 +1,13 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-use crate::types::{Market, Order, OrderSide, OrderStatus, Trade, Deposit, Withdrawal};
+use crate::types::{
+    ApiKey, ApiKeyScope, Candle, CandleInterval, FeeLedgerEntry, FeeOverride, FeeReconciliation, FeeTier, LiquidityRole, Market, MatchingAlgorithm, OnChainMarket, Order,
+    OrderSide, OrderStatus, OrderType, PageParams, Referral, ReferralEarnings, RevenueTotals,
+    SettlementStatus, Trade, Deposit, Withdrawal,
+};
 
 pub async fn get_market(pool: &PgPool, market_id: Uuid) -> Result<Option<Market>> {
     let market = sqlx::query_as!(
@@ -11,7 +16,9 @@ pub async fn get_market(pool: &PgPool, market_id: Uuid) -> Result<Option<Market>
         SELECT 
             id, base_mint, quote_mint, base_decimals, quote_decimals,
             min_order_size, tick_size, maker_fee_bps, taker_fee_bps,
-            is_active, created_at
+            is_active, matching_algorithm as "matching_algorithm: MatchingAlgorithm",
+            fifo_slice_bps, market_address, base_vault, quote_vault, fee_recipient,
+            referral_share_bps, created_at
         FROM markets
         WHERE id = $1
         "#,
@@ -30,7 +37,9 @@ pub async fn get_active_markets(pool: &PgPool) -> Result<Vec<Market>> {
         SELECT 
             id, base_mint, quote_mint, base_decimals, quote_decimals,
             min_order_size, tick_size, maker_fee_bps, taker_fee_bps,
-            is_active, created_at
+            is_active, matching_algorithm as "matching_algorithm: MatchingAlgorithm",
+            fifo_slice_bps, market_address, base_vault, quote_vault, fee_recipient,
+            referral_share_bps, created_at
         FROM markets
         WHERE is_active = true
         ORDER BY created_at DESC
@@ -42,6 +51,7 @@ pub async fn get_active_markets(pool: &PgPool) -> Result<Vec<Market>> {
     Ok(markets)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_order(
     pool: &PgPool,
     order_id: &str,
@@ -50,29 +60,48 @@ pub async fn create_order(
     side: OrderSide,
     price: i64,
     size: i64,
+    order_type: OrderType,
+    trigger_price: Option<i64>,
+    display_size: Option<i64>,
+    expires_at: Option<DateTime<Utc>>,
 ) -> Result<Order> {
     let side_str = match side {
         OrderSide::Buy => "buy",
         OrderSide::Sell => "sell",
     };
+    let order_type_str = match order_type {
+        OrderType::Limit => "limit",
+        OrderType::StopMarket => "stopmarket",
+        OrderType::StopLimit => "stoplimit",
+        OrderType::TakeProfitMarket => "takeprofitmarket",
+        OrderType::TakeProfitLimit => "takeprofitlimit",
+    };
     
     let order = sqlx::query_as!(
         Order,
         r#"
-        INSERT INTO orders (order_id, user_wallet, market_id, side, price, size, filled, status)
-        VALUES ($1, $2, $3, $4, $5, $6, 0, 'pending')
+        INSERT INTO orders (
+            order_id, user_wallet, market_id, side, price, size, filled, status,
+            order_type, trigger_price, display_size, expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, 0, 'pending', $7, $8, $9, $10)
         RETURNING 
             id, order_id, user_wallet, market_id,
             side as "side: OrderSide", price, size, filled,
             status as "status: OrderStatus",
-            on_chain_signature, created_at, updated_at
+            order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+            on_chain_signature, expires_at, created_at, updated_at
         "#,
         order_id,
         user_wallet,
         market_id,
         side_str,
         price,
-        size
+        size,
+        order_type_str,
+        trigger_price,
+        display_size,
+        expires_at
     )
     .fetch_one(pool)
     .await?;
@@ -80,6 +109,87 @@ pub async fn create_order(
     Ok(order)
 }
 
+/// Inserts or refreshes the row for an on-chain market. Every field the
+/// program stores is taken from `on_chain`; engine-only settings are kept on
+/// refresh.
+pub async fn upsert_market(
+    pool: &PgPool,
+    on_chain: &OnChainMarket,
+    matching_algorithm: MatchingAlgorithm,
+    fifo_slice_bps: i16,
+) -> Result<Market> {
+    let to_i64 = |value: u64, name: &str| {
+        i64::try_from(value)
+            .map_err(|_| AppError::InvalidRequest(format!("On-chain {} {} is out of range", name, value)))
+    };
+    let to_i16 = |value: u16, name: &str| {
+        i16::try_from(value)
+            .map_err(|_| AppError::InvalidRequest(format!("On-chain {} {} is out of range", name, value)))
+    };
+    let min_order_size = to_i64(on_chain.min_order_size, "min_order_size")?;
+    let tick_size = to_i64(on_chain.tick_size, "tick_size")?;
+    let maker_fee_bps = to_i16(on_chain.maker_fee_bps, "maker_fee_bps")?;
+    let taker_fee_bps = to_i16(on_chain.taker_fee_bps, "taker_fee_bps")?;
+    let referral_share_bps = to_i16(on_chain.referral_share_bps, "referral_share_bps")?;
+    let matching_algorithm_str = match matching_algorithm {
+        MatchingAlgorithm::Fifo => "fifo",
+        MatchingAlgorithm::ProRata => "prorata",
+        MatchingAlgorithm::Hybrid => "hybrid",
+    };
+
+    let market = sqlx::query_as!(
+        Market,
+        r#"
+        INSERT INTO markets (
+            base_mint, quote_mint, base_decimals, quote_decimals,
+            min_order_size, tick_size, maker_fee_bps, taker_fee_bps, is_active,
+            matching_algorithm, fifo_slice_bps,
+            market_address, base_vault, quote_vault, fee_recipient, referral_share_bps
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+        ON CONFLICT (base_mint, quote_mint) DO UPDATE SET
+            base_decimals = EXCLUDED.base_decimals,
+            quote_decimals = EXCLUDED.quote_decimals,
+            min_order_size = EXCLUDED.min_order_size,
+            tick_size = EXCLUDED.tick_size,
+            maker_fee_bps = EXCLUDED.maker_fee_bps,
+            taker_fee_bps = EXCLUDED.taker_fee_bps,
+            is_active = EXCLUDED.is_active,
+            market_address = EXCLUDED.market_address,
+            base_vault = EXCLUDED.base_vault,
+            quote_vault = EXCLUDED.quote_vault,
+            fee_recipient = EXCLUDED.fee_recipient,
+            referral_share_bps = EXCLUDED.referral_share_bps
+        RETURNING
+            id, base_mint, quote_mint, base_decimals, quote_decimals,
+            min_order_size, tick_size, maker_fee_bps, taker_fee_bps,
+            is_active, matching_algorithm as "matching_algorithm: MatchingAlgorithm",
+            fifo_slice_bps, market_address, base_vault, quote_vault, fee_recipient,
+            referral_share_bps, created_at
+        "#,
+        on_chain.base_mint,
+        on_chain.quote_mint,
+        on_chain.base_decimals as i16,
+        on_chain.quote_decimals as i16,
+        min_order_size,
+        tick_size,
+        maker_fee_bps,
+        taker_fee_bps,
+        on_chain.is_active,
+        matching_algorithm_str,
+        fifo_slice_bps,
+        on_chain.address,
+        on_chain.base_vault,
+        on_chain.quote_vault,
+        on_chain.fee_recipient,
+        referral_share_bps
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(market)
+}
+
 pub async fn get_order(pool: &PgPool, order_id: &str) -> Result<Option<Order>> {
     let order = sqlx::query_as!(
         Order,
@@ -88,7 +198,8 @@ pub async fn get_order(pool: &PgPool, order_id: &str) -> Result<Option<Order>> {
             id, order_id, user_wallet, market_id,
             side as "side: OrderSide", price, size, filled,
             status as "status: OrderStatus",
-            on_chain_signature, created_at, updated_at
+            order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+            on_chain_signature, expires_at, created_at, updated_at
         FROM orders
         WHERE order_id = $1
         "#,
@@ -100,6 +211,29 @@ pub async fn get_order(pool: &PgPool, order_id: &str) -> Result<Option<Order>> {
     Ok(order)
 }
 
+/// Orders still resting or waiting on their trigger, oldest first, so they
+/// keep their time priority when the books are rebuilt.
+pub async fn get_open_orders(pool: &PgPool) -> Result<Vec<Order>> {
+    let orders = sqlx::query_as!(
+        Order,
+        r#"
+        SELECT 
+            id, order_id, user_wallet, market_id,
+            side as "side: OrderSide", price, size, filled,
+            status as "status: OrderStatus",
+            order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+            on_chain_signature, expires_at, created_at, updated_at
+        FROM orders
+        WHERE status IN ('pending', 'partiallyfilled')
+        ORDER BY created_at, id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(orders)
+}
+
 pub async fn update_order_status(
     pool: &PgPool,
     order_id: &str,
@@ -111,6 +245,7 @@ pub async fn update_order_status(
         OrderStatus::PartiallyFilled => "partiallyfilled",
         OrderStatus::Filled => "filled",
         OrderStatus::Cancelled => "cancelled",
+        OrderStatus::Expired => "expired",
     };
     
     let order = sqlx::query_as!(
@@ -123,7 +258,8 @@ pub async fn update_order_status(
             id, order_id, user_wallet, market_id,
             side as "side: OrderSide", price, size, filled,
             status as "status: OrderStatus",
-            on_chain_signature, created_at, updated_at
+            order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+            on_chain_signature, expires_at, created_at, updated_at
         "#,
         order_id,
         status_str,
@@ -135,12 +271,69 @@ pub async fn update_order_status(
     Ok(order)
 }
 
+/// Adds `fill` to a resting order. Returns whether it is now fully filled.
+pub async fn fill_order(pool: &PgPool, order_id: &str, fill: i64) -> Result<bool> {
+    let filled = sqlx::query_scalar!(
+        r#"
+        UPDATE orders
+        SET filled = filled + $2,
+            status = CASE WHEN filled + $2 >= size THEN 'filled' ELSE 'partiallyfilled' END,
+            updated_at = NOW()
+        WHERE order_id = $1
+        RETURNING filled >= size AS "filled!"
+        "#,
+        order_id,
+        fill
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(filled)
+}
+
+pub async fn mark_order_triggered(pool: &PgPool, order_id: &str) -> Result<Order> {
+    let order = sqlx::query_as!(
+        Order,
+        r#"
+        UPDATE orders
+        SET triggered_at = NOW(), updated_at = NOW()
+        WHERE order_id = $1
+        RETURNING 
+            id, order_id, user_wallet, market_id,
+            side as "side: OrderSide", price, size, filled,
+            status as "status: OrderStatus",
+            order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+            on_chain_signature, expires_at, created_at, updated_at
+        "#,
+        order_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(order)
+}
+
 pub async fn get_user_orders(
     pool: &PgPool,
     user_wallet: &str,
     market_id: Option<Uuid>,
+    status: Option<OrderStatus>,
+    side: Option<OrderSide>,
+    page: &PageParams,
 ) -> Result<Vec<Order>> {
-    let orders = if let Some(mid) = market_id {
+    let status_str = status.map(|status| match status {
+        OrderStatus::Pending => "pending",
+        OrderStatus::PartiallyFilled => "partiallyfilled",
+        OrderStatus::Filled => "filled",
+        OrderStatus::Cancelled => "cancelled",
+        OrderStatus::Expired => "expired",
+    });
+    let side_str = side.map(|side| match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    });
+
+    let orders = if page.after.is_some() {
         sqlx::query_as!(
             Order,
             r#"
@@ -148,14 +341,29 @@ pub async fn get_user_orders(
                 id, order_id, user_wallet, market_id,
                 side as "side: OrderSide", price, size, filled,
                 status as "status: OrderStatus",
-                on_chain_signature, created_at, updated_at
+                order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+                on_chain_signature, expires_at, created_at, updated_at
             FROM orders
-            WHERE user_wallet = $1 AND market_id = $2
-            ORDER BY created_at DESC
-            LIMIT 100
+            WHERE user_wallet = $1
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::VARCHAR IS NULL OR status = $3)
+                AND ($4::VARCHAR IS NULL OR side = $4)
+                AND ($5::BIGINT IS NULL OR id < $5)
+                AND ($6::BIGINT IS NULL OR id > $6)
+                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
+                AND ($8::TIMESTAMPTZ IS NULL OR created_at < $8)
+            ORDER BY id ASC
+            LIMIT $9
             "#,
             user_wallet,
-            mid
+            market_id,
+            status_str,
+            side_str,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
@@ -167,21 +375,38 @@ pub async fn get_user_orders(
                 id, order_id, user_wallet, market_id,
                 side as "side: OrderSide", price, size, filled,
                 status as "status: OrderStatus",
-                on_chain_signature, created_at, updated_at
+                order_type as "order_type: OrderType", trigger_price, triggered_at, display_size,
+                on_chain_signature, expires_at, created_at, updated_at
             FROM orders
             WHERE user_wallet = $1
-            ORDER BY created_at DESC
-            LIMIT 100
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::VARCHAR IS NULL OR status = $3)
+                AND ($4::VARCHAR IS NULL OR side = $4)
+                AND ($5::BIGINT IS NULL OR id < $5)
+                AND ($6::BIGINT IS NULL OR id > $6)
+                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
+                AND ($8::TIMESTAMPTZ IS NULL OR created_at < $8)
+            ORDER BY id DESC
+            LIMIT $9
             "#,
-            user_wallet
+            user_wallet,
+            market_id,
+            status_str,
+            side_str,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
     };
     
-    Ok(orders)
+    Ok(page.newest_first(orders))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_trade(
     pool: &PgPool,
     market_id: Uuid,
@@ -191,22 +416,36 @@ pub async fn create_trade(
     taker_wallet: &str,
     price: i64,
     size: i64,
+    taker_side: OrderSide,
     maker_fee: i64,
     taker_fee: i64,
 ) -> Result<Trade> {
+    let taker_side_str = match taker_side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    };
+
+    // Callers hold the market's book lock, so numbering is serial per market.
     let trade = sqlx::query_as!(
         Trade,
         r#"
         INSERT INTO trades (
             market_id, maker_order_id, taker_order_id,
             maker_wallet, taker_wallet, price, size,
-            maker_fee, taker_fee
+            taker_side, sequence, maker_fee, taker_fee
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8,
+            COALESCE((SELECT MAX(sequence) FROM trades WHERE market_id = $1), 0) + 1,
+            $9, $10
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         RETURNING 
             id, market_id, maker_order_id, taker_order_id,
             maker_wallet, taker_wallet, price, size,
-            maker_fee, taker_fee, settlement_signature, created_at
+            taker_side as "taker_side: OrderSide", sequence,
+            maker_fee, taker_fee,
+            settlement_status as "settlement_status: SettlementStatus",
+            settlement_signature, created_at
         "#,
         market_id,
         maker_order_id,
@@ -215,6 +454,7 @@ pub async fn create_trade(
         taker_wallet,
         price,
         size,
+        taker_side_str,
         maker_fee,
         taker_fee
     )
@@ -227,22 +467,162 @@ pub async fn create_trade(
 pub async fn get_recent_trades(
     pool: &PgPool,
     market_id: Uuid,
-    limit: i64,
+    page: &PageParams,
+) -> Result<Vec<Trade>> {
+    let trades = if page.after.is_some() {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            SELECT 
+                id, market_id, maker_order_id, taker_order_id,
+                maker_wallet, taker_wallet, price, size,
+                taker_side as "taker_side: OrderSide", sequence,
+                maker_fee, taker_fee,
+                settlement_status as "settlement_status: SettlementStatus",
+                settlement_signature, created_at
+            FROM trades
+            WHERE market_id = $1
+                AND ($2::BIGINT IS NULL OR id < $2)
+                AND ($3::BIGINT IS NULL OR id > $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+            ORDER BY id ASC
+            LIMIT $6
+            "#,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
+        )
+        .fetch_all(pool)
+        .await?
+    } else {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            SELECT 
+                id, market_id, maker_order_id, taker_order_id,
+                maker_wallet, taker_wallet, price, size,
+                taker_side as "taker_side: OrderSide", sequence,
+                maker_fee, taker_fee,
+                settlement_status as "settlement_status: SettlementStatus",
+                settlement_signature, created_at
+            FROM trades
+            WHERE market_id = $1
+                AND ($2::BIGINT IS NULL OR id < $2)
+                AND ($3::BIGINT IS NULL OR id > $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+            ORDER BY id DESC
+            LIMIT $6
+            "#,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
+        )
+        .fetch_all(pool)
+        .await?
+    };
+    
+    Ok(page.newest_first(trades))
+}
+
+/// Trades where the wallet was maker or taker.
+pub async fn get_user_trades(
+    pool: &PgPool,
+    user_wallet: &str,
+    market_id: Option<Uuid>,
+    page: &PageParams,
 ) -> Result<Vec<Trade>> {
+    let trades = if page.after.is_some() {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            SELECT 
+                id, market_id, maker_order_id, taker_order_id,
+                maker_wallet, taker_wallet, price, size,
+                taker_side as "taker_side: OrderSide", sequence,
+                maker_fee, taker_fee,
+                settlement_status as "settlement_status: SettlementStatus",
+                settlement_signature, created_at
+            FROM trades
+            WHERE (maker_wallet = $1 OR taker_wallet = $1)
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id ASC
+            LIMIT $7
+            "#,
+            user_wallet,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
+        )
+        .fetch_all(pool)
+        .await?
+    } else {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            SELECT 
+                id, market_id, maker_order_id, taker_order_id,
+                maker_wallet, taker_wallet, price, size,
+                taker_side as "taker_side: OrderSide", sequence,
+                maker_fee, taker_fee,
+                settlement_status as "settlement_status: SettlementStatus",
+                settlement_signature, created_at
+            FROM trades
+            WHERE (maker_wallet = $1 OR taker_wallet = $1)
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id DESC
+            LIMIT $7
+            "#,
+            user_wallet,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
+        )
+        .fetch_all(pool)
+        .await?
+    };
+    
+    Ok(page.newest_first(trades))
+}
+
+/// Trades executed at or after `since`, in execution order per market.
+pub async fn get_trades_since(pool: &PgPool, since: DateTime<Utc>) -> Result<Vec<Trade>> {
     let trades = sqlx::query_as!(
         Trade,
         r#"
         SELECT 
             id, market_id, maker_order_id, taker_order_id,
             maker_wallet, taker_wallet, price, size,
-            maker_fee, taker_fee, settlement_signature, created_at
+            taker_side as "taker_side: OrderSide", sequence,
+            maker_fee, taker_fee,
+            settlement_status as "settlement_status: SettlementStatus",
+            settlement_signature, created_at
         FROM trades
-        WHERE market_id = $1
-        ORDER BY created_at DESC
-        LIMIT $2
+        WHERE created_at >= $1
+        ORDER BY market_id, sequence
         "#,
-        market_id,
-        limit
+        since
     )
     .fetch_all(pool)
     .await?;
@@ -250,6 +630,162 @@ pub async fn get_recent_trades(
     Ok(trades)
 }
 
+/// Price of the latest trade of each market that has traded.
+pub async fn get_last_prices(pool: &PgPool) -> Result<Vec<(Uuid, i64)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (market_id) market_id, price
+        FROM trades
+        ORDER BY market_id, sequence DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| (row.market_id, row.price)).collect())
+}
+
+/// Execution time of the oldest trade still waiting for settlement.
+pub async fn get_oldest_pending_settlement(pool: &PgPool) -> Result<Option<DateTime<Utc>>> {
+    let oldest = sqlx::query_scalar!(
+        "SELECT MIN(created_at) FROM trades WHERE settlement_status = 'pending'"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(oldest)
+}
+
+pub async fn ping(pool: &PgPool) -> Result<()> {
+    sqlx::query_scalar!("SELECT 1").fetch_one(pool).await?;
+    Ok(())
+}
+
+/// Folds one trade into the stored candle for its bucket and returns the
+/// result. Callers apply a market's trades in sequence order, so the latest
+/// price always becomes the close.
+pub async fn record_candle_trade(
+    pool: &PgPool,
+    market_id: Uuid,
+    interval: CandleInterval,
+    open_time: DateTime<Utc>,
+    price: i64,
+    size: i64,
+) -> Result<Candle> {
+    let candle = sqlx::query_as!(
+        Candle,
+        r#"
+        INSERT INTO candles (
+            market_id, timeframe, open_time,
+            open, high, low, close, volume, trade_count
+        )
+        VALUES ($1, $2, $3, $4, $4, $4, $4, $5, 1)
+        ON CONFLICT (market_id, timeframe, open_time) DO UPDATE SET
+            high = GREATEST(candles.high, EXCLUDED.high),
+            low = LEAST(candles.low, EXCLUDED.low),
+            close = EXCLUDED.close,
+            volume = candles.volume + EXCLUDED.volume,
+            trade_count = candles.trade_count + 1
+        RETURNING
+            market_id, timeframe as "interval: CandleInterval", open_time,
+            open, high, low, close, volume, trade_count
+        "#,
+        market_id,
+        interval.as_str(),
+        open_time,
+        price,
+        size
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(candle)
+}
+
+/// Rebuilds `interval` candles from the trades table, starting at the last
+/// stored candle of each market. Safe to run repeatedly.
+pub async fn backfill_candles(pool: &PgPool, interval: CandleInterval) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO candles (
+            market_id, timeframe, open_time,
+            open, high, low, close, volume, trade_count
+        )
+        SELECT
+            market_id, $1, bucket,
+            (ARRAY_AGG(price ORDER BY sequence))[1],
+            MAX(price),
+            MIN(price),
+            (ARRAY_AGG(price ORDER BY sequence DESC))[1],
+            SUM(size)::BIGINT,
+            COUNT(*)
+        FROM (
+            SELECT
+                t.market_id, t.price, t.size, t.sequence,
+                DATE_BIN(MAKE_INTERVAL(secs => $2), t.created_at, TIMESTAMPTZ 'epoch') AS bucket
+            FROM trades t
+            WHERE t.created_at >= COALESCE(
+                (SELECT MAX(c.open_time) FROM candles c
+                 WHERE c.market_id = t.market_id AND c.timeframe = $1),
+                '-infinity'
+            )
+        ) bucketed
+        GROUP BY market_id, bucket
+        ON CONFLICT (market_id, timeframe, open_time) DO UPDATE SET
+            open = EXCLUDED.open,
+            high = EXCLUDED.high,
+            low = EXCLUDED.low,
+            close = EXCLUDED.close,
+            volume = EXCLUDED.volume,
+            trade_count = EXCLUDED.trade_count
+        "#,
+        interval.as_str(),
+        interval.seconds() as f64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_candles(
+    pool: &PgPool,
+    market_id: Uuid,
+    interval: CandleInterval,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Candle>> {
+    let candles = sqlx::query_as!(
+        Candle,
+        r#"
+        SELECT
+            market_id, timeframe as "interval: CandleInterval", open_time,
+            open, high, low, close, volume, trade_count
+        FROM candles
+        WHERE market_id = $1 AND timeframe = $2 AND open_time >= $3 AND open_time < $4
+        ORDER BY open_time
+        LIMIT $5
+        "#,
+        market_id,
+        interval.as_str(),
+        from,
+        to,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(candles)
+}
+
+/// SQLSTATE 23505, raised when an insert hits a unique constraint.
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|db_error| db_error.code())
+        .is_some_and(|code| code == "23505")
+}
+
 pub async fn create_deposit(
     pool: &PgPool,
     user_wallet: &str,
@@ -275,8 +811,11 @@ pub async fn create_deposit(
     .fetch_one(pool)
     .await
     .map_err(|e| {
-        if e.to_string().contains("duplicate") || e.to_string().contains("unique") {
-            AppError::InvalidOrder("Deposit with this signature already exists".to_string())
+        if is_unique_violation(&e) {
+            AppError::DuplicateSignature {
+                kind: "Deposit",
+                signature: signature.to_string(),
+            }
         } else {
             AppError::Database(e)
         }
@@ -310,8 +849,11 @@ pub async fn create_withdrawal(
     .fetch_one(pool)
     .await
     .map_err(|e| {
-        if e.to_string().contains("duplicate") || e.to_string().contains("unique") {
-            AppError::InvalidOrder("Withdrawal with this signature already exists".to_string())
+        if is_unique_violation(&e) {
+            AppError::DuplicateSignature {
+                kind: "Withdrawal",
+                signature: signature.to_string(),
+            }
         } else {
             AppError::Database(e)
         }
@@ -324,20 +866,31 @@ pub async fn get_user_deposits(
     pool: &PgPool,
     user_wallet: &str,
     market_id: Option<Uuid>,
+    page: &PageParams,
 ) -> Result<Vec<Deposit>> {
-    let deposits = if let Some(mid) = market_id {
+    let deposits = if page.after.is_some() {
         sqlx::query_as!(
             Deposit,
             r#"
             SELECT 
                 id, user_wallet, market_id, amount, is_base, signature, created_at
             FROM deposits
-            WHERE user_wallet = $1 AND market_id = $2
-            ORDER BY created_at DESC
-            LIMIT 100
+            WHERE user_wallet = $1
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id ASC
+            LIMIT $7
             "#,
             user_wallet,
-            mid
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
@@ -349,36 +902,58 @@ pub async fn get_user_deposits(
                 id, user_wallet, market_id, amount, is_base, signature, created_at
             FROM deposits
             WHERE user_wallet = $1
-            ORDER BY created_at DESC
-            LIMIT 100
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id DESC
+            LIMIT $7
             "#,
-            user_wallet
+            user_wallet,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
     };
     
-    Ok(deposits)
+    Ok(page.newest_first(deposits))
 }
 
 pub async fn get_user_withdrawals(
     pool: &PgPool,
     user_wallet: &str,
     market_id: Option<Uuid>,
+    page: &PageParams,
 ) -> Result<Vec<Withdrawal>> {
-    let withdrawals = if let Some(mid) = market_id {
+    let withdrawals = if page.after.is_some() {
         sqlx::query_as!(
             Withdrawal,
             r#"
             SELECT 
                 id, user_wallet, market_id, amount, is_base, signature, created_at
             FROM withdrawals
-            WHERE user_wallet = $1 AND market_id = $2
-            ORDER BY created_at DESC
-            LIMIT 100
+            WHERE user_wallet = $1
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id ASC
+            LIMIT $7
             "#,
             user_wallet,
-            mid
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
@@ -390,16 +965,27 @@ pub async fn get_user_withdrawals(
                 id, user_wallet, market_id, amount, is_base, signature, created_at
             FROM withdrawals
             WHERE user_wallet = $1
-            ORDER BY created_at DESC
-            LIMIT 100
+                AND ($2::UUID IS NULL OR market_id = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+                AND ($4::BIGINT IS NULL OR id > $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY id DESC
+            LIMIT $7
             "#,
-            user_wallet
+            user_wallet,
+            market_id,
+            page.before,
+            page.after,
+            page.from,
+            page.to,
+            page.limit()
         )
         .fetch_all(pool)
         .await?
     };
     
-    Ok(withdrawals)
+    Ok(page.newest_first(withdrawals))
 }
 pub async fn update_trade_signature(
     pool: &PgPool,
@@ -407,7 +993,7 @@ pub async fn update_trade_signature(
     signature: &str,
 ) -> Result<()> {
     sqlx::query!(
-        "UPDATE trades SET settlement_signature = $1 WHERE id = $2",
+        "UPDATE trades SET settlement_signature = $1, settlement_status = 'settled' WHERE id = $2",
         signature,
         trade_id
     )
@@ -416,3 +1002,554 @@ pub async fn update_trade_signature(
     
     Ok(())
 }
+
+pub async fn mark_trade_settlement_failed(pool: &PgPool, trade_id: i64) -> Result<()> {
+    sqlx::query!(
+        "UPDATE trades SET settlement_status = 'failed' WHERE id = $1",
+        trade_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Quote atoms `wallet` traded on a market, as maker or taker, since `since`.
+pub async fn get_wallet_volume(
+    pool: &PgPool,
+    market_id: Uuid,
+    wallet: &str,
+    since: DateTime<Utc>,
+) -> Result<i64> {
+    let volume = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(
+            SUM(FLOOR(t.size::NUMERIC * t.price / POWER(10::NUMERIC, m.base_decimals))),
+            0
+        )::BIGINT as "volume!"
+        FROM trades t
+        JOIN markets m ON m.id = t.market_id
+        WHERE t.market_id = $1
+            AND (t.maker_wallet = $2 OR t.taker_wallet = $2)
+            AND t.created_at >= $3
+        "#,
+        market_id,
+        wallet,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(volume)
+}
+
+pub async fn get_fee_tiers(pool: &PgPool, market_id: Uuid) -> Result<Vec<FeeTier>> {
+    let tiers = sqlx::query_as!(
+        FeeTier,
+        r#"
+        SELECT min_volume, maker_fee_bps, taker_fee_bps
+        FROM fee_tiers
+        WHERE market_id = $1
+        ORDER BY min_volume
+        "#,
+        market_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tiers)
+}
+
+/// Replaces the whole tier schedule of a market.
+pub async fn replace_fee_tiers(pool: &PgPool, market_id: Uuid, tiers: &[FeeTier]) -> Result<Vec<FeeTier>> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM fee_tiers WHERE market_id = $1", market_id)
+        .execute(&mut tx)
+        .await?;
+    for tier in tiers {
+        sqlx::query!(
+            r#"
+            INSERT INTO fee_tiers (market_id, min_volume, maker_fee_bps, taker_fee_bps)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            market_id,
+            tier.min_volume,
+            tier.maker_fee_bps,
+            tier.taker_fee_bps
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    get_fee_tiers(pool, market_id).await
+}
+
+pub async fn get_fee_override(pool: &PgPool, wallet: &str, market_id: Uuid) -> Result<Option<FeeOverride>> {
+    let fee_override = sqlx::query_as!(
+        FeeOverride,
+        r#"
+        SELECT wallet, market_id, maker_fee_bps, taker_fee_bps, label, created_at
+        FROM fee_overrides
+        WHERE wallet = $1 AND market_id = $2
+        "#,
+        wallet,
+        market_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(fee_override)
+}
+
+pub async fn get_fee_overrides(pool: &PgPool, market_id: Option<Uuid>) -> Result<Vec<FeeOverride>> {
+    let overrides = sqlx::query_as!(
+        FeeOverride,
+        r#"
+        SELECT wallet, market_id, maker_fee_bps, taker_fee_bps, label, created_at
+        FROM fee_overrides
+        WHERE ($1::UUID IS NULL OR market_id = $1)
+        ORDER BY market_id, wallet
+        "#,
+        market_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(overrides)
+}
+
+pub async fn upsert_fee_override(
+    pool: &PgPool,
+    wallet: &str,
+    market_id: Uuid,
+    maker_fee_bps: i16,
+    taker_fee_bps: i16,
+    label: Option<&str>,
+) -> Result<FeeOverride> {
+    let fee_override = sqlx::query_as!(
+        FeeOverride,
+        r#"
+        INSERT INTO fee_overrides (wallet, market_id, maker_fee_bps, taker_fee_bps, label)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (wallet, market_id) DO UPDATE SET
+            maker_fee_bps = EXCLUDED.maker_fee_bps,
+            taker_fee_bps = EXCLUDED.taker_fee_bps,
+            label = EXCLUDED.label
+        RETURNING wallet, market_id, maker_fee_bps, taker_fee_bps, label, created_at
+        "#,
+        wallet,
+        market_id,
+        maker_fee_bps,
+        taker_fee_bps,
+        label
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(fee_override)
+}
+
+/// Returns the removed override, if there was one.
+pub async fn delete_fee_override(pool: &PgPool, wallet: &str, market_id: Uuid) -> Result<Option<FeeOverride>> {
+    let fee_override = sqlx::query_as!(
+        FeeOverride,
+        r#"
+        DELETE FROM fee_overrides
+        WHERE wallet = $1 AND market_id = $2
+        RETURNING wallet, market_id, maker_fee_bps, taker_fee_bps, label, created_at
+        "#,
+        wallet,
+        market_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(fee_override)
+}
+
+/// Registers `referrer` for `wallet`. `None` if the wallet already has one.
+pub async fn create_referral(pool: &PgPool, wallet: &str, referrer: &str) -> Result<Option<Referral>> {
+    let referral = sqlx::query_as!(
+        Referral,
+        r#"
+        INSERT INTO referrals (wallet, referrer)
+        VALUES ($1, $2)
+        ON CONFLICT (wallet) DO NOTHING
+        RETURNING wallet, referrer, created_at
+        "#,
+        wallet,
+        referrer
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(referral)
+}
+
+pub async fn get_referral(pool: &PgPool, wallet: &str) -> Result<Option<Referral>> {
+    let referral = sqlx::query_as!(
+        Referral,
+        "SELECT wallet, referrer, created_at FROM referrals WHERE wallet = $1",
+        wallet
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(referral)
+}
+
+pub async fn count_referees(pool: &PgPool, referrer: &str) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM referrals WHERE referrer = $1"#,
+        referrer
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+pub async fn create_referral_earning(
+    pool: &PgPool,
+    trade_id: i64,
+    market_id: Uuid,
+    referrer: &str,
+    wallet: &str,
+    amount: i64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO referral_earnings (trade_id, market_id, referrer, wallet, amount)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        trade_id,
+        market_id,
+        referrer,
+        wallet,
+        amount
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Per-market earnings of `referrer`. Trades whose settlement failed are
+/// left out, since nothing was paid.
+pub async fn get_referral_earnings(pool: &PgPool, referrer: &str) -> Result<Vec<ReferralEarnings>> {
+    let earnings = sqlx::query_as!(
+        ReferralEarnings,
+        r#"
+        SELECT
+            e.market_id,
+            COALESCE(SUM(e.amount) FILTER (WHERE t.settlement_status = 'settled'), 0)::BIGINT as "settled!",
+            COALESCE(SUM(e.amount) FILTER (WHERE t.settlement_status = 'pending'), 0)::BIGINT as "pending!",
+            COUNT(*) FILTER (WHERE t.settlement_status <> 'failed') as "trade_count!"
+        FROM referral_earnings e
+        JOIN trades t ON t.id = e.trade_id
+        WHERE e.referrer = $1
+        GROUP BY e.market_id
+        ORDER BY e.market_id
+        "#,
+        referrer
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(earnings)
+}
+
+/// Books a settled trade's fees into today's ledger rows. Returns `false`,
+/// changing nothing, if the trade was booked before.
+pub async fn record_settled_fees(
+    pool: &PgPool,
+    trade_id: i64,
+    market_id: Uuid,
+    maker_fee: i64,
+    taker_fee: i64,
+    referrer_fee: i64,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let claimed = sqlx::query!(
+        "UPDATE trades SET fees_recorded = TRUE WHERE id = $1 AND NOT fees_recorded",
+        trade_id
+    )
+    .execute(&mut tx)
+    .await?;
+    if claimed.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    for (role, fees, referrer_fees) in [("maker", maker_fee, 0), ("taker", taker_fee, referrer_fee)] {
+        sqlx::query!(
+            r#"
+            INSERT INTO fee_ledger (market_id, day, role, fees, referrer_fees, trade_count)
+            VALUES ($1, (NOW() AT TIME ZONE 'UTC')::DATE, $2, $3, $4, 1)
+            ON CONFLICT (market_id, day, role) DO UPDATE SET
+                fees = fee_ledger.fees + EXCLUDED.fees,
+                referrer_fees = fee_ledger.referrer_fees + EXCLUDED.referrer_fees,
+                trade_count = fee_ledger.trade_count + 1
+            "#,
+            market_id,
+            role,
+            fees,
+            referrer_fees
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Ledger rows between `from` and `to` inclusive, by day then market.
+pub async fn get_fee_ledger(
+    pool: &PgPool,
+    market_id: Option<Uuid>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<FeeLedgerEntry>> {
+    let entries = sqlx::query_as!(
+        FeeLedgerEntry,
+        r#"
+        SELECT
+            market_id, day, role as "role: LiquidityRole",
+            fees, referrer_fees, fees - referrer_fees as "net_fees!", trade_count
+        FROM fee_ledger
+        WHERE ($1::UUID IS NULL OR market_id = $1)
+            AND ($2::DATE IS NULL OR day >= $2)
+            AND ($3::DATE IS NULL OR day <= $3)
+        ORDER BY day, market_id, role
+        "#,
+        market_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_revenue_totals(
+    pool: &PgPool,
+    market_id: Option<Uuid>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<RevenueTotals>> {
+    let totals = sqlx::query_as!(
+        RevenueTotals,
+        r#"
+        SELECT
+            market_id,
+            COALESCE(SUM(fees) FILTER (WHERE role = 'maker'), 0)::BIGINT as "maker_fees!",
+            COALESCE(SUM(fees) FILTER (WHERE role = 'taker'), 0)::BIGINT as "taker_fees!",
+            COALESCE(SUM(referrer_fees), 0)::BIGINT as "referrer_fees!",
+            COALESCE(SUM(fees - referrer_fees), 0)::BIGINT as "net_revenue!",
+            COALESCE(SUM(trade_count) FILTER (WHERE role = 'taker'), 0)::BIGINT as "trade_count!"
+        FROM fee_ledger
+        WHERE ($1::UUID IS NULL OR market_id = $1)
+            AND ($2::DATE IS NULL OR day >= $2)
+            AND ($3::DATE IS NULL OR day <= $3)
+        GROUP BY market_id
+        ORDER BY market_id
+        "#,
+        market_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(totals)
+}
+
+/// Fee recipient accounts recorded for markets.
+pub async fn get_fee_recipients(pool: &PgPool) -> Result<Vec<String>> {
+    let recipients = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT fee_recipient as "fee_recipient!"
+        FROM markets
+        WHERE fee_recipient IS NOT NULL
+        ORDER BY 1
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recipients)
+}
+
+/// Net fees the ledger has booked for every market paying `fee_recipient`.
+pub async fn get_ledger_total(pool: &PgPool, fee_recipient: &str) -> Result<i64> {
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(l.fees - l.referrer_fees), 0)::BIGINT as "total!"
+        FROM fee_ledger l
+        JOIN markets m ON m.id = l.market_id
+        WHERE m.fee_recipient = $1
+        "#,
+        fee_recipient
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(total)
+}
+
+pub async fn create_fee_reconciliation(
+    pool: &PgPool,
+    fee_recipient: &str,
+    observed_balance: i64,
+    ledger_total: i64,
+) -> Result<FeeReconciliation> {
+    let reconciliation = sqlx::query_as!(
+        FeeReconciliation,
+        r#"
+        INSERT INTO fee_reconciliations (fee_recipient, observed_balance, ledger_total, difference)
+        VALUES ($1, $2, $3, $2::BIGINT - $3::BIGINT)
+        RETURNING id, fee_recipient, observed_balance, ledger_total, difference, created_at
+        "#,
+        fee_recipient,
+        observed_balance,
+        ledger_total
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(reconciliation)
+}
+
+/// `api_keys` row with scopes as stored.
+struct ApiKeyRow {
+    key_id: String,
+    wallet: String,
+    label: String,
+    secret_hash: String,
+    scopes: Vec<String>,
+    ip_allowlist: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        ApiKey {
+            key_id: row.key_id,
+            wallet: row.wallet,
+            label: row.label,
+            secret_hash: row.secret_hash,
+            scopes: row.scopes.iter().filter_map(|scope| ApiKeyScope::parse(scope)).collect(),
+            ip_allowlist: row.ip_allowlist,
+            expires_at: row.expires_at,
+            revoked_at: row.revoked_at,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_api_key(
+    pool: &PgPool,
+    key_id: &str,
+    wallet: &str,
+    label: &str,
+    secret_hash: &str,
+    scopes: &[ApiKeyScope],
+    ip_allowlist: &[String],
+    expires_at: Option<DateTime<Utc>>,
+    registration_signature: &str,
+) -> Result<ApiKey> {
+    let scopes: Vec<String> = scopes.iter().map(|scope| scope.as_str().to_string()).collect();
+    let row = sqlx::query_as!(
+        ApiKeyRow,
+        r#"
+        INSERT INTO api_keys (
+            key_id, wallet, label, secret_hash, scopes, ip_allowlist, expires_at, registration_signature
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING key_id, wallet, label, secret_hash, scopes, ip_allowlist, expires_at, revoked_at, created_at
+        "#,
+        key_id,
+        wallet,
+        label,
+        secret_hash,
+        &scopes,
+        ip_allowlist,
+        expires_at,
+        registration_signature
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        if is_unique_violation(&e) {
+            AppError::DuplicateSignature {
+                kind: "API key",
+                signature: registration_signature.to_string(),
+            }
+        } else {
+            AppError::Database(e)
+        }
+    })?;
+
+    Ok(row.into())
+}
+
+pub async fn get_api_key(pool: &PgPool, key_id: &str) -> Result<Option<ApiKey>> {
+    let row = sqlx::query_as!(
+        ApiKeyRow,
+        r#"
+        SELECT key_id, wallet, label, secret_hash, scopes, ip_allowlist, expires_at, revoked_at, created_at
+        FROM api_keys
+        WHERE key_id = $1
+        "#,
+        key_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(ApiKey::from))
+}
+
+/// Keys of a wallet, newest first, revoked and expired ones included.
+pub async fn get_wallet_api_keys(pool: &PgPool, wallet: &str) -> Result<Vec<ApiKey>> {
+    let rows = sqlx::query_as!(
+        ApiKeyRow,
+        r#"
+        SELECT key_id, wallet, label, secret_hash, scopes, ip_allowlist, expires_at, revoked_at, created_at
+        FROM api_keys
+        WHERE wallet = $1
+        ORDER BY created_at DESC
+        "#,
+        wallet
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(ApiKey::from).collect())
+}
+
+/// Revokes a key of `wallet`. Returns `None` if the wallet has no such key;
+/// revoking twice keeps the first revocation time.
+pub async fn revoke_api_key(pool: &PgPool, wallet: &str, key_id: &str) -> Result<Option<ApiKey>> {
+    let row = sqlx::query_as!(
+        ApiKeyRow,
+        r#"
+        UPDATE api_keys
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE key_id = $1 AND wallet = $2
+        RETURNING key_id, wallet, label, secret_hash, scopes, ip_allowlist, expires_at, revoked_at, created_at
+        "#,
+        key_id,
+        wallet
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(ApiKey::from))
+}
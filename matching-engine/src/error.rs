@@ -1,57 +1,301 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{json, Value};
 use thiserror::Error;
 
+use crate::api::request_id;
+
+/// Stable, machine-readable error codes. Clients branch on these; messages
+/// are for humans and may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidOrder,
+    InvalidRequest,
+    MarketInactive,
+    OrderBelowMinSize,
+    OrderTickMisaligned,
+    OrderValueOutOfRange,
+    OrderExpiryInPast,
+    TriggerAlreadyCrossed,
+    OrderNotCancellable,
+    OrderNotFound,
+    MarketNotFound,
+    DuplicateSignature,
+    ReferralExists,
+    Unauthorized,
+    InvalidApiKey,
+    InvalidDelegate,
+    Forbidden,
+    RateLimited,
+    OrderToTradeRatioExceeded,
+    RecoveryInProgress,
+    AuthorityUnavailable,
+    InternalError,
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Invalid order: {0}")]
     InvalidOrder(String),
-    
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Market is not active")]
+    MarketInactive,
+
+    #[error("Order size {size} is below minimum {min_order_size}")]
+    OrderBelowMinSize { size: i64, min_order_size: i64 },
+
+    /// `field` is the misaligned price, `price` or `trigger_price`.
+    #[error("{} {value} is not aligned to tick size {tick_size}", price_label(.field))]
+    OrderTickMisaligned { field: &'static str, value: i64, tick_size: i64 },
+
+    #[error("Order value of {size} at {price} is out of range")]
+    OrderValueOutOfRange { size: i64, price: i64 },
+
+    #[error("Order expiry must be in the future")]
+    OrderExpiryInPast,
+
+    #[error("Trigger price {trigger_price} is already crossed by last price {last_price}")]
+    TriggerAlreadyCrossed { trigger_price: i64, last_price: i64 },
+
+    #[error("Order cannot be cancelled")]
+    OrderNotCancellable { order_id: String },
+
     #[error("Order not found")]
     OrderNotFound,
-    
+
     #[error("Market not found")]
     MarketNotFound,
-    
-    #[error("Insufficient balance")]
-    InsufficientBalance,
-    
+
+    /// A deposit, withdrawal or API key registration with this signature is
+    /// already recorded.
+    #[error("{kind} with this signature already exists")]
+    DuplicateSignature { kind: &'static str, signature: String },
+
+    #[error("{wallet} already has a referrer")]
+    ReferralExists { wallet: String },
+
     #[error("Unauthorized")]
     Unauthorized,
-    
+
+    /// API key authentication failed for the given reason.
+    #[error("{0}")]
+    InvalidApiKey(&'static str),
+
+    /// The request was signed by a session key that is not an active
+    /// delegate of the wallet, or the order exceeds its limits.
+    #[error("{0}")]
+    InvalidDelegate(String),
+
+    /// Authenticated, but the credentials do not allow this call.
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("Too many requests")]
+    RateLimited { retry_after_secs: u64 },
+
+    /// The wallet sent too many orders per trade and may not place orders
+    /// for a while.
+    #[error("Too many orders relative to trades; order placement is restricted")]
+    TradingRestricted { retry_after_secs: u64 },
+
+    #[error("Order books are still being recovered")]
+    RecoveryInProgress,
+
+    /// No authority keypair is configured, so nothing can be signed on chain.
+    #[error("The engine has no market authority key configured")]
+    AuthorityUnavailable,
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
-    
+
     #[error("Redis error: {0}")]
     Redis(#[from] redis::RedisError),
-    
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
 
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::InvalidOrder(_) => ErrorCode::InvalidOrder,
+            AppError::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            AppError::MarketInactive => ErrorCode::MarketInactive,
+            AppError::OrderBelowMinSize { .. } => ErrorCode::OrderBelowMinSize,
+            AppError::OrderTickMisaligned { .. } => ErrorCode::OrderTickMisaligned,
+            AppError::OrderValueOutOfRange { .. } => ErrorCode::OrderValueOutOfRange,
+            AppError::OrderExpiryInPast => ErrorCode::OrderExpiryInPast,
+            AppError::TriggerAlreadyCrossed { .. } => ErrorCode::TriggerAlreadyCrossed,
+            AppError::OrderNotCancellable { .. } => ErrorCode::OrderNotCancellable,
+            AppError::OrderNotFound => ErrorCode::OrderNotFound,
+            AppError::MarketNotFound => ErrorCode::MarketNotFound,
+            AppError::DuplicateSignature { .. } => ErrorCode::DuplicateSignature,
+            AppError::ReferralExists { .. } => ErrorCode::ReferralExists,
+            AppError::Unauthorized => ErrorCode::Unauthorized,
+            AppError::InvalidApiKey(_) => ErrorCode::InvalidApiKey,
+            AppError::InvalidDelegate(_) => ErrorCode::InvalidDelegate,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::RateLimited { .. } => ErrorCode::RateLimited,
+            AppError::TradingRestricted { .. } => ErrorCode::OrderToTradeRatioExceeded,
+            AppError::RecoveryInProgress => ErrorCode::RecoveryInProgress,
+            AppError::AuthorityUnavailable => ErrorCode::AuthorityUnavailable,
+            AppError::Database(_) | AppError::Redis(_) | AppError::Internal(_) => ErrorCode::InternalError,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::OrderNotFound | AppError::MarketNotFound => StatusCode::NOT_FOUND,
+            AppError::DuplicateSignature { .. } | AppError::ReferralExists { .. } => StatusCode::CONFLICT,
+            AppError::Unauthorized | AppError::InvalidApiKey(_) => StatusCode::UNAUTHORIZED,
+            AppError::InvalidDelegate(_) | AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::RateLimited { .. } | AppError::TradingRestricted { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::RecoveryInProgress | AppError::AuthorityUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(_) | AppError::Redis(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Structured context for the error, beside the message.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            AppError::OrderBelowMinSize { size, min_order_size } => {
+                Some(json!({ "size": size, "min_order_size": min_order_size }))
+            }
+            AppError::OrderTickMisaligned { field, value, tick_size } => {
+                Some(json!({ "field": field, "value": value, "tick_size": tick_size }))
+            }
+            AppError::OrderValueOutOfRange { size, price } => Some(json!({ "size": size, "price": price })),
+            AppError::TriggerAlreadyCrossed { trigger_price, last_price } => {
+                Some(json!({ "trigger_price": trigger_price, "last_price": last_price }))
+            }
+            AppError::OrderNotCancellable { order_id } => Some(json!({ "order_id": order_id })),
+            AppError::DuplicateSignature { signature, .. } => Some(json!({ "signature": signature })),
+            AppError::ReferralExists { wallet } => Some(json!({ "wallet": wallet })),
+            AppError::RateLimited { retry_after_secs } | AppError::TradingRestricted { retry_after_secs } => {
+                Some(json!({ "retry_after_secs": retry_after_secs }))
+            }
+            _ => None,
+        }
+    }
+
+    fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::RateLimited { retry_after_secs } | AppError::TradingRestricted { retry_after_secs } => {
+                Some(*retry_after_secs)
+            }
+            _ => None,
+        }
+    }
+
+    /// What the client is told. Internal failures are logged in full and
+    /// reported without their cause.
+    fn public_message(&self) -> String {
+        match self {
+            AppError::InvalidOrder(msg) | AppError::InvalidRequest(msg) => msg.clone(),
+            AppError::Database(_) | AppError::Redis(_) | AppError::Internal(_) => {
+                "Internal server error".to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match &self {
-            AppError::InvalidOrder(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            AppError::OrderNotFound => (StatusCode::NOT_FOUND, "Order not found".to_string()),
-            AppError::MarketNotFound => (StatusCode::NOT_FOUND, "Market not found".to_string()),
-            AppError::InsufficientBalance => (StatusCode::BAD_REQUEST, "Insufficient balance".to_string()),
-            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
-            AppError::Database(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)),
-            AppError::Redis(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Redis error: {}", e)),
-            AppError::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Internal error: {}", e)),
-        };
-
-        let body = Json(json!({
-            "error": message
-        }));
-
-        (status, body).into_response()
+        let status = self.status();
+        let request_id = request_id::current();
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            tracing::error!(request_id = request_id.as_deref(), "Request failed: {:?}", self);
+        }
+
+        let mut body = json!({
+            "error": self.public_message(),
+            "code": self.code(),
+        });
+        if let Some(details) = self.details() {
+            body["details"] = details;
+        }
+        if let Some(request_id) = request_id {
+            body["request_id"] = Value::String(request_id);
+        }
+
+        let mut response = (status, Json(body)).into_response();
+        if let Some(retry_after) = self.retry_after() {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
+
+fn price_label(field: &str) -> &'static str {
+    match field {
+        "trigger_price" => "Trigger price",
+        _ => "Price",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(error: AppError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_body_has_code_and_details() {
+        let (status, body) = body(AppError::OrderTickMisaligned {
+            field: "price",
+            value: 105,
+            tick_size: 10,
+        })
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "ORDER_TICK_MISALIGNED");
+        assert_eq!(body["error"], "Price 105 is not aligned to tick size 10");
+        assert_eq!(body["details"], json!({ "field": "price", "value": 105, "tick_size": 10 }));
+    }
+
+    #[tokio::test]
+    async fn test_internal_errors_are_sanitized() {
+        let (status, body) = body(AppError::Internal(anyhow::anyhow!("connection to 10.0.0.5 refused"))).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "INTERNAL_ERROR");
+        assert_eq!(body["error"], "Internal server error");
+        assert!(body.get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_sets_retry_after() {
+        let response = AppError::RateLimited { retry_after_secs: 3 }.into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed() {
+        let (_, body) = request_id::scope("req-1".to_string(), body(AppError::MarketInactive)).await;
+
+        assert_eq!(body["code"], "MARKET_INACTIVE");
+        assert_eq!(body["request_id"], "req-1");
+    }
+}
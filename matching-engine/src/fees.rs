@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::db;
+use crate::error::Result;
+use crate::types::{FeeOverride, FeeRates, FeeSource, FeeTier, Market, WalletFees};
+
+/// Volume is recomputed at most this often per wallet and market, so a wallet
+/// crossing a tier threshold pays the new rate within a minute.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+const VOLUME_WINDOW: chrono::Duration = chrono::Duration::days(30);
+
+/// Rates for a wallet: its override if any, else the highest tier its 30-day
+/// volume reaches, else the market default.
+pub fn resolve(
+    market: &Market,
+    fee_override: Option<&FeeOverride>,
+    tiers: &[FeeTier],
+    volume_30d: i64,
+) -> FeeRates {
+    if let Some(fee_override) = fee_override {
+        return FeeRates {
+            maker_fee_bps: fee_override.maker_fee_bps,
+            taker_fee_bps: fee_override.taker_fee_bps,
+            source: FeeSource::Override,
+        };
+    }
+
+    tiers
+        .iter()
+        .filter(|tier| tier.min_volume <= volume_30d)
+        .max_by_key(|tier| tier.min_volume)
+        .map(|tier| FeeRates {
+            maker_fee_bps: tier.maker_fee_bps,
+            taker_fee_bps: tier.taker_fee_bps,
+            source: FeeSource::Tier,
+        })
+        .unwrap_or_else(|| FeeRates::market_default(market))
+}
+
+struct CachedFees {
+    fees: WalletFees,
+    fetched_at: Instant,
+}
+
+/// Resolves the fee rates and referrer of each fill's maker and taker,
+/// caching them per wallet and market.
+pub struct FeeManager {
+    cache: RwLock<HashMap<(Uuid, String), CachedFees>>,
+}
+
+impl FeeManager {
+    pub fn new() -> Self {
+        Self {
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn wallet_fees(&self, pool: &PgPool, market: &Market, wallet: &str) -> Result<WalletFees> {
+        let key = (market.id, wallet.to_string());
+        if let Some(cached) = self.cache.read().await.get(&key) {
+            if cached.fetched_at.elapsed() < CACHE_TTL {
+                return Ok(cached.fees.clone());
+            }
+        }
+
+        let fee_override = db::get_fee_override(pool, wallet, market.id).await?;
+        let tiers = db::get_fee_tiers(pool, market.id).await?;
+        let volume_30d = db::get_wallet_volume(pool, market.id, wallet, chrono::Utc::now() - VOLUME_WINDOW).await?;
+        let referral = db::get_referral(pool, wallet).await?;
+        let fees = WalletFees {
+            wallet: wallet.to_string(),
+            market_id: market.id,
+            volume_30d,
+            rates: resolve(market, fee_override.as_ref(), &tiers, volume_30d),
+            referrer: referral.map(|referral| referral.referrer),
+        };
+
+        self.cache.write().await.insert(
+            key,
+            CachedFees {
+                fees: fees.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(fees)
+    }
+
+    pub async fn rates(&self, pool: &PgPool, market: &Market, wallet: &str) -> Result<FeeRates> {
+        Ok(self.wallet_fees(pool, market, wallet).await?.rates)
+    }
+
+    /// Drops cached rates of a market after its schedule or overrides change.
+    pub async fn invalidate_market(&self, market_id: Uuid) {
+        self.cache.write().await.retain(|(cached_market, _), _| *cached_market != market_id);
+    }
+
+    /// Drops cached entries of a wallet, e.g. once it registers a referrer.
+    pub async fn invalidate_wallet(&self, wallet: &str) {
+        self.cache.write().await.retain(|(_, cached_wallet), _| cached_wallet != wallet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MatchingAlgorithm;
+
+    fn market() -> Market {
+        Market {
+            id: Uuid::new_v4(),
+            base_mint: String::new(),
+            quote_mint: String::new(),
+            base_decimals: 9,
+            quote_decimals: 6,
+            min_order_size: 1,
+            tick_size: 1,
+            maker_fee_bps: 5,
+            taker_fee_bps: 10,
+            is_active: true,
+            matching_algorithm: MatchingAlgorithm::Fifo,
+            fifo_slice_bps: 0,
+            market_address: None,
+            base_vault: None,
+            quote_vault: None,
+            fee_recipient: None,
+            referral_share_bps: 0,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn tier(min_volume: i64, maker_fee_bps: i16, taker_fee_bps: i16) -> FeeTier {
+        FeeTier {
+            min_volume,
+            maker_fee_bps,
+            taker_fee_bps,
+        }
+    }
+
+    #[test]
+    fn test_resolve_picks_highest_reached_tier() {
+        let market = market();
+        let tiers = [tier(1_000_000, 2, 8), tier(0, 4, 10), tier(10_000_000, -1, 6)];
+
+        let rates = resolve(&market, None, &tiers, 5_000_000);
+        assert_eq!((rates.maker_fee_bps, rates.taker_fee_bps, rates.source), (2, 8, FeeSource::Tier));
+
+        let rates = resolve(&market, None, &tiers, 10_000_000);
+        assert_eq!((rates.maker_fee_bps, rates.taker_fee_bps), (-1, 6));
+
+        let rates = resolve(&market, None, &tiers[..1], 10);
+        assert_eq!(rates, FeeRates::market_default(&market));
+    }
+
+    #[test]
+    fn test_resolve_prefers_override() {
+        let market = market();
+        let fee_override = FeeOverride {
+            wallet: "mm".to_string(),
+            market_id: market.id,
+            maker_fee_bps: -2,
+            taker_fee_bps: 5,
+            label: Some("market maker".to_string()),
+            created_at: chrono::Utc::now(),
+        };
+
+        let rates = resolve(&market, Some(&fee_override), &[tier(0, 4, 10)], 0);
+        assert_eq!((rates.maker_fee_bps, rates.taker_fee_bps, rates.source), (-2, 5, FeeSource::Override));
+    }
+}
@@ -0,0 +1,199 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use crate::db;
+use crate::AppState;
+
+/// Upper bound for a single dependency check, so one hung dependency cannot
+/// hold up the whole probe.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: ComponentStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Components {
+    pub postgres: ComponentHealth,
+    pub redis: ComponentHealth,
+    pub solana: ComponentHealth,
+    pub settlement: ComponentHealth,
+    pub orderbook: ComponentHealth,
+}
+
+impl Components {
+    fn all_up(&self) -> bool {
+        [&self.postgres, &self.redis, &self.solana, &self.settlement, &self.orderbook]
+            .iter()
+            .all(|component| component.status == ComponentStatus::Up)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub version: String,
+    pub components: Components,
+}
+
+/// Readiness thresholds plus the startup state the probes report on.
+pub struct HealthMonitor {
+    max_slot_lag: u64,
+    max_settlement_age: chrono::Duration,
+    recovered: AtomicBool,
+}
+
+impl HealthMonitor {
+    pub fn new(max_slot_lag: u64, max_settlement_age_secs: i64) -> Self {
+        Self {
+            max_slot_lag,
+            max_settlement_age: chrono::Duration::seconds(max_settlement_age_secs),
+            recovered: AtomicBool::new(false),
+        }
+    }
+
+    /// Called once the books have been rebuilt from Postgres.
+    pub fn mark_recovered(&self) {
+        self.recovered.store(true, Ordering::Release);
+    }
+
+    pub fn is_recovered(&self) -> bool {
+        self.recovered.load(Ordering::Acquire)
+    }
+
+    /// Checks every dependency concurrently. The engine is ready only when
+    /// all of them are up.
+    pub async fn check(&self, state: &AppState) -> ReadinessReport {
+        let (postgres, redis, solana, settlement, orderbook) = tokio::join!(
+            timed(check_postgres(state)),
+            timed(check_redis(state)),
+            timed(self.check_solana(state)),
+            timed(self.check_settlement(state)),
+            timed(self.check_orderbook(state)),
+        );
+        let components = Components {
+            postgres,
+            redis,
+            solana,
+            settlement,
+            orderbook,
+        };
+
+        ReadinessReport {
+            ready: components.all_up(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            components,
+        }
+    }
+
+    async fn check_solana(&self, state: &AppState) -> CheckResult {
+        let lag = state.settlement_queue
+            .solana_client()
+            .slot_lag()
+            .await
+            .map_err(|e| e.to_string())?;
+        let detail = format!("{} slots behind", lag);
+        if lag > self.max_slot_lag {
+            return Err(detail);
+        }
+        Ok(Some(detail))
+    }
+
+    /// Fails when the oldest unsettled trade has waited longer than allowed,
+    /// or when there is no authority key to settle with.
+    async fn check_settlement(&self, state: &AppState) -> CheckResult {
+        if state.settlement_queue.solana_client().authority_pubkey().is_none() {
+            return Err("No authority keypair configured".to_string());
+        }
+        let queued = state.settlement_queue.queue_depth();
+        let oldest = db::get_oldest_pending_settlement(&state.db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(oldest) = oldest else {
+            return Ok(Some(format!("{} jobs queued, no pending trades", queued)));
+        };
+
+        let age = chrono::Utc::now() - oldest;
+        let detail = format!(
+            "{} jobs queued, oldest pending trade {}s old",
+            queued,
+            age.num_seconds()
+        );
+        if age > self.max_settlement_age {
+            return Err(detail);
+        }
+        Ok(Some(detail))
+    }
+
+    async fn check_orderbook(&self, state: &AppState) -> CheckResult {
+        if !self.is_recovered() {
+            return Err("Recovery in progress".to_string());
+        }
+        let books = state.orderbook_manager.read().await.books().count();
+        Ok(Some(format!("{} books loaded", books)))
+    }
+}
+
+/// `Ok` with an optional detail when the component is up, `Err` with the
+/// reason when it is down.
+type CheckResult = std::result::Result<Option<String>, String>;
+
+async fn check_postgres(state: &AppState) -> CheckResult {
+    db::ping(&state.db_pool).await.map_err(|e| e.to_string())?;
+    Ok(None)
+}
+
+async fn check_redis(state: &AppState) -> CheckResult {
+    let mut redis = state.redis.clone();
+    redis::cmd("PING")
+        .query_async::<String>(&mut redis)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(None)
+}
+
+async fn timed(check: impl Future<Output = CheckResult>) -> ComponentHealth {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err("Timed out".to_string()));
+    let (status, detail) = match outcome {
+        Ok(detail) => (ComponentStatus::Up, detail),
+        Err(detail) => (ComponentStatus::Down, Some(detail)),
+    };
+
+    ComponentHealth {
+        status,
+        latency_ms: started.elapsed().as_millis() as u64,
+        detail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timed_reports_status_and_detail() {
+        let up = timed(async { Ok(Some("fine".to_string())) }).await;
+        assert_eq!(up.status, ComponentStatus::Up);
+        assert_eq!(up.detail.as_deref(), Some("fine"));
+
+        let down = timed(async { Err("refused".to_string()) }).await;
+        assert_eq!(down.status, ComponentStatus::Down);
+        assert_eq!(down.detail.as_deref(), Some("refused"));
+    }
+}
@@ -1,28 +1,88 @@
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod api;
+mod auth;
+mod market_data;
 mod orderbook;
 mod settlement;
 mod websocket;
 mod config;
 mod db;
 mod error;
+mod fees;
+mod health;
+mod metrics;
+mod ratelimit;
 mod types;
 
+use crate::fees::FeeManager;
+use crate::health::HealthMonitor;
+use crate::market_data::{CandleAggregator, TickerManager};
 use crate::orderbook::OrderbookManager;
+use crate::ratelimit::RateLimiter;
 use crate::settlement::SettlementQueue;
-use crate::websocket::WebSocketManager;
+use crate::websocket::{SessionManager, WebSocketManager};
 
 pub struct AppState {
     pub orderbook_manager: Arc<RwLock<OrderbookManager>>,
     pub settlement_queue: Arc<SettlementQueue>,
     pub ws_manager: Arc<WebSocketManager>,
+    pub session_manager: Arc<SessionManager>,
+    pub ticker_manager: Arc<TickerManager>,
+    pub candle_aggregator: Arc<CandleAggregator>,
+    pub fee_manager: Arc<FeeManager>,
+    pub health: Arc<HealthMonitor>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub admin_token: Option<String>,
+    pub api_key_secret: Option<String>,
     pub db_pool: sqlx::PgPool,
     pub redis: redis::aio::ConnectionManager,
 }
 
+/// Recovery is retried with exponential backoff from this delay.
+const RECOVERY_RETRY_BASE: Duration = Duration::from_secs(1);
+const RECOVERY_RETRY_MAX: Duration = Duration::from_secs(30);
+/// About five minutes of retries before the process exits, so a supervisor
+/// can restart it instead of leaving it up but refusing every order.
+const MAX_RECOVERY_ATTEMPTS: u32 = 15;
+
+async fn recover_orderbooks(state: Arc<AppState>) {
+    tracing::info!("Recovering orderbooks...");
+    let mut delay = RECOVERY_RETRY_BASE;
+    for attempt in 1.. {
+        match orderbook::recover(&state.db_pool, &state.orderbook_manager).await {
+            Ok(recovery) => {
+                tracing::info!(
+                    "Restored {} open orders, cancelled {}",
+                    recovery.restored,
+                    recovery.cancelled.len()
+                );
+                for order in recovery.cancelled {
+                    state.session_manager.untrack_order(&order.order_id).await;
+                    state.ws_manager.send_order_update(order).await;
+                }
+                state.health.mark_recovered();
+                return;
+            }
+            Err(e) if attempt < MAX_RECOVERY_ATTEMPTS => {
+                tracing::error!(
+                    "Orderbook recovery failed (attempt {}), retrying in {:?}: {:?}",
+                    attempt, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RECOVERY_RETRY_MAX);
+            }
+            Err(e) => {
+                tracing::error!("Orderbook recovery failed {} times, exiting: {:?}", attempt, e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
@@ -43,37 +103,76 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Running migrations...");
     sqlx::migrate!("./migrations").run(&db_pool).await?;
     
+    tracing::info!("Backfilling candles...");
+    market_data::candles::backfill(&db_pool).await?;
+
     tracing::info!("Connecting to Redis...");
     let redis_client = redis::Client::open(config.redis_url.clone())?;
     let redis = redis::aio::ConnectionManager::new(redis_client).await?;
     
     let orderbook_manager = Arc::new(RwLock::new(OrderbookManager::new()));
     let ws_manager = Arc::new(WebSocketManager::new());
+    metrics::METRICS.register_ws(ws_manager.metrics());
+    let session_manager = Arc::new(SessionManager::new());
+    let ticker_manager = Arc::new(TickerManager::new());
+    ticker_manager.load(&db_pool).await?;
+    let authority = match &config.authority_keypair_path {
+        Some(path) => Some(
+            solana_sdk::signature::read_keypair_file(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read authority keypair {}: {}", path, e))?,
+        ),
+        None => {
+            tracing::warn!("AUTHORITY_KEYPAIR_PATH is not set; market creation and settlement are disabled");
+            None
+        }
+    };
     let settlement_queue = Arc::new(SettlementQueue::new(
         db_pool.clone(),
         config.solana_rpc_url.clone(),
         config.program_id.clone(),
+        authority,
+        ws_manager.clone(),
     ));
 
     let state = Arc::new(AppState {
         orderbook_manager: orderbook_manager.clone(),
         settlement_queue: settlement_queue.clone(),
         ws_manager: ws_manager.clone(),
+        session_manager,
+        ticker_manager,
+        candle_aggregator: Arc::new(CandleAggregator::new(db_pool.clone(), ws_manager.clone())),
+        fee_manager: Arc::new(FeeManager::new()),
+        health: Arc::new(HealthMonitor::new(config.max_slot_lag, config.max_settlement_age_secs)),
+        rate_limiter: Arc::new(RateLimiter::new(redis.clone(), config.rate_limits.clone())),
+        admin_token: config.admin_token.clone(),
+        api_key_secret: config.api_key_secret.clone(),
         db_pool,
         redis,
     });
 
+    // Orders and cancels are refused until the books are rebuilt, so
+    // nothing can interleave with the restore.
+    tokio::spawn(recover_orderbooks(state.clone()));
+
     let settlement_state = state.clone();
     tokio::spawn(async move {
         settlement_state.settlement_queue.run().await;
     });
 
+    let candle_state = state.clone();
+    tokio::spawn(async move {
+        candle_state.candle_aggregator.run().await;
+    });
+
+    tokio::spawn(api::run_expiry_sweeper(state.clone()));
+    tokio::spawn(websocket::run_pending_flusher(ws_manager.clone()));
+
     let app = api::create_router(state);
 
     let listener = tokio::net::TcpListener::bind(&config.server_addr).await?;
     tracing::info!("Matching engine listening on {}", config.server_addr);
     
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
 
     Ok(())
 }
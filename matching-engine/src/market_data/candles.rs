@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::db;
+use crate::error::Result;
+use crate::types::{CandleInterval, Channel, Trade, WsMessage};
+use crate::websocket::{Topic, WebSocketManager};
+
+/// Folds trades into candles off the matching path. Trades are applied in the
+/// order they were queued, so each candle closes on the latest trade.
+pub struct CandleAggregator {
+    db_pool: PgPool,
+    ws_manager: Arc<WebSocketManager>,
+    tx: mpsc::Sender<Trade>,
+    rx: Mutex<mpsc::Receiver<Trade>>,
+}
+
+impl CandleAggregator {
+    pub fn new(db_pool: PgPool, ws_manager: Arc<WebSocketManager>) -> Self {
+        let (tx, rx) = mpsc::channel(10000);
+
+        Self {
+            db_pool,
+            ws_manager,
+            tx,
+            rx: Mutex::new(rx),
+        }
+    }
+
+    /// Hands a trade to [`Self::run`] without waiting. If the aggregator has
+    /// fallen that far behind the trade is dropped; the backfill at the next
+    /// start rebuilds its candles from the trades table.
+    pub fn queue(&self, trade: Trade) {
+        if let Err(e) = self.tx.try_send(trade) {
+            tracing::warn!("Candle queue full, dropping a trade until the next backfill: {}", e);
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut rx = self.rx.lock().await;
+
+        while let Some(trade) = rx.recv().await {
+            if let Err(e) = record_trade(&self.db_pool, &self.ws_manager, &trade).await {
+                tracing::warn!("Candle update for trade {} failed: {}", trade.id, e);
+            }
+        }
+    }
+}
+
+/// Folds a matched trade into every interval's current candle and streams the
+/// updated candles on `candles.<interval>`.
+async fn record_trade(
+    pool: &PgPool,
+    ws_manager: &WebSocketManager,
+    trade: &Trade,
+) -> Result<()> {
+    for interval in CandleInterval::ALL {
+        let candle = db::record_candle_trade(
+            pool,
+            trade.market_id,
+            interval,
+            interval.open_time(trade.created_at),
+            trade.price,
+            trade.size,
+        ).await?;
+
+        let topic = Topic::Market(trade.market_id, Channel::Candles(interval));
+        ws_manager.publish(&topic, WsMessage::Candle(candle)).await;
+    }
+
+    Ok(())
+}
+
+/// Catches stored candles up with the trades table, e.g. after a restart or
+/// when candles were introduced on a market with existing trades.
+pub async fn backfill(pool: &PgPool) -> Result<()> {
+    for interval in CandleInterval::ALL {
+        let rows = db::backfill_candles(pool, interval).await?;
+        tracing::info!("Backfilled {} {} candles", rows, interval.as_str());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_open_time_aligns_to_interval() {
+        let time = at("2024-03-05T13:47:29.5Z");
+
+        assert_eq!(CandleInterval::M1.open_time(time), at("2024-03-05T13:47:00Z"));
+        assert_eq!(CandleInterval::M5.open_time(time), at("2024-03-05T13:45:00Z"));
+        assert_eq!(CandleInterval::M15.open_time(time), at("2024-03-05T13:45:00Z"));
+        assert_eq!(CandleInterval::H1.open_time(time), at("2024-03-05T13:00:00Z"));
+        assert_eq!(CandleInterval::H4.open_time(time), at("2024-03-05T12:00:00Z"));
+        assert_eq!(CandleInterval::D1.open_time(time), at("2024-03-05T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_interval_round_trips_through_strings() {
+        for interval in CandleInterval::ALL {
+            assert_eq!(interval.as_str().parse::<CandleInterval>(), Ok(interval));
+            let json = serde_json::to_string(&interval).unwrap();
+            assert_eq!(json, format!("\"{}\"", interval.as_str()));
+        }
+        assert!("2m".parse::<CandleInterval>().is_err());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod candles;
+pub mod ticker;
+
+pub use self::candles::CandleAggregator;
+pub use self::ticker::TickerManager;
@@ -0,0 +1,258 @@
+use std::collections::{HashMap, VecDeque};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::db;
+use crate::error::Result;
+use crate::orderbook::Orderbook;
+use crate::settlement;
+use crate::types::{CandleInterval, Channel, Ticker, Trade, WsMessage};
+use crate::websocket::{Topic, WebSocketManager};
+
+/// Trades are kept in one-minute buckets, so the window edge moves in
+/// one-minute steps and memory stays bounded however busy a market is.
+const BUCKET: CandleInterval = CandleInterval::M1;
+
+const WINDOW: Duration = Duration::hours(24);
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    start: DateTime<Utc>,
+    open: i64,
+    high: i64,
+    low: i64,
+    close: i64,
+    volume: i64,
+    quote_volume: i64,
+    trade_count: i64,
+}
+
+/// One market's trades over the last 24h plus its current top of book.
+#[derive(Debug, Default)]
+pub struct TickerWindow {
+    buckets: VecDeque<Bucket>,
+    last_price: Option<i64>,
+    best_bid: Option<i64>,
+    best_ask: Option<i64>,
+}
+
+impl TickerWindow {
+    /// Adds a trade worth `quote` quote atoms. Trades must arrive in
+    /// execution order.
+    pub fn record(&mut self, time: DateTime<Utc>, price: i64, size: i64, quote: i64) {
+        let start = BUCKET.open_time(time);
+
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start >= start => {
+                bucket.high = bucket.high.max(price);
+                bucket.low = bucket.low.min(price);
+                bucket.close = price;
+                bucket.volume = bucket.volume.saturating_add(size);
+                bucket.quote_volume = bucket.quote_volume.saturating_add(quote);
+                bucket.trade_count += 1;
+            }
+            _ => self.buckets.push_back(Bucket {
+                start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: size,
+                quote_volume: quote,
+                trade_count: 1,
+            }),
+        }
+        self.last_price = Some(price);
+
+        let cutoff = time - WINDOW;
+        while self.buckets.front().is_some_and(|bucket| !Self::in_window(bucket, cutoff)) {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Updates the top of book; returns whether it changed.
+    pub fn set_top(&mut self, best_bid: Option<i64>, best_ask: Option<i64>) -> bool {
+        let changed = (self.best_bid, self.best_ask) != (best_bid, best_ask);
+        self.best_bid = best_bid;
+        self.best_ask = best_ask;
+        changed
+    }
+
+    fn in_window(bucket: &Bucket, cutoff: DateTime<Utc>) -> bool {
+        bucket.start + Duration::seconds(BUCKET.seconds()) > cutoff
+    }
+
+    pub fn ticker(&self, market_id: Uuid, now: DateTime<Utc>) -> Ticker {
+        let cutoff = now - WINDOW;
+        let buckets: Vec<&Bucket> = self
+            .buckets
+            .iter()
+            .filter(|bucket| Self::in_window(bucket, cutoff))
+            .collect();
+
+        let open = buckets.first().map(|bucket| bucket.open);
+        let close = buckets.last().map(|bucket| bucket.close);
+        let price_change = open.zip(close).map(|(open, close)| close - open);
+        let price_change_bps = open
+            .zip(price_change)
+            .filter(|(open, _)| *open > 0)
+            .map(|(open, change)| (change as i128 * 10_000 / open as i128) as i64);
+
+        Ticker {
+            market_id,
+            last_price: self.last_price,
+            best_bid: self.best_bid,
+            best_ask: self.best_ask,
+            open_24h: open,
+            high_24h: buckets.iter().map(|bucket| bucket.high).max(),
+            low_24h: buckets.iter().map(|bucket| bucket.low).min(),
+            price_change_24h: price_change,
+            price_change_bps_24h: price_change_bps,
+            volume_24h: buckets.iter().fold(0i64, |sum, bucket| sum.saturating_add(bucket.volume)),
+            quote_volume_24h: buckets
+                .iter()
+                .fold(0i64, |sum, bucket| sum.saturating_add(bucket.quote_volume)),
+            trade_count_24h: buckets.iter().map(|bucket| bucket.trade_count).sum(),
+            timestamp: now,
+        }
+    }
+}
+
+/// In-memory tickers for every market, fed by matched trades and book changes.
+pub struct TickerManager {
+    windows: RwLock<HashMap<Uuid, TickerWindow>>,
+}
+
+impl TickerManager {
+    pub fn new() -> Self {
+        Self {
+            windows: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Warms the windows with the last 24h of trades, e.g. on startup.
+    pub async fn load(&self, pool: &PgPool) -> Result<()> {
+        let trades = db::get_trades_since(pool, Utc::now() - WINDOW).await?;
+
+        let mut base_decimals = HashMap::new();
+        let mut windows = self.windows.write().await;
+        for trade in &trades {
+            let decimals = match base_decimals.get(&trade.market_id) {
+                Some(decimals) => *decimals,
+                None => {
+                    let market = db::get_market(pool, trade.market_id).await?;
+                    let decimals = market.map(|market| market.base_decimals);
+                    base_decimals.insert(trade.market_id, decimals);
+                    decimals
+                }
+            };
+            let Some(decimals) = decimals else {
+                continue;
+            };
+            let quote = settlement::quote_amount(trade.size, trade.price, decimals).unwrap_or(0);
+            windows
+                .entry(trade.market_id)
+                .or_default()
+                .record(trade.created_at, trade.price, trade.size, quote);
+        }
+
+        Ok(())
+    }
+
+    pub async fn record_trade(&self, trade: &Trade, quote_amount: i64) {
+        self.windows
+            .write()
+            .await
+            .entry(trade.market_id)
+            .or_default()
+            .record(trade.created_at, trade.price, trade.size, quote_amount);
+    }
+
+    /// Picks up the book's top of book and publishes the ticker on `ticker`
+    /// when it or the trade statistics changed. Called under the book lock
+    /// after `record_trade` for the same changes.
+    pub async fn refresh(&self, ws_manager: &WebSocketManager, orderbook: &Orderbook, traded: bool) {
+        let ticker = {
+            let mut windows = self.windows.write().await;
+            let window = windows.entry(orderbook.market_id).or_default();
+            let top_changed = window.set_top(orderbook.best_bid(), orderbook.best_ask());
+            if !top_changed && !traded {
+                return;
+            }
+            window.ticker(orderbook.market_id, Utc::now())
+        };
+
+        let topic = Topic::Market(orderbook.market_id, Channel::Ticker);
+        ws_manager.publish(&topic, WsMessage::Ticker(ticker)).await;
+    }
+
+    pub async fn ticker(&self, market_id: Uuid) -> Ticker {
+        let now = Utc::now();
+        match self.windows.read().await.get(&market_id) {
+            Some(window) => window.ticker(market_id, now),
+            None => TickerWindow::default().ticker(market_id, now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_ticker_aggregates_window() {
+        let market_id = Uuid::new_v4();
+        let mut window = TickerWindow::default();
+        window.record(at("2024-03-05T10:00:10Z"), 100_000_000, 2_000_000_000, 200_000_000);
+        window.record(at("2024-03-05T10:00:40Z"), 120_000_000, 1_000_000_000, 120_000_000);
+        window.record(at("2024-03-05T11:30:00Z"), 90_000_000, 1_000_000_000, 90_000_000);
+        window.record(at("2024-03-05T12:00:00Z"), 110_000_000, 500_000_000, 55_000_000);
+        window.set_top(Some(105_000_000), Some(115_000_000));
+
+        let ticker = window.ticker(market_id, at("2024-03-05T12:00:30Z"));
+        assert_eq!(ticker.last_price, Some(110_000_000));
+        assert_eq!(ticker.open_24h, Some(100_000_000));
+        assert_eq!(ticker.high_24h, Some(120_000_000));
+        assert_eq!(ticker.low_24h, Some(90_000_000));
+        assert_eq!(ticker.price_change_24h, Some(10_000_000));
+        assert_eq!(ticker.price_change_bps_24h, Some(1_000));
+        assert_eq!(ticker.volume_24h, 4_500_000_000);
+        assert_eq!(ticker.quote_volume_24h, 200_000_000 + 120_000_000 + 90_000_000 + 55_000_000);
+        assert_eq!(ticker.trade_count_24h, 4);
+        assert_eq!((ticker.best_bid, ticker.best_ask), (Some(105_000_000), Some(115_000_000)));
+    }
+
+    #[test]
+    fn test_ticker_drops_trades_older_than_24h() {
+        let market_id = Uuid::new_v4();
+        let mut window = TickerWindow::default();
+        window.record(at("2024-03-05T10:00:00Z"), 100, 5, 500);
+        window.record(at("2024-03-06T09:00:00Z"), 200, 1, 200);
+
+        let ticker = window.ticker(market_id, at("2024-03-06T10:01:00Z"));
+        assert_eq!(ticker.open_24h, Some(200));
+        assert_eq!(ticker.volume_24h, 1);
+        assert_eq!(ticker.trade_count_24h, 1);
+
+        // Once the window is empty only the last price remains.
+        let ticker = window.ticker(market_id, at("2024-03-07T10:00:00Z"));
+        assert_eq!(ticker.last_price, Some(200));
+        assert_eq!(ticker.open_24h, None);
+        assert_eq!(ticker.price_change_24h, None);
+        assert_eq!(ticker.trade_count_24h, 0);
+    }
+
+    #[test]
+    fn test_set_top_reports_changes() {
+        let mut window = TickerWindow::default();
+        assert!(window.set_top(Some(1), None));
+        assert!(!window.set_top(Some(1), None));
+        assert!(window.set_top(Some(1), Some(2)));
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::LazyLock;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::orderbook::OrderbookManager;
+use crate::types::OrderSide;
+use crate::websocket::WsMetrics;
+
+/// Process-wide metrics, shared by code that has no handle on `AppState`
+/// such as [`crate::orderbook::MatchingEngine`].
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Request latencies are mostly sub-millisecond book work plus a few
+/// Postgres round trips.
+const LATENCY_BUCKETS: &[f64] = &[0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+/// Matching itself is in-memory.
+const MATCH_BUCKETS: &[f64] = &[0.00001, 0.000025, 0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.01];
+/// RPC calls include confirmation, which takes seconds.
+const RPC_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+pub struct Metrics {
+    registry: Registry,
+    /// Accepted orders, by market.
+    pub orders_placed: IntCounterVec,
+    /// Cancelled orders, by market and cancel reason.
+    pub orders_cancelled: IntCounterVec,
+    /// Calls rejected by rate limits, by budget and what was limited
+    /// (`ip`, `wallet`, `order_to_trade`).
+    pub rate_limited: IntCounterVec,
+    /// Latency of order requests, by market and operation (`place`, `cancel`).
+    pub order_latency: HistogramVec,
+    /// Time spent in [`crate::orderbook::MatchingEngine::execute`], by market.
+    pub match_latency: HistogramVec,
+    /// Resting quantity per market and side, refreshed on scrape.
+    pub book_depth: IntGaugeVec,
+    /// Resting orders per market and side, refreshed on scrape.
+    pub book_orders: IntGaugeVec,
+    /// Settlement jobs waiting for the worker, refreshed on scrape.
+    pub settlement_queue_depth: IntGauge,
+    /// Finished settlement jobs, by kind (`trade`, `unlock`) and outcome.
+    pub settlement_jobs: IntCounterVec,
+    /// Solana RPC latency, by client method.
+    pub rpc_latency: HistogramVec,
+    /// Postgres pool connections, by state (`idle`, `in_use`), refreshed on scrape.
+    pub db_connections: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("dcex".to_string()), None)
+            .expect("valid metrics namespace");
+
+        let metrics = Self {
+            orders_placed: IntCounterVec::new(
+                Opts::new("orders_placed_total", "Orders accepted by the engine"),
+                &["market"],
+            )
+            .unwrap(),
+            orders_cancelled: IntCounterVec::new(
+                Opts::new("orders_cancelled_total", "Orders cancelled by users or the engine"),
+                &["market", "reason"],
+            )
+            .unwrap(),
+            rate_limited: IntCounterVec::new(
+                Opts::new("rate_limited_total", "Calls rejected by rate limits"),
+                &["budget", "scope"],
+            )
+            .unwrap(),
+            order_latency: HistogramVec::new(
+                HistogramOpts::new("order_request_seconds", "Latency of order placement and cancel requests")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                &["market", "operation"],
+            )
+            .unwrap(),
+            match_latency: HistogramVec::new(
+                HistogramOpts::new("match_seconds", "Time to match an order and fire the triggers it crosses")
+                    .buckets(MATCH_BUCKETS.to_vec()),
+                &["market"],
+            )
+            .unwrap(),
+            book_depth: IntGaugeVec::new(
+                Opts::new("book_depth", "Resting quantity in base atoms"),
+                &["market", "side"],
+            )
+            .unwrap(),
+            book_orders: IntGaugeVec::new(
+                Opts::new("book_orders", "Resting orders"),
+                &["market", "side"],
+            )
+            .unwrap(),
+            settlement_queue_depth: IntGauge::new(
+                "settlement_queue_depth",
+                "Settlement jobs waiting for the worker",
+            )
+            .unwrap(),
+            settlement_jobs: IntCounterVec::new(
+                Opts::new("settlement_jobs_total", "Finished settlement jobs"),
+                &["kind", "outcome"],
+            )
+            .unwrap(),
+            rpc_latency: HistogramVec::new(
+                HistogramOpts::new("rpc_seconds", "Solana RPC latency, including confirmation")
+                    .buckets(RPC_BUCKETS.to_vec()),
+                &["method"],
+            )
+            .unwrap(),
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Postgres pool connections"),
+                &["state"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 11] = [
+            Box::new(metrics.orders_placed.clone()),
+            Box::new(metrics.orders_cancelled.clone()),
+            Box::new(metrics.rate_limited.clone()),
+            Box::new(metrics.order_latency.clone()),
+            Box::new(metrics.match_latency.clone()),
+            Box::new(metrics.book_depth.clone()),
+            Box::new(metrics.book_orders.clone()),
+            Box::new(metrics.settlement_queue_depth.clone()),
+            Box::new(metrics.settlement_jobs.clone()),
+            Box::new(metrics.rpc_latency.clone()),
+            Box::new(metrics.db_connections.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("unique metric names");
+        }
+        metrics
+    }
+
+    /// Exposes the WebSocket manager's own counters. Called once at startup.
+    pub fn register_ws(&self, ws: &WsMetrics) {
+        let counters: [&IntCounter; 4] = [
+            &ws.frames_sent,
+            &ws.frames_dropped,
+            &ws.frames_conflated,
+            &ws.lagging_disconnects,
+        ];
+        for counter in counters {
+            self.registry
+                .register(Box::new(counter.clone()))
+                .expect("WebSocket metrics registered once");
+        }
+        self.registry
+            .register(Box::new(ws.connected_clients.clone()))
+            .expect("WebSocket metrics registered once");
+    }
+
+    /// Sets the per-side book gauges of every market.
+    pub fn observe_books(&self, manager: &OrderbookManager) {
+        for orderbook in manager.books() {
+            let market = orderbook.market_id.to_string();
+            for side in [OrderSide::Buy, OrderSide::Sell] {
+                let (orders, depth) = orderbook.side_totals(side);
+                let side = side_label(side);
+                self.book_orders.with_label_values(&[&market, side]).set(orders);
+                self.book_depth.with_label_values(&[&market, side]).set(depth);
+            }
+        }
+    }
+
+    pub fn observe_db_pool(&self, pool: &sqlx::PgPool) {
+        let size = i64::from(pool.size());
+        let idle = i64::try_from(pool.num_idle()).unwrap_or(i64::MAX);
+        self.db_connections.with_label_values(&["idle"]).set(idle);
+        self.db_connections.with_label_values(&["in_use"]).set((size - idle).max(0));
+    }
+
+    /// Everything registered, in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+fn side_label(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "bid",
+        OrderSide::Sell => "ask",
+    }
+}
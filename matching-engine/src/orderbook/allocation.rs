@@ -0,0 +1,148 @@
+use crate::types::{Market, MatchingAlgorithm};
+
+/// Splits a taker's quantity across the resting orders at one price level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Allocation {
+    #[default]
+    Fifo,
+    ProRata,
+    /// The first `fifo_slice_bps` of the quantity goes in time priority, the
+    /// rest is shared pro-rata over what is left.
+    Hybrid { fifo_slice_bps: i64 },
+}
+
+impl Allocation {
+    pub fn for_market(market: &Market) -> Self {
+        match market.matching_algorithm {
+            MatchingAlgorithm::Fifo => Allocation::Fifo,
+            MatchingAlgorithm::ProRata => Allocation::ProRata,
+            MatchingAlgorithm::Hybrid => Allocation::Hybrid {
+                fifo_slice_bps: i64::from(market.fifo_slice_bps).clamp(0, 10_000),
+            },
+        }
+    }
+
+    /// Returns the fill for each entry of `visible`, given in time priority.
+    ///
+    /// Fills always sum to `min(quantity, visible.sum())` and never exceed an
+    /// entry's visible size.
+    pub fn allocate(&self, quantity: i64, visible: &[i64]) -> Vec<i64> {
+        let mut fills = vec![0; visible.len()];
+        match *self {
+            Allocation::Fifo => {
+                fill_fifo(quantity, visible, &mut fills);
+            }
+            Allocation::ProRata => fill_pro_rata(quantity, visible, &mut fills),
+            Allocation::Hybrid { fifo_slice_bps } => {
+                let slice = (i128::from(quantity) * i128::from(fifo_slice_bps) / 10_000) as i64;
+                let left = quantity - fill_fifo(slice, visible, &mut fills);
+                fill_pro_rata(left, visible, &mut fills);
+            }
+        }
+        fills
+    }
+}
+
+/// Tops up `fills` in time priority. Returns the quantity allocated.
+fn fill_fifo(quantity: i64, visible: &[i64], fills: &mut [i64]) -> i64 {
+    let mut left = quantity;
+    for (fill, &size) in fills.iter_mut().zip(visible) {
+        if left <= 0 {
+            break;
+        }
+        let take = left.min(size - *fill);
+        *fill += take;
+        left -= take;
+    }
+    quantity - left
+}
+
+/// Tops up `fills` in proportion to each entry's unallocated visible size.
+///
+/// Shares are rounded down; the residual (fewer units than there are entries)
+/// is handed out one unit at a time in time priority, so the result depends
+/// only on the queue and never on hashing or float rounding.
+fn fill_pro_rata(quantity: i64, visible: &[i64], fills: &mut [i64]) {
+    let open: Vec<i64> = visible.iter().zip(fills.iter()).map(|(v, f)| v - f).collect();
+    let total: i64 = open.iter().sum();
+    if quantity <= 0 || total <= 0 {
+        return;
+    }
+    if quantity >= total {
+        fills.copy_from_slice(visible);
+        return;
+    }
+
+    let mut allocated = 0;
+    for (fill, &size) in fills.iter_mut().zip(&open) {
+        let share = (i128::from(quantity) * i128::from(size) / i128::from(total)) as i64;
+        *fill += share;
+        allocated += share;
+    }
+
+    let mut residual = quantity - allocated;
+    for (fill, &size) in fills.iter_mut().zip(visible) {
+        if residual == 0 {
+            break;
+        }
+        if *fill < size {
+            *fill += 1;
+            residual -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pro_rata_splits_by_size() {
+        let fills = Allocation::ProRata.allocate(50, &[100, 300, 100]);
+        assert_eq!(fills, vec![10, 30, 10]);
+    }
+
+    #[test]
+    fn test_pro_rata_residual_goes_in_time_priority() {
+        // Exact shares are 3.33 each: 3 + 3 + 3, one unit left for the oldest.
+        let fills = Allocation::ProRata.allocate(10, &[7, 7, 7]);
+        assert_eq!(fills, vec![4, 3, 3]);
+
+        let fills = Allocation::ProRata.allocate(5, &[1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(fills, vec![1, 1, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_pro_rata_fills_always_sum_to_quantity() {
+        let visible = [13, 1, 999, 42, 7, 250];
+        let total: i64 = visible.iter().sum();
+        for quantity in 1..=total + 5 {
+            let fills = Allocation::ProRata.allocate(quantity, &visible);
+            assert_eq!(fills.iter().sum::<i64>(), quantity.min(total));
+            assert!(fills.iter().zip(&visible).all(|(f, v)| f <= v));
+        }
+    }
+
+    #[test]
+    fn test_hybrid_gives_fifo_slice_to_head_of_queue() {
+        let allocation = Allocation::Hybrid { fifo_slice_bps: 4_000 };
+
+        // 40 of 100 go to the head first; the other 60 are shared over the
+        // remaining 10 + 100 + 100 pro-rata (2.86 / 28.57 / 28.57).
+        let fills = allocation.allocate(100, &[50, 100, 100]);
+        assert_eq!(fills, vec![43, 29, 28]);
+        assert_eq!(fills.iter().sum::<i64>(), 100);
+    }
+
+    #[test]
+    fn test_hybrid_fills_always_sum_to_quantity() {
+        let allocation = Allocation::Hybrid { fifo_slice_bps: 2_500 };
+        let visible = [5, 17, 3, 64];
+        let total: i64 = visible.iter().sum();
+        for quantity in 1..=total + 5 {
+            let fills = allocation.allocate(quantity, &visible);
+            assert_eq!(fills.iter().sum::<i64>(), quantity.min(total));
+            assert!(fills.iter().zip(&visible).all(|(f, v)| f <= v));
+        }
+    }
+}
@@ -1,9 +1,20 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::cmp::Reverse;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
-use crate::types::{Order, OrderSide, OrderbookLevel, OrderbookSnapshot};
+use super::allocation::Allocation;
+use super::expiry::{ExpiryEntry, ExpiryQueue};
+use super::triggers::TriggerBook;
+use crate::types::{Order, OrderSide, OrderbookDelta, OrderbookLevel, OrderbookSnapshot};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Standard,
+    /// Shows at most `display_size` at a time; `visible` is what is left of
+    /// the current slice, the rest of `remaining()` is hidden reserve.
+    Iceberg { display_size: i64, visible: i64 },
+}
 
 #[derive(Debug, Clone)]
 pub struct OrderEntry {
@@ -12,12 +23,48 @@ pub struct OrderEntry {
     pub size: i64,
     pub filled: i64,
     pub timestamp: i64,
+    pub kind: EntryKind,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 impl OrderEntry {
     pub fn remaining(&self) -> i64 {
         self.size - self.filled
     }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Quantity that is shown in the book and can be matched right now.
+    pub fn visible_remaining(&self) -> i64 {
+        match self.kind {
+            EntryKind::Standard => self.remaining(),
+            EntryKind::Iceberg { visible, .. } => visible,
+        }
+    }
+
+    pub fn fill(&mut self, amount: i64) {
+        self.filled += amount;
+        if let EntryKind::Iceberg { visible, .. } = &mut self.kind {
+            *visible -= amount;
+        }
+    }
+
+    /// An iceberg whose shown slice is used up but still has reserve.
+    pub fn needs_refresh(&self) -> bool {
+        self.visible_remaining() <= 0 && self.remaining() > 0
+    }
+
+    /// Shows the next slice from reserve. The caller requeues the entry at the
+    /// back of its level, so the refreshed slice loses time priority.
+    pub fn refresh(&mut self, timestamp: i64) {
+        let remaining = self.remaining();
+        if let EntryKind::Iceberg { display_size, visible } = &mut self.kind {
+            *visible = (*display_size).min(remaining);
+        }
+        self.timestamp = timestamp;
+    }
 }
 
 pub struct Orderbook {
@@ -25,7 +72,14 @@ pub struct Orderbook {
     pub bids: BTreeMap<Reverse<i64>, Vec<OrderEntry>>,
     pub asks: BTreeMap<i64, Vec<OrderEntry>>,
     pub order_locations: HashMap<String, (OrderSide, i64)>,
+    pub triggers: TriggerBook,
+    pub allocation: Allocation,
     pub last_price: Option<i64>,
+    /// Number of deltas published for this book so far.
+    pub sequence: u64,
+    /// Bid and ask prices touched since the last [`Orderbook::take_delta`].
+    pub changed_bids: BTreeSet<i64>,
+    pub changed_asks: BTreeSet<i64>,
 }
 
 impl Orderbook {
@@ -35,33 +89,49 @@ impl Orderbook {
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             order_locations: HashMap::new(),
+            triggers: TriggerBook::new(),
+            allocation: Allocation::default(),
             last_price: None,
+            sequence: 0,
+            changed_bids: BTreeSet::new(),
+            changed_asks: BTreeSet::new(),
         }
     }
 
     pub fn add_order(&mut self, order: &Order) {
+        let kind = match order.display_size {
+            Some(display_size) => EntryKind::Iceberg {
+                display_size,
+                visible: display_size.min(order.remaining()),
+            },
+            None => EntryKind::Standard,
+        };
         let entry = OrderEntry {
             order_id: order.order_id.clone(),
             user_wallet: order.user_wallet.clone(),
             size: order.size,
             filled: order.filled,
             timestamp: order.created_at.timestamp_nanos_opt().unwrap_or(0),
+            kind,
+            expires_at: order.expires_at,
         };
 
         match order.side {
             OrderSide::Buy => {
                 self.bids
                     .entry(Reverse(order.price))
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(entry);
                 self.order_locations.insert(order.order_id.clone(), (OrderSide::Buy, order.price));
+                self.changed_bids.insert(order.price);
             }
             OrderSide::Sell => {
                 self.asks
                     .entry(order.price)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(entry);
                 self.order_locations.insert(order.order_id.clone(), (OrderSide::Sell, order.price));
+                self.changed_asks.insert(order.price);
             }
         }
     }
@@ -72,6 +142,7 @@ impl Orderbook {
                 OrderSide::Buy => {
                     if let Some(orders) = self.bids.get_mut(&Reverse(price)) {
                         if let Some(idx) = orders.iter().position(|o| o.order_id == order_id) {
+                            self.changed_bids.insert(price);
                             let entry = orders.remove(idx);
                             if orders.is_empty() {
                                 self.bids.remove(&Reverse(price));
@@ -83,6 +154,7 @@ impl Orderbook {
                 OrderSide::Sell => {
                     if let Some(orders) = self.asks.get_mut(&price) {
                         if let Some(idx) = orders.iter().position(|o| o.order_id == order_id) {
+                            self.changed_asks.insert(price);
                             let entry = orders.remove(idx);
                             if orders.is_empty() {
                                 self.asks.remove(&price);
@@ -103,6 +175,7 @@ impl Orderbook {
                     if let Some(orders) = self.bids.get_mut(&Reverse(*price)) {
                         if let Some(order) = orders.iter_mut().find(|o| o.order_id == order_id) {
                             order.filled += filled_amount;
+                            self.changed_bids.insert(*price);
                             if order.remaining() <= 0 {
                                 self.remove_order(order_id);
                             }
@@ -113,6 +186,7 @@ impl Orderbook {
                     if let Some(orders) = self.asks.get_mut(price) {
                         if let Some(order) = orders.iter_mut().find(|o| o.order_id == order_id) {
                             order.filled += filled_amount;
+                            self.changed_asks.insert(*price);
                             if order.remaining() <= 0 {
                                 self.remove_order(order_id);
                             }
@@ -131,6 +205,18 @@ impl Orderbook {
         self.asks.first_key_value().map(|(price, _)| *price)
     }
 
+    /// Resting orders and their total remaining quantity on one side,
+    /// hidden iceberg quantity included.
+    pub fn side_totals(&self, side: OrderSide) -> (i64, i64) {
+        let levels: Box<dyn Iterator<Item = &Vec<OrderEntry>>> = match side {
+            OrderSide::Buy => Box::new(self.bids.values()),
+            OrderSide::Sell => Box::new(self.asks.values()),
+        };
+        levels.flatten().fold((0, 0), |(orders, quantity), entry| {
+            (orders + 1, quantity + entry.remaining())
+        })
+    }
+
     pub fn spread(&self) -> Option<i64> {
         match (self.best_ask(), self.best_bid()) {
             (Some(ask), Some(bid)) => Some(ask - bid),
@@ -142,11 +228,7 @@ impl Orderbook {
         self.bids
             .iter()
             .take(depth)
-            .map(|(Reverse(price), orders)| OrderbookLevel {
-                price: *price,
-                size: orders.iter().map(|o| o.remaining()).sum(),
-                order_count: orders.len(),
-            })
+            .map(|(Reverse(price), orders)| level(*price, orders))
             .collect()
     }
 
@@ -154,17 +236,14 @@ impl Orderbook {
         self.asks
             .iter()
             .take(depth)
-            .map(|(price, orders)| OrderbookLevel {
-                price: *price,
-                size: orders.iter().map(|o| o.remaining()).sum(),
-                order_count: orders.len(),
-            })
+            .map(|(price, orders)| level(*price, orders))
             .collect()
     }
 
     pub fn snapshot(&self, depth: usize) -> OrderbookSnapshot {
         OrderbookSnapshot {
             market_id: self.market_id,
+            sequence: self.sequence,
             bids: self.get_bids(depth),
             asks: self.get_asks(depth),
             last_price: self.last_price,
@@ -172,22 +251,79 @@ impl Orderbook {
         }
     }
 
+    /// Collects the levels changed since the previous call and advances the
+    /// sequence. Returns `None`, leaving the sequence alone, if nothing changed.
+    pub fn take_delta(&mut self) -> Option<OrderbookDelta> {
+        if self.changed_bids.is_empty() && self.changed_asks.is_empty() {
+            return None;
+        }
+
+        let bids = std::mem::take(&mut self.changed_bids)
+            .into_iter()
+            .rev()
+            .map(|price| match self.bids.get(&Reverse(price)) {
+                Some(orders) => level(price, orders),
+                None => level(price, &[]),
+            })
+            .collect();
+        let asks = std::mem::take(&mut self.changed_asks)
+            .into_iter()
+            .map(|price| match self.asks.get(&price) {
+                Some(orders) => level(price, orders),
+                None => level(price, &[]),
+            })
+            .collect();
+
+        let prev_sequence = self.sequence;
+        self.sequence += 1;
+
+        Some(OrderbookDelta {
+            market_id: self.market_id,
+            sequence: self.sequence,
+            prev_sequence,
+            bids,
+            asks,
+            last_price: self.last_price,
+            timestamp: Utc::now(),
+        })
+    }
+
     pub fn set_last_price(&mut self, price: i64) {
         self.last_price = Some(price);
     }
 }
 
+fn level(price: i64, orders: &[OrderEntry]) -> OrderbookLevel {
+    OrderbookLevel {
+        price,
+        size: orders.iter().map(|o| o.visible_remaining()).sum(),
+        order_count: orders.len(),
+    }
+}
+
 pub struct OrderbookManager {
     orderbooks: HashMap<Uuid, Orderbook>,
+    expiries: ExpiryQueue,
 }
 
 impl OrderbookManager {
     pub fn new() -> Self {
         Self {
             orderbooks: HashMap::new(),
+            expiries: ExpiryQueue::new(),
+        }
+    }
+
+    pub fn schedule_expiry(&mut self, order: &Order) {
+        if let Some(expires_at) = order.expires_at {
+            self.expiries.schedule(order.market_id, &order.order_id, expires_at);
         }
     }
 
+    pub fn take_expired(&mut self, now: DateTime<Utc>) -> Vec<ExpiryEntry> {
+        self.expiries.pop_expired(now)
+    }
+
     pub fn get_or_create(&mut self, market_id: Uuid) -> &mut Orderbook {
         self.orderbooks
             .entry(market_id)
@@ -201,4 +337,8 @@ impl OrderbookManager {
     pub fn get_mut(&mut self, market_id: &Uuid) -> Option<&mut Orderbook> {
         self.orderbooks.get_mut(market_id)
     }
+
+    pub fn books(&self) -> impl Iterator<Item = &Orderbook> {
+        self.orderbooks.values()
+    }
 }
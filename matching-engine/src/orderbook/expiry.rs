@@ -0,0 +1,75 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ExpiryEntry {
+    pub expires_at: DateTime<Utc>,
+    pub market_id: Uuid,
+    pub order_id: String,
+}
+
+/// Min-heap of good-till-time deadlines across all books.
+///
+/// Entries are never removed when an order fills or is cancelled early; the
+/// sweeper re-checks the order status when an entry comes due instead.
+#[derive(Default)]
+pub struct ExpiryQueue {
+    heap: BinaryHeap<Reverse<ExpiryEntry>>,
+}
+
+impl ExpiryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schedule(&mut self, market_id: Uuid, order_id: &str, expires_at: DateTime<Utc>) {
+        self.heap.push(Reverse(ExpiryEntry {
+            expires_at,
+            market_id,
+            order_id: order_id.to_string(),
+        }));
+    }
+
+    /// Pops every entry due at or before `now`, earliest first.
+    pub fn pop_expired(&mut self, now: DateTime<Utc>) -> Vec<ExpiryEntry> {
+        let mut expired = Vec::new();
+        while let Some(Reverse(entry)) = self.heap.peek() {
+            if entry.expires_at > now {
+                break;
+            }
+            if let Some(Reverse(entry)) = self.heap.pop() {
+                expired.push(entry);
+            }
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_pop_expired_in_deadline_order() {
+        let now = Utc::now();
+        let market_id = Uuid::new_v4();
+        let mut queue = ExpiryQueue::new();
+
+        queue.schedule(market_id, "late", now + Duration::seconds(30));
+        queue.schedule(market_id, "second", now - Duration::seconds(1));
+        queue.schedule(market_id, "first", now - Duration::seconds(10));
+
+        let expired: Vec<_> = queue
+            .pop_expired(now)
+            .into_iter()
+            .map(|e| e.order_id)
+            .collect();
+        assert_eq!(expired, vec!["first", "second"]);
+
+        assert!(queue.pop_expired(now).is_empty());
+        assert_eq!(queue.pop_expired(now + Duration::seconds(30)).len(), 1);
+    }
+}
@@ -1,9 +1,11 @@
 use std::cmp::Reverse;
-use uuid::Uuid;
+use std::collections::HashMap;
 use chrono::Utc;
 
-use super::orderbook::{Orderbook, OrderEntry};
-use crate::types::{Order, OrderSide, Trade};
+use super::allocation::Allocation;
+use super::book::{OrderEntry, Orderbook};
+use crate::metrics::METRICS;
+use crate::types::{Order, OrderSide};
 
 #[derive(Debug, Clone)]
 pub struct MatchResult {
@@ -21,9 +23,71 @@ pub struct TradeMatch {
     pub size: i64,
 }
 
+/// One order's pass through the matching engine.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    /// The order with `filled` updated by this pass.
+    pub order: Order,
+    pub result: MatchResult,
+    /// Fired from the trigger book rather than submitted directly.
+    pub triggered: bool,
+    /// The unfilled remainder was added to the book.
+    pub rested: bool,
+}
+
 pub struct MatchingEngine;
 
 impl MatchingEngine {
+    /// Matches `incoming`, rests any remainder, then fires trigger orders
+    /// crossed by the resulting last price.
+    ///
+    /// Cascade rules:
+    /// - triggers are only evaluated after an order has finished matching;
+    /// - every trigger crossed by the last price at that point fires as one
+    ///   batch, in [`TriggerBook::take_crossed`] order;
+    /// - each fired order matches and rests before the next one in the batch;
+    /// - trades made by the batch can cross further triggers, which fire as the
+    ///   next batch. This repeats until a batch crosses nothing.
+    ///
+    /// The returned executions are in processing order, `incoming` first.
+    ///
+    /// [`TriggerBook::take_crossed`]: super::TriggerBook::take_crossed
+    pub fn execute(orderbook: &mut Orderbook, incoming: &Order) -> Vec<Execution> {
+        let _timer = METRICS.match_latency
+            .with_label_values(&[&orderbook.market_id.to_string()])
+            .start_timer();
+        let mut executions = vec![Self::execute_one(orderbook, incoming.clone(), false)];
+
+        while let Some(last_price) = orderbook.last_price {
+            let fired = orderbook.triggers.take_crossed(last_price);
+            if fired.is_empty() {
+                break;
+            }
+            for order in fired {
+                executions.push(Self::execute_one(orderbook, order, true));
+            }
+        }
+
+        executions
+    }
+
+    fn execute_one(orderbook: &mut Orderbook, mut order: Order, triggered: bool) -> Execution {
+        let result = Self::match_order(orderbook, &order);
+        order.filled = order.size - result.remaining_size;
+
+        let rested = result.remaining_size > 0 && order.order_type.rests();
+        if rested {
+            orderbook.add_order(&order);
+        }
+
+        Execution {
+            order,
+            result,
+            triggered,
+            rested,
+        }
+    }
+
     pub fn match_order(orderbook: &mut Orderbook, incoming: &Order) -> MatchResult {
         let mut trades = Vec::new();
         let mut remaining = incoming.size - incoming.filled;
@@ -55,43 +119,24 @@ impl MatchingEngine {
         trades: &mut Vec<TradeMatch>,
         remaining: &mut i64,
     ) {
+        let allocation = orderbook.allocation;
         let mut prices_to_remove = Vec::new();
-        
+
         for (price, orders) in orderbook.asks.iter_mut() {
             if *price > incoming.price {
                 break;
             }
 
-            let mut orders_to_remove = Vec::new();
-            
-            for (idx, maker_order) in orders.iter_mut().enumerate() {
-                if *remaining <= 0 {
-                    break;
-                }
-
-                let fill_size = (*remaining).min(maker_order.remaining());
-                
-                trades.push(TradeMatch {
-                    maker_order_id: maker_order.order_id.clone(),
-                    maker_wallet: maker_order.user_wallet.clone(),
-                    taker_order_id: incoming.order_id.clone(),
-                    taker_wallet: incoming.user_wallet.clone(),
-                    price: *price,
-                    size: fill_size,
-                });
-
-                maker_order.filled += fill_size;
-                *remaining -= fill_size;
-
-                if maker_order.remaining() <= 0 {
-                    orders_to_remove.push(idx);
-                    orderbook.order_locations.remove(&maker_order.order_id);
-                }
-            }
-
-            for idx in orders_to_remove.into_iter().rev() {
-                orders.remove(idx);
-            }
+            Self::match_level(
+                *price,
+                allocation,
+                orders,
+                &mut orderbook.order_locations,
+                incoming,
+                trades,
+                remaining,
+            );
+            orderbook.changed_asks.insert(*price);
 
             if orders.is_empty() {
                 prices_to_remove.push(*price);
@@ -113,55 +158,101 @@ impl MatchingEngine {
         trades: &mut Vec<TradeMatch>,
         remaining: &mut i64,
     ) {
+        let allocation = orderbook.allocation;
         let mut prices_to_remove = Vec::new();
-        
+
         for (Reverse(price), orders) in orderbook.bids.iter_mut() {
             if *price < incoming.price {
                 break;
             }
 
-            let mut orders_to_remove = Vec::new();
-            
-            for (idx, maker_order) in orders.iter_mut().enumerate() {
-                if *remaining <= 0 {
-                    break;
+            Self::match_level(
+                *price,
+                allocation,
+                orders,
+                &mut orderbook.order_locations,
+                incoming,
+                trades,
+                remaining,
+            );
+            orderbook.changed_bids.insert(*price);
+
+            if orders.is_empty() {
+                prices_to_remove.push(Reverse(*price));
+            }
+
+            if *remaining <= 0 {
+                break;
+            }
+        }
+
+        for price in prices_to_remove {
+            orderbook.bids.remove(&price);
+        }
+    }
+
+    /// Fills `incoming` against one price level using the book's allocation.
+    ///
+    /// Makers past their deadline are evicted first: the chain would reject
+    /// their fills, and the expiry sweeper still marks them expired and
+    /// unlocks their funds. Each round then shares the remaining quantity over
+    /// the visible size of every entry. Filled entries leave the level;
+    /// icebergs whose slice ran out are refreshed from reserve and requeued at
+    /// the back, and the next round can reach them again.
+    fn match_level(
+        price: i64,
+        allocation: Allocation,
+        orders: &mut Vec<OrderEntry>,
+        order_locations: &mut HashMap<String, (OrderSide, i64)>,
+        incoming: &Order,
+        trades: &mut Vec<TradeMatch>,
+        remaining: &mut i64,
+    ) {
+        let now = Utc::now();
+        orders.retain(|maker_order| {
+            let expired = maker_order.is_expired(now);
+            if expired {
+                order_locations.remove(&maker_order.order_id);
+            }
+            !expired
+        });
+
+        while *remaining > 0 && !orders.is_empty() {
+            let visible: Vec<i64> = orders.iter().map(|o| o.visible_remaining()).collect();
+            let fills = allocation.allocate(*remaining, &visible);
+
+            for (maker_order, fill_size) in orders.iter_mut().zip(fills) {
+                if fill_size <= 0 {
+                    continue;
                 }
 
-                let fill_size = (*remaining).min(maker_order.remaining());
-                
                 trades.push(TradeMatch {
                     maker_order_id: maker_order.order_id.clone(),
                     maker_wallet: maker_order.user_wallet.clone(),
                     taker_order_id: incoming.order_id.clone(),
                     taker_wallet: incoming.user_wallet.clone(),
-                    price: *price,
+                    price,
                     size: fill_size,
                 });
 
-                maker_order.filled += fill_size;
+                maker_order.fill(fill_size);
                 *remaining -= fill_size;
+            }
 
+            let mut refreshed = Vec::new();
+            orders.retain_mut(|maker_order| {
                 if maker_order.remaining() <= 0 {
-                    orders_to_remove.push(idx);
-                    orderbook.order_locations.remove(&maker_order.order_id);
+                    order_locations.remove(&maker_order.order_id);
+                    false
+                } else if maker_order.needs_refresh() {
+                    maker_order.refresh(Utc::now().timestamp_nanos_opt().unwrap_or(0));
+                    refreshed.push(maker_order.clone());
+                    false
+                } else {
+                    true
                 }
-            }
-
-            for idx in orders_to_remove.into_iter().rev() {
-                orders.remove(idx);
-            }
-
-            if orders.is_empty() {
-                prices_to_remove.push(Reverse(*price));
-            }
-
-            if *remaining <= 0 {
-                break;
-            }
-        }
-
-        for price in prices_to_remove {
-            orderbook.bids.remove(&price);
+            });
+            orders.extend(refreshed);
         }
     }
 }
@@ -169,6 +260,8 @@ impl MatchingEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use uuid::Uuid;
+    use crate::types::OrderType;
 
     fn create_test_order(
         order_id: &str,
@@ -187,7 +280,12 @@ mod tests {
             size,
             filled: 0,
             status: crate::types::OrderStatus::Pending,
+            order_type: OrderType::Limit,
+            trigger_price: None,
+            triggered_at: None,
+            display_size: None,
             on_chain_signature: None,
+            expires_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -225,6 +323,28 @@ mod tests {
         assert_eq!(result.remaining_size, 5);
     }
 
+    #[test]
+    fn test_expired_maker_is_skipped_and_evicted() {
+        let market_id = Uuid::new_v4();
+        let mut orderbook = Orderbook::new(market_id);
+
+        let mut expired = create_test_order("1", "seller", OrderSide::Sell, 100, 10);
+        expired.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        orderbook.add_order(&expired);
+        let mut live = create_test_order("2", "seller", OrderSide::Sell, 100, 10);
+        live.expires_at = Some(Utc::now() + chrono::Duration::seconds(60));
+        orderbook.add_order(&live);
+
+        let buy_order = create_test_order("3", "buyer", OrderSide::Buy, 100, 15);
+        let result = MatchingEngine::match_order(&mut orderbook, &buy_order);
+
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].maker_order_id, "2");
+        assert_eq!(result.remaining_size, 5);
+        assert!(!orderbook.order_locations.contains_key("1"));
+        assert!(orderbook.asks.is_empty());
+    }
+
     #[test]
     fn test_no_match_price_gap() {
         let market_id = Uuid::new_v4();
@@ -239,4 +359,313 @@ mod tests {
         assert_eq!(result.trades.len(), 0);
         assert_eq!(result.remaining_size, 5);
     }
+
+    fn create_trigger_order(
+        order_id: &str,
+        side: OrderSide,
+        order_type: OrderType,
+        trigger_price: i64,
+        price: i64,
+        size: i64,
+    ) -> Order {
+        Order {
+            order_type,
+            trigger_price: Some(trigger_price),
+            ..create_test_order(order_id, "trigger", side, price, size)
+        }
+    }
+
+    #[test]
+    fn test_partial_fill_remainder_rests() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_test_order("1", "seller", OrderSide::Sell, 100, 5));
+
+        let buy_order = create_test_order("2", "buyer", OrderSide::Buy, 100, 8);
+        let executions = MatchingEngine::execute(&mut orderbook, &buy_order);
+
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].order.filled, 5);
+        assert!(executions[0].rested);
+        assert_eq!(orderbook.best_bid(), Some(100));
+        assert_eq!(orderbook.get_bids(1)[0].size, 3);
+    }
+
+    #[test]
+    fn test_stop_market_fires_when_last_price_crosses() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_test_order("b1", "bidder", OrderSide::Buy, 100, 5));
+        orderbook.add_order(&create_test_order("b2", "bidder", OrderSide::Buy, 95, 5));
+        orderbook.triggers.insert(&create_trigger_order(
+            "stop", OrderSide::Sell, OrderType::StopMarket, 98, 90, 8,
+        ));
+
+        let first = create_test_order("s1", "seller", OrderSide::Sell, 100, 5);
+        let executions = MatchingEngine::execute(&mut orderbook, &first);
+        assert_eq!(executions.len(), 1);
+        assert_eq!(orderbook.last_price, Some(100));
+
+        let second = create_test_order("s2", "seller", OrderSide::Sell, 95, 2);
+        let executions = MatchingEngine::execute(&mut orderbook, &second);
+        assert_eq!(executions.len(), 2);
+
+        let fired = &executions[1];
+        assert!(fired.triggered);
+        assert_eq!(fired.order.order_id, "stop");
+        assert_eq!(fired.order.filled, 3);
+        // Market variants drop what the book could not fill.
+        assert!(!fired.rested);
+        assert!(orderbook.best_ask().is_none());
+        assert_eq!(orderbook.triggers.len(), 0);
+    }
+
+    #[test]
+    fn test_stop_limit_remainder_rests_after_trigger() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_test_order("a1", "seller", OrderSide::Sell, 105, 5));
+        orderbook.add_order(&create_test_order("a2", "seller", OrderSide::Sell, 110, 5));
+        orderbook.triggers.insert(&create_trigger_order(
+            "stop", OrderSide::Buy, OrderType::StopLimit, 105, 106, 4,
+        ));
+
+        let taker = create_test_order("t", "buyer", OrderSide::Buy, 105, 3);
+        let executions = MatchingEngine::execute(&mut orderbook, &taker);
+
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[1].order.filled, 2);
+        assert!(executions[1].rested);
+        assert_eq!(orderbook.best_bid(), Some(106));
+    }
+
+    #[test]
+    fn test_cascading_triggers_fire_in_batches() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_test_order("b1", "bidder", OrderSide::Buy, 100, 5));
+        orderbook.add_order(&create_test_order("b2", "bidder", OrderSide::Buy, 96, 5));
+        orderbook.add_order(&create_test_order("b3", "bidder", OrderSide::Buy, 93, 5));
+        orderbook.triggers.insert(&create_trigger_order(
+            "deep", OrderSide::Sell, OrderType::StopMarket, 94, 1, 5,
+        ));
+        orderbook.triggers.insert(&create_trigger_order(
+            "first", OrderSide::Sell, OrderType::StopMarket, 98, 1, 1,
+        ));
+        orderbook.triggers.insert(&create_trigger_order(
+            "second", OrderSide::Sell, OrderType::StopMarket, 98, 1, 1,
+        ));
+
+        let taker = create_test_order("t", "seller", OrderSide::Sell, 96, 10);
+        let executions = MatchingEngine::execute(&mut orderbook, &taker);
+
+        // 96 crosses both 98 stops (same price: arrival order); their fills at
+        // 93 then cross the 94 stop in a second batch.
+        let fired: Vec<_> = executions
+            .iter()
+            .skip(1)
+            .map(|e| e.order.order_id.as_str())
+            .collect();
+        assert_eq!(fired, vec!["first", "second", "deep"]);
+        assert_eq!(executions[3].order.filled, 3);
+        assert_eq!(orderbook.last_price, Some(93));
+        assert!(orderbook.best_bid().is_none());
+    }
+
+    fn create_iceberg_order(
+        order_id: &str,
+        side: OrderSide,
+        price: i64,
+        size: i64,
+        display_size: i64,
+    ) -> Order {
+        Order {
+            display_size: Some(display_size),
+            ..create_test_order(order_id, "iceberg", side, price, size)
+        }
+    }
+
+    #[test]
+    fn test_iceberg_shows_only_visible_size() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Sell, 100, 50, 5));
+        orderbook.add_order(&create_test_order("plain", "seller", OrderSide::Sell, 100, 2));
+
+        let asks = orderbook.get_asks(1);
+        assert_eq!(asks[0].size, 7);
+        assert_eq!(asks[0].order_count, 2);
+    }
+
+    #[test]
+    fn test_iceberg_refresh_requeues_at_back_of_level() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Sell, 100, 10, 3));
+        orderbook.add_order(&create_test_order("plain", "seller", OrderSide::Sell, 100, 5));
+
+        // Consuming the 3 visible loses the iceberg its place: the next unit
+        // comes from the order queued behind it.
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 100, 4);
+        let result = MatchingEngine::match_order(&mut orderbook, &buy_order);
+
+        let fills: Vec<_> = result
+            .trades
+            .iter()
+            .map(|t| (t.maker_order_id.as_str(), t.size))
+            .collect();
+        assert_eq!(fills, vec![("ice", 3), ("plain", 1)]);
+
+        let level = &orderbook.asks[&100];
+        assert_eq!(level[0].order_id, "plain");
+        assert_eq!(level[1].order_id, "ice");
+        assert_eq!(level[1].visible_remaining(), 3);
+        assert_eq!(level[1].remaining(), 7);
+        assert_eq!(orderbook.get_asks(1)[0].size, 4 + 3);
+    }
+
+    #[test]
+    fn test_iceberg_refreshes_repeatedly_for_large_taker() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Buy, 100, 10, 3));
+
+        let sell_order = create_test_order("t", "seller", OrderSide::Sell, 100, 8);
+        let result = MatchingEngine::match_order(&mut orderbook, &sell_order);
+
+        let sizes: Vec<_> = result.trades.iter().map(|t| t.size).collect();
+        assert_eq!(sizes, vec![3, 3, 2]);
+        assert_eq!(result.remaining_size, 0);
+
+        // 2 remain: 1 still showing from the third slice, 1 hidden in reserve.
+        let bids = orderbook.get_bids(1);
+        assert_eq!(bids[0].size, 1);
+        assert_eq!(orderbook.bids[&Reverse(100)][0].remaining(), 2);
+    }
+
+    #[test]
+    fn test_iceberg_last_slice_is_capped_by_reserve() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Sell, 100, 7, 3));
+
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 100, 6);
+        MatchingEngine::match_order(&mut orderbook, &buy_order);
+        assert_eq!(orderbook.get_asks(1)[0].size, 1);
+
+        let sweep = create_test_order("t2", "buyer", OrderSide::Buy, 100, 5);
+        let result = MatchingEngine::match_order(&mut orderbook, &sweep);
+        assert_eq!(result.remaining_size, 4);
+        assert!(orderbook.best_ask().is_none());
+        assert!(!orderbook.order_locations.contains_key("ice"));
+    }
+
+    #[test]
+    fn test_pro_rata_level_fills_sum_to_taker() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.allocation = Allocation::ProRata;
+        orderbook.add_order(&create_test_order("a", "s1", OrderSide::Sell, 100, 10));
+        orderbook.add_order(&create_test_order("b", "s2", OrderSide::Sell, 100, 20));
+        orderbook.add_order(&create_test_order("c", "s3", OrderSide::Sell, 101, 50));
+
+        // 40 clears level 100 (30), then takes 10 at 101.
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 101, 40);
+        let result = MatchingEngine::match_order(&mut orderbook, &buy_order);
+        assert_eq!(result.remaining_size, 0);
+        assert_eq!(result.trades.iter().map(|t| t.size).sum::<i64>(), 40);
+
+        // 10 over 10 + 20 splits 3.33 / 6.67: the residual unit goes to "a".
+        let sell_order = create_test_order("t2", "seller", OrderSide::Sell, 99, 10);
+        orderbook.add_order(&create_test_order("d", "b1", OrderSide::Buy, 99, 10));
+        orderbook.add_order(&create_test_order("e", "b2", OrderSide::Buy, 99, 20));
+        let result = MatchingEngine::match_order(&mut orderbook, &sell_order);
+        let fills: Vec<_> = result
+            .trades
+            .iter()
+            .map(|t| (t.maker_order_id.as_str(), t.size))
+            .collect();
+        assert_eq!(fills, vec![("d", 4), ("e", 6)]);
+    }
+
+    #[test]
+    fn test_pro_rata_refreshes_icebergs_between_rounds() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        orderbook.allocation = Allocation::ProRata;
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Sell, 100, 20, 5));
+        orderbook.add_order(&create_test_order("plain", "seller", OrderSide::Sell, 100, 5));
+
+        // Round one takes all 10 visible, round two the refreshed slice.
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 100, 12);
+        let result = MatchingEngine::match_order(&mut orderbook, &buy_order);
+        let fills: Vec<_> = result
+            .trades
+            .iter()
+            .map(|t| (t.maker_order_id.as_str(), t.size))
+            .collect();
+        assert_eq!(fills, vec![("ice", 5), ("plain", 5), ("ice", 2)]);
+        assert_eq!(orderbook.get_asks(1)[0].size, 3);
+    }
+
+    #[test]
+    fn test_delta_reports_changed_levels_in_sequence() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        assert!(orderbook.take_delta().is_none());
+
+        orderbook.add_order(&create_test_order("a", "s1", OrderSide::Sell, 100, 5));
+        orderbook.add_order(&create_test_order("b", "s2", OrderSide::Sell, 101, 5));
+        orderbook.add_order(&create_test_order("c", "b1", OrderSide::Buy, 98, 5));
+        orderbook.add_order(&create_test_order("d", "b2", OrderSide::Buy, 99, 5));
+
+        let delta = orderbook.take_delta().unwrap();
+        assert_eq!((delta.prev_sequence, delta.sequence), (0, 1));
+        let bids: Vec<_> = delta.bids.iter().map(|l| l.price).collect();
+        let asks: Vec<_> = delta.asks.iter().map(|l| l.price).collect();
+        assert_eq!(bids, vec![99, 98]);
+        assert_eq!(asks, vec![100, 101]);
+        assert!(orderbook.take_delta().is_none());
+
+        // Sweeping 100 and part of 101 removes one level and shrinks the next.
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 101, 7);
+        MatchingEngine::match_order(&mut orderbook, &buy_order);
+
+        let delta = orderbook.take_delta().unwrap();
+        assert_eq!((delta.prev_sequence, delta.sequence), (1, 2));
+        assert!(delta.bids.is_empty());
+        let asks: Vec<_> = delta.asks.iter().map(|l| (l.price, l.size)).collect();
+        assert_eq!(asks, vec![(100, 0), (101, 3)]);
+        assert_eq!(delta.last_price, Some(101));
+
+        orderbook.remove_order("c");
+        let delta = orderbook.take_delta().unwrap();
+        assert_eq!(delta.prev_sequence, 2);
+        assert_eq!(delta.bids[0].price, 98);
+        assert_eq!(delta.bids[0].order_count, 0);
+        assert_eq!(orderbook.snapshot(10).sequence, 3);
+    }
+
+    #[test]
+    fn test_deltas_rebuild_snapshot() {
+        let mut orderbook = Orderbook::new(Uuid::new_v4());
+        let mut asks = std::collections::BTreeMap::new();
+
+        let mut apply = |orderbook: &mut Orderbook| {
+            if let Some(delta) = orderbook.take_delta() {
+                for level in delta.asks {
+                    if level.size == 0 {
+                        asks.remove(&level.price);
+                    } else {
+                        asks.insert(level.price, level.size);
+                    }
+                }
+            }
+            orderbook
+                .get_asks(usize::MAX)
+                .iter()
+                .map(|l| (l.price, l.size))
+                .collect::<Vec<_>>()
+                == asks.iter().map(|(p, s)| (*p, *s)).collect::<Vec<_>>()
+        };
+
+        orderbook.add_order(&create_iceberg_order("ice", OrderSide::Sell, 100, 20, 4));
+        assert!(apply(&mut orderbook));
+        orderbook.add_order(&create_test_order("a", "s1", OrderSide::Sell, 102, 5));
+        assert!(apply(&mut orderbook));
+        let buy_order = create_test_order("t", "buyer", OrderSide::Buy, 102, 6);
+        MatchingEngine::match_order(&mut orderbook, &buy_order);
+        assert!(apply(&mut orderbook));
+        orderbook.remove_order("a");
+        assert!(apply(&mut orderbook));
+    }
 }
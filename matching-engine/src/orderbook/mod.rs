@@ -1,5 +1,12 @@
-mod orderbook;
+mod book;
+mod allocation;
 mod matching;
+mod expiry;
+mod triggers;
+mod recovery;
 
-pub use orderbook::*;
+pub use book::*;
+pub use allocation::*;
 pub use matching::*;
+pub use triggers::*;
+pub use recovery::*;
@@ -0,0 +1,136 @@
+use std::collections::{BTreeSet, HashMap};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use super::allocation::Allocation;
+use super::book::OrderbookManager;
+use crate::db;
+use crate::error::Result;
+use crate::types::{Order, OrderStatus};
+
+/// Puts an open order back where it was before a restart: untriggered stop
+/// and take-profit orders into the trigger book, the rest into the price
+/// levels. Callers restore oldest first so time priority is kept. Returns
+/// false for a market order that was still open, which cannot rest.
+pub fn restore_order(manager: &mut OrderbookManager, order: &Order) -> bool {
+    let orderbook = manager.get_or_create(order.market_id);
+    let parked = order.triggered_at.is_none() && orderbook.triggers.insert(order);
+    if !parked {
+        if !order.order_type.rests() {
+            return false;
+        }
+        orderbook.add_order(order);
+    }
+    manager.schedule_expiry(order);
+    true
+}
+
+/// Outcome of [`recover`].
+#[derive(Debug)]
+pub struct Recovery {
+    pub restored: usize,
+    /// Open market orders, e.g. a stop-market that triggered just before the
+    /// restart. Their remainder is cancelled as it would have been had the
+    /// match finished.
+    pub cancelled: Vec<Order>,
+}
+
+/// Rebuilds every book from the open orders in Postgres. The books are only
+/// swapped in once everything is read, so a failed attempt can be retried.
+pub async fn recover(pool: &PgPool, manager: &RwLock<OrderbookManager>) -> Result<Recovery> {
+    let orders = db::get_open_orders(pool).await?;
+    let last_prices = db::get_last_prices(pool).await?;
+
+    let market_ids: BTreeSet<_> = orders.iter().map(|order| order.market_id).collect();
+    let mut allocations = HashMap::new();
+    for market_id in market_ids {
+        if let Some(market) = db::get_market(pool, market_id).await? {
+            allocations.insert(market_id, Allocation::for_market(&market));
+        }
+    }
+
+    let mut rebuilt = OrderbookManager::new();
+    for (market_id, price) in last_prices {
+        rebuilt.get_or_create(market_id).set_last_price(price);
+    }
+    for (market_id, allocation) in allocations {
+        rebuilt.get_or_create(market_id).allocation = allocation;
+    }
+    let mut restored = 0;
+    let mut cancelled = Vec::new();
+    for order in &orders {
+        if restore_order(&mut rebuilt, order) {
+            restored += 1;
+        } else {
+            tracing::warn!("Cancelling open {:?} order {} on recovery", order.order_type, order.order_id);
+            cancelled.push(
+                db::update_order_status(pool, &order.order_id, OrderStatus::Cancelled, order.filled).await?,
+            );
+        }
+    }
+
+    // Subscribers start from a snapshot, so the rebuild itself is not a delta.
+    let market_ids: Vec<_> = rebuilt.books().map(|orderbook| orderbook.market_id).collect();
+    for market_id in market_ids {
+        if let Some(orderbook) = rebuilt.get_mut(&market_id) {
+            orderbook.changed_bids.clear();
+            orderbook.changed_asks.clear();
+        }
+    }
+
+    *manager.write().await = rebuilt;
+    Ok(Recovery { restored, cancelled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+    use crate::types::{OrderSide, OrderType};
+
+    fn order(order_id: &str, market_id: Uuid, order_type: OrderType, trigger_price: Option<i64>) -> Order {
+        Order {
+            id: 1,
+            order_id: order_id.to_string(),
+            user_wallet: "wallet".to_string(),
+            market_id,
+            side: OrderSide::Buy,
+            price: 100,
+            size: 10,
+            filled: 4,
+            status: OrderStatus::PartiallyFilled,
+            order_type,
+            trigger_price,
+            triggered_at: None,
+            display_size: None,
+            on_chain_signature: None,
+            expires_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_restore_order_rests_or_parks() {
+        let market_id = Uuid::new_v4();
+        let mut manager = OrderbookManager::new();
+
+        assert!(restore_order(&mut manager, &order("limit", market_id, OrderType::Limit, None)));
+        assert!(restore_order(&mut manager, &order("stop", market_id, OrderType::StopLimit, Some(120))));
+
+        let mut triggered = order("fired", market_id, OrderType::StopLimit, Some(90));
+        triggered.triggered_at = Some(Utc::now());
+        assert!(restore_order(&mut manager, &triggered));
+
+        let mut fired_market = order("fired-market", market_id, OrderType::StopMarket, Some(90));
+        fired_market.triggered_at = Some(Utc::now());
+        assert!(!restore_order(&mut manager, &fired_market));
+
+        let orderbook = manager.get(&market_id).unwrap();
+        assert_eq!(orderbook.side_totals(OrderSide::Buy), (2, 12));
+        assert_eq!(orderbook.triggers.len(), 1);
+        assert!(orderbook.order_locations.contains_key("limit"));
+        assert!(orderbook.order_locations.contains_key("fired"));
+    }
+}
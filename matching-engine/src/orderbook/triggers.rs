@@ -0,0 +1,122 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::{Order, OrderSide, OrderType};
+
+/// Which way the last trade price has to move for a trigger order to fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerDirection {
+    /// Fires once `last_price >= trigger_price` (stop buys, take-profit sells).
+    Rise,
+    /// Fires once `last_price <= trigger_price` (stop sells, take-profit buys).
+    Fall,
+}
+
+impl TriggerDirection {
+    pub fn of(order_type: OrderType, side: OrderSide) -> Option<Self> {
+        match (order_type, side) {
+            (OrderType::Limit, _) => None,
+            (OrderType::StopMarket | OrderType::StopLimit, OrderSide::Buy)
+            | (OrderType::TakeProfitMarket | OrderType::TakeProfitLimit, OrderSide::Sell) => {
+                Some(TriggerDirection::Rise)
+            }
+            (OrderType::StopMarket | OrderType::StopLimit, OrderSide::Sell)
+            | (OrderType::TakeProfitMarket | OrderType::TakeProfitLimit, OrderSide::Buy) => {
+                Some(TriggerDirection::Fall)
+            }
+        }
+    }
+
+    pub fn is_crossed(&self, trigger_price: i64, last_price: i64) -> bool {
+        match self {
+            TriggerDirection::Rise => last_price >= trigger_price,
+            TriggerDirection::Fall => last_price <= trigger_price,
+        }
+    }
+}
+
+/// Untriggered stop and take-profit orders, keyed by trigger price and then
+/// arrival sequence so firing order is deterministic.
+#[derive(Default)]
+pub struct TriggerBook {
+    rising: BTreeMap<(i64, u64), Order>,
+    falling: BTreeMap<(Reverse<i64>, u64), Order>,
+    locations: HashMap<String, (TriggerDirection, i64, u64)>,
+    next_seq: u64,
+}
+
+impl TriggerBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parks a trigger order. Returns `false` for orders without a trigger.
+    pub fn insert(&mut self, order: &Order) -> bool {
+        let (Some(direction), Some(trigger_price)) =
+            (TriggerDirection::of(order.order_type, order.side), order.trigger_price)
+        else {
+            return false;
+        };
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        match direction {
+            TriggerDirection::Rise => {
+                self.rising.insert((trigger_price, seq), order.clone());
+            }
+            TriggerDirection::Fall => {
+                self.falling.insert((Reverse(trigger_price), seq), order.clone());
+            }
+        }
+        self.locations
+            .insert(order.order_id.clone(), (direction, trigger_price, seq));
+        true
+    }
+
+    pub fn remove(&mut self, order_id: &str) -> Option<Order> {
+        let (direction, trigger_price, seq) = self.locations.remove(order_id)?;
+        match direction {
+            TriggerDirection::Rise => self.rising.remove(&(trigger_price, seq)),
+            TriggerDirection::Fall => self.falling.remove(&(Reverse(trigger_price), seq)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Removes and returns every order crossed by `last_price`.
+    ///
+    /// Rising triggers come first in ascending trigger price, then falling
+    /// triggers in descending trigger price, i.e. in the order a price path
+    /// moving away from them would have crossed them. Ties at one trigger
+    /// price fire in arrival order.
+    pub fn take_crossed(&mut self, last_price: i64) -> Vec<Order> {
+        let rising_keys: Vec<_> = self
+            .rising
+            .range(..=(last_price, u64::MAX))
+            .map(|(key, _)| *key)
+            .collect();
+        let falling_keys: Vec<_> = self
+            .falling
+            .range(..=(Reverse(last_price), u64::MAX))
+            .map(|(key, _)| *key)
+            .collect();
+
+        let mut fired = Vec::with_capacity(rising_keys.len() + falling_keys.len());
+        for key in rising_keys {
+            if let Some(order) = self.rising.remove(&key) {
+                self.locations.remove(&order.order_id);
+                fired.push(order);
+            }
+        }
+        for key in falling_keys {
+            if let Some(order) = self.falling.remove(&key) {
+                self.locations.remove(&order.order_id);
+                fired.push(order);
+            }
+        }
+        fired
+    }
+}
@@ -0,0 +1,325 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use redis::aio::ConnectionManager;
+
+use crate::error::{AppError, Result};
+use crate::metrics::METRICS;
+use crate::AppState;
+
+/// Refills a bucket by elapsed time, then takes `cost` tokens if it can.
+/// Returns `{allowed, wait_ms}`. Uses the Redis clock so every replica agrees.
+static TOKEN_BUCKET: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+        local capacity = tonumber(ARGV[1])
+        local rate = tonumber(ARGV[2])
+        local cost = tonumber(ARGV[3])
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+        local tokens = tonumber(bucket[1]) or capacity
+        local ts = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate / 1000)
+        local allowed = 0
+        local wait_ms = 0
+        if tokens >= cost then
+            tokens = tokens - cost
+            allowed = 1
+        else
+            wait_ms = math.ceil((cost - tokens) * 1000 / rate)
+        end
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+        redis.call('PEXPIRE', KEYS[1], math.ceil(capacity * 1000 / rate) + 1000)
+        return {allowed, wait_ms}
+        ",
+    )
+});
+
+/// Call classes with separate budgets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    Place,
+    Cancel,
+    Query,
+}
+
+impl Budget {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Budget::Place => "place",
+            Budget::Cancel => "cancel",
+            Budget::Query => "query",
+        }
+    }
+}
+
+/// A token bucket: up to `burst` calls at once, refilled at `per_second`.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+/// Wallets sending many orders but rarely trading are restricted from
+/// placing orders for `restriction`.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderToTradePolicy {
+    pub window: Duration,
+    /// Orders in the window before the ratio is enforced at all.
+    pub min_orders: u64,
+    pub max_ratio: u64,
+    pub restriction: Duration,
+}
+
+impl OrderToTradePolicy {
+    pub fn exceeded(&self, orders: u64, trades: u64) -> bool {
+        orders >= self.min_orders && orders > self.max_ratio.saturating_mul(trades.max(1))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub place: BucketLimit,
+    pub cancel: BucketLimit,
+    pub query: BucketLimit,
+    pub order_to_trade: OrderToTradePolicy,
+    /// Take the client IP from `x-forwarded-for`; only safe behind a proxy
+    /// that overwrites it.
+    pub trust_forwarded_for: bool,
+}
+
+impl RateLimitConfig {
+    fn limit(&self, budget: Budget) -> BucketLimit {
+        match budget {
+            Budget::Place => self.place,
+            Budget::Cancel => self.cancel,
+            Budget::Query => self.query,
+        }
+    }
+}
+
+/// Redis-backed limits shared by every engine replica. Redis outages fail
+/// open: trading carries on unthrottled rather than stopping.
+pub struct RateLimiter {
+    redis: ConnectionManager,
+    config: RateLimitConfig,
+}
+
+impl RateLimiter {
+    pub fn new(redis: ConnectionManager, config: RateLimitConfig) -> Self {
+        Self { redis, config }
+    }
+
+    pub async fn check_ip(&self, budget: Budget, ip: IpAddr) -> Result<()> {
+        self.take(budget, "ip", &ip.to_string()).await
+    }
+
+    /// Takes from `wallet`'s budget. Only pass a wallet the request has
+    /// authenticated as, or callers can spend each other's budgets.
+    pub async fn check_wallet(&self, budget: Budget, wallet: &str) -> Result<()> {
+        self.take(budget, "wallet", wallet).await
+    }
+
+    async fn take(&self, budget: Budget, scope: &'static str, subject: &str) -> Result<()> {
+        let limit = self.config.limit(budget);
+        let key = format!("ratelimit:{}:{}:{}", budget.label(), scope, subject);
+        let outcome: redis::RedisResult<(i64, i64)> = TOKEN_BUCKET
+            .key(&key)
+            .arg(limit.burst)
+            .arg(limit.per_second)
+            .arg(1)
+            .invoke_async(&mut self.redis.clone())
+            .await;
+
+        match outcome {
+            Ok((1, _)) => Ok(()),
+            Ok((_, wait_ms)) => {
+                METRICS.rate_limited.with_label_values(&[budget.label(), scope]).inc();
+                Err(AppError::RateLimited {
+                    retry_after_secs: retry_after_secs(wait_ms),
+                })
+            }
+            Err(e) => {
+                tracing::warn!("Rate limit check for {} failed, allowing: {}", key, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Rejects wallets under an order-to-trade restriction.
+    pub async fn check_restriction(&self, wallet: &str) -> Result<()> {
+        let ttl: redis::RedisResult<i64> = redis::cmd("TTL")
+            .arg(restriction_key(wallet))
+            .query_async(&mut self.redis.clone())
+            .await;
+        match ttl {
+            Ok(ttl) if ttl > 0 => Err(AppError::TradingRestricted {
+                retry_after_secs: ttl as u64,
+            }),
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::warn!("Restriction check for {} failed, allowing: {}", wallet, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Counts an accepted order and restricts the wallet once its ratio of
+    /// orders to trades in the current window is too high.
+    pub async fn record_order(&self, wallet: &str) {
+        let policy = self.config.order_to_trade;
+        let key = self.window_key(wallet);
+        let counts: redis::RedisResult<(u64, Option<u64>)> = redis::pipe()
+            .atomic()
+            .hincr(&key, "orders", 1)
+            .hget(&key, "trades")
+            .expire(&key, policy.window.as_secs() as i64 * 2)
+            .ignore()
+            .query_async(&mut self.redis.clone())
+            .await;
+
+        let (orders, trades) = match counts {
+            Ok((orders, trades)) => (orders, trades.unwrap_or(0)),
+            Err(e) => {
+                tracing::warn!("Order-to-trade update for {} failed: {}", wallet, e);
+                return;
+            }
+        };
+        if !policy.exceeded(orders, trades) {
+            return;
+        }
+
+        tracing::warn!(
+            "Restricting {} for {:?}: {} orders to {} trades",
+            wallet, policy.restriction, orders, trades
+        );
+        METRICS.rate_limited.with_label_values(&["place", "order_to_trade"]).inc();
+        let restricted: redis::RedisResult<()> = redis::cmd("SET")
+            .arg(restriction_key(wallet))
+            .arg(orders)
+            .arg("EX")
+            .arg(policy.restriction.as_secs())
+            .query_async(&mut self.redis.clone())
+            .await;
+        if let Err(e) = restricted {
+            tracing::warn!("Failed to restrict {}: {}", wallet, e);
+        }
+    }
+
+    /// Counts a trade for each of the given wallets.
+    pub async fn record_trades(&self, wallets: &[&str]) {
+        if wallets.is_empty() {
+            return;
+        }
+        let window = self.config.order_to_trade.window.as_secs() as i64 * 2;
+        let mut pipe = redis::pipe();
+        for wallet in wallets {
+            let key = self.window_key(wallet);
+            pipe.hincr(&key, "trades", 1).ignore().expire(&key, window).ignore();
+        }
+        let recorded: redis::RedisResult<()> = pipe.query_async(&mut self.redis.clone()).await;
+        if let Err(e) = recorded {
+            tracing::warn!("Order-to-trade trade count failed: {}", e);
+        }
+    }
+
+    /// Fixed window the wallet's order and trade counts fall into.
+    fn window_key(&self, wallet: &str) -> String {
+        let window = self.config.order_to_trade.window.as_secs().max(1);
+        let index = chrono::Utc::now().timestamp() as u64 / window;
+        format!("ratelimit:otr:{}:{}", wallet, index)
+    }
+
+    /// Client address: the first `x-forwarded-for` hop when trusted, else
+    /// the peer address.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        let forwarded = self.config.trust_forwarded_for.then(|| forwarded_for(headers)).flatten();
+        forwarded.or(peer.map(|peer| peer.ip()))
+    }
+}
+
+fn restriction_key(wallet: &str) -> String {
+    format!("ratelimit:restricted:{}", wallet)
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get("x-forwarded-for")?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn retry_after_secs(wait_ms: i64) -> u64 {
+    (wait_ms.max(1) as u64).div_ceil(1000)
+}
+
+/// Route layer charging the caller's IP against `budget`.
+pub async fn limit_ip(
+    State((state, budget)): State<(Arc<AppState>, Budget)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer);
+    if let Some(ip) = state.rate_limiter.client_ip(request.headers(), peer) {
+        if let Err(e) = state.rate_limiter.check_ip(budget, ip).await {
+            return e.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> OrderToTradePolicy {
+        OrderToTradePolicy {
+            window: Duration::from_secs(300),
+            min_orders: 100,
+            max_ratio: 20,
+            restriction: Duration::from_secs(600),
+        }
+    }
+
+    #[test]
+    fn test_order_to_trade_ratio() {
+        let policy = policy();
+        assert!(!policy.exceeded(99, 0));
+        assert!(policy.exceeded(100, 0));
+        assert!(!policy.exceeded(100, 5));
+        assert!(policy.exceeded(101, 5));
+    }
+
+    #[test]
+    fn test_retry_after_rounds_up() {
+        assert_eq!(retry_after_secs(0), 1);
+        assert_eq!(retry_after_secs(1), 1);
+        assert_eq!(retry_after_secs(1000), 1);
+        assert_eq!(retry_after_secs(1001), 2);
+    }
+
+    #[test]
+    fn test_forwarded_for_takes_first_hop() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), Some("203.0.113.7".parse().unwrap()));
+
+        headers.insert("x-forwarded-for", "garbage".parse().unwrap());
+        assert_eq!(forwarded_for(&headers), None);
+    }
+}
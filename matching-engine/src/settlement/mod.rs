@@ -1,101 +1,483 @@
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use solana_sdk::signature::Keypair;
 use sqlx::PgPool;
 
 use crate::orderbook::TradeMatch;
+use crate::types::{
+    BalanceChange, BalanceChangeReason, CancelReason, Channel, FeeRates, Market, OnChainOrder, OrderSide,
+    SettlementStatus, WsMessage,
+};
+use crate::metrics::METRICS;
+use crate::websocket::{Topic, WebSocketManager};
 
 pub mod solana;
 use self::solana::SolanaSettlementClient;
 
+/// Failed unlocks are retried with exponential backoff from this delay.
+const UNLOCK_RETRY_BASE: Duration = Duration::from_secs(2);
+const UNLOCK_RETRY_MAX: Duration = Duration::from_secs(60);
+/// About a quarter of an hour of retries before the unlock is given up.
+const MAX_UNLOCK_ATTEMPTS: u32 = 20;
+
 pub struct SettlementQueue {
     db_pool: PgPool,
     solana_client: Arc<SolanaSettlementClient>,
-    tx: mpsc::Sender<SettlementTask>,
-    rx: tokio::sync::Mutex<mpsc::Receiver<SettlementTask>>,
+    ws_manager: Arc<WebSocketManager>,
+    tx: mpsc::Sender<SettlementJob>,
+    rx: tokio::sync::Mutex<mpsc::Receiver<SettlementJob>>,
+}
+
+/// Work for the on-chain settlement worker. Jobs start in the order they were
+/// queued, so an unlock never overtakes a fill queued before it. A failed
+/// unlock is requeued behind later jobs; no fill of its order is queued after
+/// the unlock, and the retry does nothing once the order is closed on chain.
+#[derive(Debug)]
+pub enum SettlementJob {
+    Trade(SettlementTask),
+    Unlock(UnlockTask),
 }
 
+/// Settles a trade the matching step has already recorded.
 #[derive(Debug)]
 pub struct SettlementTask {
+    pub trade_id: i64,
+    pub sequence: i64,
     pub trade_match: TradeMatch,
     pub market_id: uuid::Uuid,
-    pub maker_fee_bps: i16,
-    pub taker_fee_bps: i16,
+    pub taker_side: OrderSide,
+    pub quote_amount: i64,
+    /// Negative when the maker earns a rebate.
+    pub maker_fee: i64,
+    pub taker_fee: i64,
+    /// Rates the fees were computed with; settlement proves them on chain.
+    pub maker_rates: FeeRates,
+    pub taker_rates: FeeRates,
+    /// The taker's referrer, paid `referrer_fee` out of the taker fee.
+    pub referrer: Option<String>,
+    pub referrer_fee: i64,
+}
+
+impl SettlementTask {
+    /// Vault balance changes of the maker and the taker once the trade settles,
+    /// mirroring `settle_trade`: the buyer pays quote plus fee, the seller
+    /// receives quote minus fee. A maker rebate is a negative fee.
+    pub fn balance_changes(&self) -> [(String, BalanceChange); 2] {
+        let trade = &self.trade_match;
+        let change = |side: OrderSide, fee: i64| {
+            let (base_delta, quote_delta) = match side {
+                OrderSide::Buy => (trade.size, -(self.quote_amount + fee)),
+                OrderSide::Sell => (-trade.size, self.quote_amount - fee),
+            };
+            BalanceChange {
+                market_id: self.market_id,
+                reason: BalanceChangeReason::Trade,
+                base_delta,
+                quote_delta,
+                reference: self.trade_id.to_string(),
+            }
+        };
+
+        [
+            (trade.maker_wallet.clone(), change(self.taker_side.opposite(), self.maker_fee)),
+            (trade.taker_wallet.clone(), change(self.taker_side, self.taker_fee)),
+        ]
+    }
+}
+
+/// Quote value and fees of one fill, in quote atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillValue {
+    pub quote_amount: i64,
+    /// Negative when the maker earns a rebate.
+    pub maker_fee: i64,
+    pub taker_fee: i64,
+    /// Paid to the taker's referrer, if it was referred.
+    pub referrer_fee: i64,
+}
+
+/// Quote atoms for `size` base atoms at `price`, as the program computes it.
+/// `None` if an input is negative or the result does not fit.
+pub fn quote_amount(size: i64, price: i64, base_decimals: i16) -> Option<i64> {
+    let amount = dcex_pricing::quote_amount(
+        u64::try_from(size).ok()?,
+        u64::try_from(price).ok()?,
+        u8::try_from(base_decimals).ok()?,
+    )?;
+    i64::try_from(amount).ok()
+}
+
+/// What `settle_trade` transfers for one fill, so recorded fees always match
+/// the chain. The maker pays its own maker rate and the taker its taker rate;
+/// a referred taker's referrer gets the market's referral share of it.
+pub fn fill_value(
+    trade_match: &TradeMatch,
+    market: &Market,
+    maker_rates: &FeeRates,
+    taker_rates: &FeeRates,
+    taker_referred: bool,
+) -> Option<FillValue> {
+    let amounts = dcex_pricing::fill_amounts(
+        u64::try_from(trade_match.size).ok()?,
+        u64::try_from(trade_match.price).ok()?,
+        u8::try_from(market.base_decimals).ok()?,
+        maker_rates.maker_fee_bps,
+        u16::try_from(taker_rates.taker_fee_bps).ok()?,
+    )?;
+
+    let referrer_fee = if taker_referred {
+        amounts.referrer_fee(u16::try_from(market.referral_share_bps).ok()?)?
+    } else {
+        0
+    };
+
+    Some(FillValue {
+        quote_amount: i64::try_from(amounts.quote).ok()?,
+        maker_fee: amounts.maker_fee,
+        taker_fee: i64::try_from(amounts.taker_fee).ok()?,
+        referrer_fee: i64::try_from(referrer_fee).ok()?,
+    })
+}
+
+/// Releases the funds locked by an order the engine cancelled or expired
+/// without the owner signing an on-chain cancel.
+#[derive(Debug)]
+pub struct UnlockTask {
+    pub order_id: String,
+    pub user_wallet: String,
+    pub market_id: uuid::Uuid,
+    pub reason: CancelReason,
+    /// Failed attempts so far.
+    pub attempt: u32,
+}
+
+/// Delay before retrying an unlock that failed `attempt` times, or `None`
+/// once it should be given up. `expire_order` only succeeds once chain time
+/// reaches the order's expiry, which can trail the engine clock, so an
+/// early expiry unlock simply waits for the next attempt.
+fn unlock_retry_delay(attempt: u32) -> Option<Duration> {
+    if attempt >= MAX_UNLOCK_ATTEMPTS {
+        return None;
+    }
+    let delay = UNLOCK_RETRY_BASE.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+    Some(delay.min(UNLOCK_RETRY_MAX))
+}
+
+/// Whether the on-chain order still holds funds an unlock would release.
+fn needs_unlock(order: Option<&OnChainOrder>) -> bool {
+    order.is_some_and(|order| order.is_open)
 }
 
 impl SettlementQueue {
-    pub fn new(db_pool: PgPool, rpc_url: String, program_id: String) -> Self {
+    pub fn new(
+        db_pool: PgPool,
+        rpc_url: String,
+        program_id: String,
+        authority: Option<Keypair>,
+        ws_manager: Arc<WebSocketManager>,
+    ) -> Self {
         let (tx, rx) = mpsc::channel(10000);
-        let solana_client = Arc::new(SolanaSettlementClient::new(&rpc_url, &program_id));
+        let solana_client = Arc::new(SolanaSettlementClient::new(&rpc_url, &program_id, authority));
         
         Self {
             db_pool,
             solana_client,
+            ws_manager,
             tx,
             rx: tokio::sync::Mutex::new(rx),
         }
     }
 
+    pub fn solana_client(&self) -> &SolanaSettlementClient {
+        &self.solana_client
+    }
+
     pub async fn queue_settlement(&self, task: SettlementTask) -> anyhow::Result<()> {
-        self.tx.send(task).await?;
+        self.tx.send(SettlementJob::Trade(task)).await?;
+        Ok(())
+    }
+
+    pub async fn queue_unlock(&self, task: UnlockTask) -> anyhow::Result<()> {
+        self.tx.send(SettlementJob::Unlock(task)).await?;
         Ok(())
     }
 
     pub async fn run(&self) {
         let mut rx = self.rx.lock().await;
         
-        while let Some(task) = rx.recv().await {
-            if let Err(e) = self.process_settlement(task).await {
-                tracing::error!("Settlement failed: {:?}", e);
-            }
+        while let Some(job) = rx.recv().await {
+            let (kind, result) = match job {
+                SettlementJob::Trade(task) => {
+                    let result = self.process_settlement(task).await;
+                    if let Err(e) = &result {
+                        tracing::error!("Settlement failed: {:?}", e);
+                    }
+                    ("trade", result)
+                }
+                SettlementJob::Unlock(task) => {
+                    let result = self.process_unlock(&task).await;
+                    if let Err(e) = &result {
+                        self.retry_unlock(task, e);
+                    }
+                    ("unlock", result)
+                }
+            };
+            let outcome = if result.is_ok() { "success" } else { "failure" };
+            METRICS.settlement_jobs.with_label_values(&[kind, outcome]).inc();
         }
     }
 
-    async fn process_settlement(&self, task: SettlementTask) -> anyhow::Result<()> {
-        // 1. Record trade in database first (as pending settlement)
-        let quote_amount = task.trade_match.size * task.trade_match.price / 1_000_000_000;
-        let maker_fee = quote_amount * task.maker_fee_bps as i64 / 10000;
-        let taker_fee = quote_amount * task.taker_fee_bps as i64 / 10000;
-
-        let trade = crate::db::create_trade(
-            &self.db_pool,
-            task.market_id,
-            &task.trade_match.maker_order_id,
-            &task.trade_match.taker_order_id,
-            &task.trade_match.maker_wallet,
-            &task.trade_match.taker_wallet,
-            task.trade_match.price,
-            task.trade_match.size,
-            maker_fee,
-            taker_fee,
-        ).await?;
-
-        tracing::info!(
-            "Trade recorded locally: id={}, maker={}, taker={}",
-            trade.id,
-            trade.maker_order_id,
-            trade.taker_order_id
+    /// Requeues a failed unlock after a backoff, behind whatever was queued
+    /// meanwhile. The order's funds stay locked on chain until one attempt
+    /// succeeds.
+    fn retry_unlock(&self, mut task: UnlockTask, error: &anyhow::Error) {
+        task.attempt += 1;
+        let Some(delay) = unlock_retry_delay(task.attempt) else {
+            tracing::error!(
+                "Unlock of order {} failed {} times, giving up: {:?}",
+                task.order_id, task.attempt, error
+            );
+            return;
+        };
+        tracing::warn!(
+            "Unlock of order {} failed (attempt {}), retrying in {:?}: {:?}",
+            task.order_id, task.attempt, delay, error
         );
 
-        // 2. Fetch market details for settlement
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(e) = tx.send(SettlementJob::Unlock(task)).await {
+                tracing::error!("Failed to requeue unlock: {}", e);
+            }
+        });
+    }
+
+    /// Jobs queued but not yet picked up by [`Self::run`].
+    pub fn queue_depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    async fn process_settlement(&self, task: SettlementTask) -> anyhow::Result<()> {
         let market = crate::db::get_market(&self.db_pool, task.market_id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("Market not found"))?;
 
-        // 3. Settle on Solana
-        match self.solana_client.settle_trade(&task.trade_match, &market).await {
+        let settled = self
+            .solana_client
+            .settle_trade(&task, &market)
+            .await;
+        match settled {
             Ok(signature) => {
-                tracing::info!("Trade settled on-chain: {}", signature);
-                
-                // 4. Update trade with signature
-                crate::db::update_trade_signature(&self.db_pool, trade.id, &signature).await?;
+                tracing::info!("Trade {} settled on-chain: {}", task.trade_id, signature);
+
+                crate::db::update_trade_signature(&self.db_pool, task.trade_id, &signature).await?;
+                crate::db::record_settled_fees(
+                    &self.db_pool,
+                    task.trade_id,
+                    task.market_id,
+                    task.maker_fee,
+                    task.taker_fee,
+                    task.referrer_fee,
+                ).await?;
+                self.publish_settlement(&task, SettlementStatus::Settled, Some(signature)).await;
+                for (wallet, change) in task.balance_changes() {
+                    self.ws_manager.send_balance_change(&wallet, change).await;
+                }
             }
             Err(e) => {
-                // TODO: Implement retry logic or mark as failed
-                tracing::error!("Failed to settle trade on-chain for trade {}: {:?}", trade.id, e);
+                // TODO: Implement retry logic
+                tracing::error!("Failed to settle trade on-chain for trade {}: {:?}", task.trade_id, e);
+
+                crate::db::mark_trade_settlement_failed(&self.db_pool, task.trade_id).await?;
+                self.publish_settlement(&task, SettlementStatus::Failed, None).await;
                 return Err(e);
             }
         }
 
         Ok(())
     }
+
+    async fn publish_settlement(
+        &self,
+        task: &SettlementTask,
+        status: SettlementStatus,
+        signature: Option<String>,
+    ) {
+        let message = WsMessage::TradeSettlement {
+            market_id: task.market_id,
+            trade_id: task.trade_id,
+            sequence: task.sequence,
+            status,
+            signature,
+        };
+        let topic = Topic::Market(task.market_id, Channel::Trades);
+        self.ws_manager.publish(&topic, message).await;
+    }
+
+    async fn process_unlock(&self, task: &UnlockTask) -> anyhow::Result<()> {
+        let market = crate::db::get_market(&self.db_pool, task.market_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Market not found"))?;
+
+        // An earlier attempt may have landed before it reported back, or the
+        // order filled or was cancelled by its owner meanwhile.
+        let on_chain = self.solana_client.fetch_order(task.order_id.parse()?).await?;
+        if !needs_unlock(on_chain.as_ref()) {
+            tracing::info!("Order {} holds no locked funds on chain, skipping unlock", task.order_id);
+            return Ok(());
+        }
+
+        let signature = self.solana_client.cancel_order(task, &market).await?;
+        tracing::info!("Order {} unlocked on-chain: {}", task.order_id, signature);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FeeSource, MatchingAlgorithm};
+
+    fn market(base_decimals: u8, maker_fee_bps: i16, taker_fee_bps: u16) -> Market {
+        Market {
+            id: uuid::Uuid::new_v4(),
+            base_mint: String::new(),
+            quote_mint: String::new(),
+            base_decimals: base_decimals.into(),
+            quote_decimals: 6,
+            min_order_size: 1,
+            tick_size: 1,
+            maker_fee_bps,
+            taker_fee_bps: taker_fee_bps as i16,
+            is_active: true,
+            matching_algorithm: MatchingAlgorithm::Fifo,
+            fifo_slice_bps: 0,
+            market_address: None,
+            base_vault: None,
+            quote_vault: None,
+            fee_recipient: None,
+            referral_share_bps: 0,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn fill(size: i64, price: i64) -> TradeMatch {
+        TradeMatch {
+            maker_order_id: "1".to_string(),
+            maker_wallet: "maker".to_string(),
+            taker_order_id: "2".to_string(),
+            taker_wallet: "taker".to_string(),
+            price,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_recorded_fees_match_program_transfers() {
+        for case in dcex_pricing::golden::FILLS {
+            let market = market(case.base_decimals, case.maker_fee_bps, case.taker_fee_bps);
+            let rates = FeeRates::market_default(&market);
+            let value = i64::try_from(case.size)
+                .ok()
+                .zip(i64::try_from(case.price).ok())
+                .and_then(|(size, price)| fill_value(&fill(size, price), &market, &rates, &rates, false));
+
+            let expected = case.expected.map(|amounts| FillValue {
+                quote_amount: amounts.quote as i64,
+                maker_fee: amounts.maker_fee,
+                taker_fee: amounts.taker_fee as i64,
+                referrer_fee: 0,
+            });
+            assert_eq!(value, expected, "{}", case.name);
+        }
+    }
+
+    fn task(trade_match: TradeMatch, market: &Market, maker_rates: FeeRates, taker_rates: FeeRates) -> SettlementTask {
+        let value = fill_value(&trade_match, market, &maker_rates, &taker_rates, false).unwrap();
+        SettlementTask {
+            trade_id: 1,
+            sequence: 1,
+            trade_match,
+            market_id: market.id,
+            taker_side: OrderSide::Buy,
+            quote_amount: value.quote_amount,
+            maker_fee: value.maker_fee,
+            taker_fee: value.taker_fee,
+            maker_rates,
+            taker_rates,
+            referrer: None,
+            referrer_fee: value.referrer_fee,
+        }
+    }
+
+    #[test]
+    fn test_balance_changes_charge_fees_on_top_of_quote() {
+        let market = market(9, 5, 10);
+        let rates = FeeRates::market_default(&market);
+        let task = task(fill(2_500_000_000, 150_000_000), &market, rates, rates);
+
+        let [(maker, maker_change), (taker, taker_change)] = task.balance_changes();
+        assert_eq!(maker, "maker");
+        assert_eq!((maker_change.base_delta, maker_change.quote_delta), (-2_500_000_000, 375_000_000 - 187_500));
+        assert_eq!(taker, "taker");
+        assert_eq!((taker_change.base_delta, taker_change.quote_delta), (2_500_000_000, -(375_000_000 + 375_000)));
+    }
+
+    #[test]
+    fn test_maker_rebate_uses_maker_wallet_rates() {
+        let market = market(9, 5, 10);
+        let maker_rates = FeeRates {
+            maker_fee_bps: -2,
+            taker_fee_bps: 10,
+            source: FeeSource::Override,
+        };
+        let taker_rates = FeeRates {
+            maker_fee_bps: 4,
+            taker_fee_bps: 8,
+            source: FeeSource::Tier,
+        };
+        let task = task(fill(2_500_000_000, 150_000_000), &market, maker_rates, taker_rates);
+        assert_eq!((task.maker_fee, task.taker_fee), (-75_000, 300_000));
+
+        let [(_, maker_change), (_, taker_change)] = task.balance_changes();
+        assert_eq!(maker_change.quote_delta, 375_000_000 + 75_000);
+        assert_eq!(taker_change.quote_delta, -(375_000_000 + 300_000));
+    }
+
+    #[test]
+    fn test_referrer_fee_only_for_referred_takers() {
+        let mut market = market(9, 5, 10);
+        market.referral_share_bps = 2_000;
+        let rates = FeeRates::market_default(&market);
+        let trade_match = fill(2_500_000_000, 150_000_000);
+
+        let referred = fill_value(&trade_match, &market, &rates, &rates, true).unwrap();
+        assert_eq!(referred.referrer_fee, 75_000);
+        assert_eq!(referred.taker_fee, 375_000);
+
+        let direct = fill_value(&trade_match, &market, &rates, &rates, false).unwrap();
+        assert_eq!(direct.referrer_fee, 0);
+    }
+
+    #[test]
+    fn test_unlock_retry_backs_off_then_gives_up() {
+        assert_eq!(unlock_retry_delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(unlock_retry_delay(2), Some(Duration::from_secs(4)));
+        assert_eq!(unlock_retry_delay(10), Some(UNLOCK_RETRY_MAX));
+        assert_eq!(unlock_retry_delay(MAX_UNLOCK_ATTEMPTS), None);
+    }
+
+    #[test]
+    fn test_unlock_retry_skips_orders_closed_on_chain() {
+        let open = OnChainOrder { is_open: true, expires_at: 0 };
+        // Filled, cancelled by its owner, or unlocked by an attempt that timed out.
+        let closed = OnChainOrder { is_open: false, ..open };
+
+        assert!(needs_unlock(Some(&open)));
+        assert!(!needs_unlock(Some(&closed)));
+        assert!(!needs_unlock(None));
+    }
 }
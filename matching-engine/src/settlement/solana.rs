@@ -1,6 +1,8 @@
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use anchor_client::anchor_lang::prelude::Pubkey;
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     instruction::{AccountMeta, Instruction},
@@ -8,48 +10,139 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use anyhow::Result;
+use tokio::sync::RwLock;
 
-use crate::orderbook::TradeMatch;
-use crate::types::Market;
+use crate::metrics::METRICS;
+use crate::settlement::{SettlementTask, UnlockTask};
+use crate::types::{CancelReason, FeeRates, FeeSource, Market, OnChainDelegate, OnChainMarket, OnChainOrder};
 
 // Import constants or define them here if not available
 const MARKET_SEED: &[u8] = b"market";
 const VAULT_SEED: &[u8] = b"vault";
 const ORDER_SEED: &[u8] = b"order";
+const ESCROW_SEED: &[u8] = b"escrow";
+const FEE_TIER_SEED: &[u8] = b"fee_tier";
+const REFERRAL_SEED: &[u8] = b"referral";
+const DELEGATE_SEED: &[u8] = b"delegate";
+
+// Sha256("global:<instruction>")[..8]
+const INITIALIZE_MARKET_DISCRIMINATOR: [u8; 8] = [35, 35, 189, 193, 155, 48, 170, 203];
+const SETTLE_TRADE_DISCRIMINATOR: [u8; 8] = [252, 176, 98, 248, 73, 123, 8, 157];
+const AUTHORITY_CANCEL_ORDER_DISCRIMINATOR: [u8; 8] = [41, 175, 11, 43, 136, 27, 69, 18];
+const EXPIRE_ORDER_DISCRIMINATOR: [u8; 8] = [174, 27, 85, 247, 105, 245, 220, 13];
+const SET_FEE_TIER_DISCRIMINATOR: [u8; 8] = [128, 172, 128, 22, 246, 79, 7, 219];
+const SET_REFERRAL_DISCRIMINATOR: [u8; 8] = [213, 23, 157, 74, 199, 152, 182, 8];
+const SET_REFERRAL_SHARE_DISCRIMINATOR: [u8; 8] = [230, 159, 74, 188, 192, 81, 25, 107];
+
+// Sha256("account:Market")[..8]
+const MARKET_DISCRIMINATOR: [u8; 8] = [219, 190, 213, 55, 0, 227, 198, 154];
+// Sha256("account:FeeTier")[..8]
+const FEE_TIER_DISCRIMINATOR: [u8; 8] = [56, 75, 159, 76, 142, 68, 190, 105];
+// Sha256("account:Referral")[..8]
+const REFERRAL_DISCRIMINATOR: [u8; 8] = [30, 235, 136, 224, 106, 107, 49, 64];
+// Sha256("account:Order")[..8]
+const ORDER_DISCRIMINATOR: [u8; 8] = [134, 173, 223, 185, 77, 86, 28, 51];
+// Sha256("account:Delegate")[..8]
+const DELEGATE_DISCRIMINATOR: [u8; 8] = [92, 145, 166, 111, 11, 38, 38, 247];
+
+// Borsh variant indexes of the program's `OrderStatus`
+const ORDER_STATUS_PENDING: u8 = 0;
+const ORDER_STATUS_PARTIALLY_FILLED: u8 = 1;
+
+/// Parameters of the program's `initialize_market` instruction.
+pub struct InitializeMarketParams {
+    pub min_order_size: u64,
+    pub tick_size: u64,
+    pub maker_fee_bps: u16,
+    pub taker_fee_bps: u16,
+}
+
+pub fn market_address(program_id: &Pubkey, base_mint: &Pubkey, quote_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[MARKET_SEED, base_mint.as_ref(), quote_mint.as_ref()],
+        program_id,
+    )
+    .0
+}
+
+pub fn fee_tier_address(program_id: &Pubkey, market: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[FEE_TIER_SEED, market.as_ref(), wallet.as_ref()], program_id).0
+}
+
+pub fn referral_address(program_id: &Pubkey, market: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REFERRAL_SEED, market.as_ref(), wallet.as_ref()], program_id).0
+}
+
+/// The delegate account of `wallet`'s vault on `market`.
+pub fn delegate_address(program_id: &Pubkey, market: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    let vault = Pubkey::find_program_address(&[VAULT_SEED, wallet.as_ref(), market.as_ref()], program_id).0;
+    Pubkey::find_program_address(&[DELEGATE_SEED, vault.as_ref()], program_id).0
+}
+
+/// How long a fetched delegate account is reused. The program enforces the
+/// delegate's limits again at settlement, so a few seconds of staleness only
+/// delays a revocation taking effect in the engine.
+const DELEGATE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Recently fetched delegate accounts, so every signed request from a
+/// session key does not cost an RPC round trip.
+#[derive(Default)]
+struct DelegateCache {
+    entries: HashMap<Pubkey, (Instant, Option<OnChainDelegate>)>,
+}
+
+impl DelegateCache {
+    fn get(&self, address: &Pubkey, now: Instant) -> Option<Option<OnChainDelegate>> {
+        self.entries
+            .get(address)
+            .filter(|(fetched_at, _)| now.duration_since(*fetched_at) < DELEGATE_CACHE_TTL)
+            .map(|(_, delegate)| delegate.clone())
+    }
+
+    fn insert(&mut self, address: Pubkey, delegate: Option<OnChainDelegate>, now: Instant) {
+        self.entries
+            .retain(|_, (fetched_at, _)| now.duration_since(*fetched_at) < DELEGATE_CACHE_TTL);
+        self.entries.insert(address, (now, delegate));
+    }
+}
 
 pub struct SolanaSettlementClient {
     client: RpcClient,
     program_id: Pubkey,
-    payer: Keypair,
+    /// The markets' authority, which signs and pays for every transaction the
+    /// engine sends. Without it the client can only read from chain.
+    authority: Option<Keypair>,
+    delegates: RwLock<DelegateCache>,
 }
 
 impl SolanaSettlementClient {
-    pub fn new(rpc_url: &str, program_id_str: &str) -> Self {
+    pub fn new(rpc_url: &str, program_id_str: &str, authority: Option<Keypair>) -> Self {
         let client = RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
         let program_id = Pubkey::from_str(program_id_str).expect("Invalid program ID");
-        
-        // For development, we'll generate a payer or load from file. 
-        // In a real system, this should be loaded from a secure location.
-        // Here we assume the matching engine authority is the payer.
-        // TODO: Load real keypair
-        let payer = Keypair::new(); 
-        
+
         Self {
             client,
             program_id,
-            payer,
+            authority,
+            delegates: RwLock::new(DelegateCache::default()),
         }
     }
 
-    pub fn set_payer(&mut self, keypair: Keypair) {
-        self.payer = keypair;
+    /// The engine's authority key, if one was configured.
+    pub fn authority_pubkey(&self) -> Option<Pubkey> {
+        self.authority.as_ref().map(Keypair::pubkey)
     }
 
-    pub async fn settle_trade(
-        &self,
-        trade: &TradeMatch,
-        market: &Market,
-    ) -> Result<String> {
+    fn authority(&self) -> Result<&Keypair> {
+        self.authority
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No authority keypair configured; set AUTHORITY_KEYPAIR_PATH"))
+    }
+
+    pub async fn settle_trade(&self, task: &SettlementTask, market: &Market) -> Result<String> {
+        let authority = self.authority()?;
+        let _timer = METRICS.rpc_latency.with_label_values(&["settle_trade"]).start_timer();
+        let trade = &task.trade_match;
         let maker_wallet = Pubkey::from_str(&trade.maker_wallet)?;
         let taker_wallet = Pubkey::from_str(&trade.taker_wallet)?;
         let base_mint = Pubkey::from_str(&market.base_mint)?;
@@ -84,39 +177,52 @@ impl SolanaSettlementClient {
             &self.program_id,
         );
 
-         // We need the market's base and quote vaults to pass to the instruction
-        // Assuming associated token accounts for the market PDA? 
-        // Or specific vault accounts created during initialization?
-        // Based on the contract: 
-        // pub base_vault: Account<'info, TokenAccount>,
-        // pub quote_vault: Account<'info, TokenAccount>,
-        // These are usually stored in the Market account state.
-        // Since we don't have the Market account data deserialized here easily without 
-        // fetching and parsing, we might need to derive them if they are deterministic 
-        // or fetch the market account first.
-        // For now, let's assume they are ATA of the market PDA for simplicity, 
-        // BUT the contract usually initializes specific token accounts.
-        // Let's assume we can derive them or they are ATAs.
-        // If the contract uses `token::spl_token` and `anchor_spl`, standard ATAs are common.
-        // Let's check `initialize_market` in the contract later if this fails.
-        // Actually, matching-engine Market struct doesn't have vault addresses.
-        // We might need to fetch the on-chain market account to get these.
-        // For now, let's implement a helper to fetch on-chain market data if needed,
-        // or just use get_associated_token_address. 
-        
-        let base_vault = spl_associated_token_account::get_associated_token_address(&market_pda, &base_mint);
-        let quote_vault = spl_associated_token_account::get_associated_token_address(&market_pda, &quote_mint);
+        // Vaults are recorded when the market is created or imported from
+        // chain; older rows fall back to the market PDA's associated accounts.
+        let base_vault = match &market.base_vault {
+            Some(address) => Pubkey::from_str(address)?,
+            None => spl_associated_token_account::get_associated_token_address(&market_pda, &base_mint),
+        };
+        let quote_vault = match &market.quote_vault {
+            Some(address) => Pubkey::from_str(address)?,
+            None => spl_associated_token_account::get_associated_token_address(&market_pda, &quote_mint),
+        };
         
-        // Fee recipient is likely the market authority or a specific fee wallet
-        // Let's assume it's the market authority's ATA for quote token for now,
-        // or a configured fee wallet.
-        // The contract requires `fee_recipient: Account<'info, TokenAccount>`.
-        // And `constraint = market.authority == authority.key()`.
-        // So `authority` must be the signer (our payer).
-        let fee_recipient = spl_associated_token_account::get_associated_token_address(&self.payer.pubkey(), &quote_mint);
+        // Fees go to the market's recorded fee recipient, which the fee ledger
+        // is reconciled against; older rows fall back to the engine's own
+        // quote token account.
+        let fee_recipient = match &market.fee_recipient {
+            Some(address) => Pubkey::from_str(address)?,
+            None => spl_associated_token_account::get_associated_token_address(&authority.pubkey(), &quote_mint),
+        };
+
+        let maker_fee_tier = self.fee_tier_account(&market_pda, &maker_wallet, &task.maker_rates).await?;
+        let taker_fee_tier = self.fee_tier_account(&market_pda, &taker_wallet, &task.taker_rates).await?;
+
+        // The referrer is paid into its quote token account, created here if
+        // it does not exist yet. Without a cut both slots read as `None`.
+        let mut instructions = Vec::with_capacity(2);
+        let (taker_referral, referrer_token_account) = match &task.referrer {
+            Some(referrer) if task.referrer_fee > 0 => {
+                let referrer = Pubkey::from_str(referrer)?;
+                let referral = self.referral_account(&market_pda, &taker_wallet, &referrer).await?;
+                instructions.push(
+                    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                        &authority.pubkey(),
+                        &referrer,
+                        &quote_mint,
+                        &spl_token::id(),
+                    ),
+                );
+                let token_account =
+                    spl_associated_token_account::get_associated_token_address(&referrer, &quote_mint);
+                (referral, token_account)
+            }
+            _ => (self.program_id, self.program_id),
+        };
 
         let accounts = vec![
-            AccountMeta::new_readonly(self.payer.pubkey(), true), // authority
+            AccountMeta::new_readonly(authority.pubkey(), true), // authority
             AccountMeta::new_readonly(market_pda, false),         // market
             AccountMeta::new(maker_vault, false),                 // maker_vault
             AccountMeta::new(taker_vault, false),                 // taker_vault
@@ -126,33 +232,654 @@ impl SolanaSettlementClient {
             AccountMeta::new(quote_vault, false),                 // quote_vault
             AccountMeta::new(fee_recipient, false),               // fee_recipient
             AccountMeta::new_readonly(spl_token::id(), false),    // token_program
+            AccountMeta::new_readonly(maker_fee_tier, false),     // maker_fee_tier
+            AccountMeta::new_readonly(taker_fee_tier, false),     // taker_fee_tier
+            AccountMeta::new_readonly(taker_referral, false),     // taker_referral
+            AccountMeta::new(referrer_token_account, false),      // referrer_token_account
         ];
 
         // SettleTrade params: [fill_size: u64, fill_price: u64]
-        // Instruction discriminant for "settle_trade" is needed.
-        // Anchor uses Sha256("global:settle_trade")[..8]
-        let discriminator = [163, 137, 23, 10, 163, 153, 93, 222]; // Calculated or known
-        
         let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(&discriminator);
+        data.extend_from_slice(&SETTLE_TRADE_DISCRIMINATOR);
         data.extend_from_slice(&(trade.size as u64).to_le_bytes()); // fill_size
         data.extend_from_slice(&(trade.price as u64).to_le_bytes()); // fill_price
 
+        instructions.push(Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        });
+
+        let recent_blockhash = self.client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&authority.pubkey()),
+            &[authority],
+            recent_blockhash,
+        );
+
+        let signature = self.client.send_and_confirm_transaction(&transaction).await?;
+        Ok(signature.to_string())
+    }
+
+    /// The fee tier account proving `rates` for `wallet`, written first if it
+    /// is missing or stale. Market default rates need no proof, and Anchor
+    /// reads the program id in an optional account's slot as `None`.
+    async fn fee_tier_account(&self, market_pda: &Pubkey, wallet: &Pubkey, rates: &FeeRates) -> Result<Pubkey> {
+        if rates.source == FeeSource::Market {
+            return Ok(self.program_id);
+        }
+
+        let address = fee_tier_address(&self.program_id, market_pda, wallet);
+        let taker_fee_bps = u16::try_from(rates.taker_fee_bps)?;
+        let current = self
+            .client
+            .get_account_with_commitment(&address, self.client.commitment())
+            .await?
+            .value
+            .map(|account| decode_fee_tier_account(&address, &account.data))
+            .transpose()?;
+        if current != Some((rates.maker_fee_bps, taker_fee_bps)) {
+            let signature = self
+                .set_fee_tier(market_pda, wallet, rates.maker_fee_bps, taker_fee_bps)
+                .await?;
+            tracing::info!("Fee tier of {} set in {}", wallet, signature);
+        }
+        Ok(address)
+    }
+
+    /// The referral account recording `referrer` for `wallet`, written first
+    /// if it does not exist yet.
+    async fn referral_account(&self, market_pda: &Pubkey, wallet: &Pubkey, referrer: &Pubkey) -> Result<Pubkey> {
+        let address = referral_address(&self.program_id, market_pda, wallet);
+        let account = self
+            .client
+            .get_account_with_commitment(&address, self.client.commitment())
+            .await?
+            .value;
+        if let Some(account) = account {
+            anyhow::ensure!(
+                account.data.starts_with(&REFERRAL_DISCRIMINATOR),
+                "Account {} is not a referral",
+                address
+            );
+        } else {
+            let signature = self.set_referral(market_pda, wallet, referrer).await?;
+            tracing::info!("Referral of {} set in {}", wallet, signature);
+        }
+        Ok(address)
+    }
+
+    async fn set_referral(&self, market_pda: &Pubkey, wallet: &Pubkey, referrer: &Pubkey) -> Result<String> {
+        let authority = self.authority()?;
+        let referral = referral_address(&self.program_id, market_pda, wallet);
+
+        let accounts = vec![
+            AccountMeta::new(authority.pubkey(), true),                        // authority
+            AccountMeta::new_readonly(*market_pda, false),                      // market
+            AccountMeta::new(referral, false),                                  // referral
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false), // system_program
+        ];
+
+        let mut data = Vec::with_capacity(8 + 32 + 32);
+        data.extend_from_slice(&SET_REFERRAL_DISCRIMINATOR);
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(referrer.as_ref());
+
+        self.send_instruction(Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        })
+        .await
+    }
+
+    /// Sets the market's share of referred takers' fees paid to referrers.
+    pub async fn set_referral_share(&self, market_pda: &Pubkey, referral_share_bps: u16) -> Result<String> {
+        let authority = self.authority()?;
+        let _timer = METRICS.rpc_latency.with_label_values(&["set_referral_share"]).start_timer();
+        let accounts = vec![
+            AccountMeta::new_readonly(authority.pubkey(), true), // authority
+            AccountMeta::new(*market_pda, false),                 // market
+        ];
+
+        let mut data = Vec::with_capacity(8 + 2);
+        data.extend_from_slice(&SET_REFERRAL_SHARE_DISCRIMINATOR);
+        data.extend_from_slice(&referral_share_bps.to_le_bytes());
+
+        self.send_instruction(Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        })
+        .await
+    }
+
+    async fn send_instruction(&self, instruction: Instruction) -> Result<String> {
+        let authority = self.authority()?;
+        let recent_blockhash = self.client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&authority.pubkey()),
+            &[authority],
+            recent_blockhash,
+        );
+
+        let signature = self.client.send_and_confirm_transaction(&transaction).await?;
+        Ok(signature.to_string())
+    }
+
+    /// Records on chain the rates `wallet` pays on a market.
+    pub async fn set_fee_tier(
+        &self,
+        market_pda: &Pubkey,
+        wallet: &Pubkey,
+        maker_fee_bps: i16,
+        taker_fee_bps: u16,
+    ) -> Result<String> {
+        let authority = self.authority()?;
+        let _timer = METRICS.rpc_latency.with_label_values(&["set_fee_tier"]).start_timer();
+        let fee_tier = fee_tier_address(&self.program_id, market_pda, wallet);
+
+        let accounts = vec![
+            AccountMeta::new(authority.pubkey(), true),                     // authority
+            AccountMeta::new_readonly(*market_pda, false),                   // market
+            AccountMeta::new(fee_tier, false),                               // fee_tier
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false), // system_program
+        ];
+
+        let mut data = Vec::with_capacity(8 + 32 + 2 + 2);
+        data.extend_from_slice(&SET_FEE_TIER_DISCRIMINATOR);
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(&maker_fee_bps.to_le_bytes());
+        data.extend_from_slice(&taker_fee_bps.to_le_bytes());
+
         let instruction = Instruction {
             program_id: self.program_id,
             accounts,
             data,
         };
 
-        let recent_blockhash = self.client.get_latest_blockhash()?;
+        let recent_blockhash = self.client.get_latest_blockhash().await?;
         let transaction = Transaction::new_signed_with_payer(
             &[instruction],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
+            Some(&authority.pubkey()),
+            &[authority],
             recent_blockhash,
         );
 
-        let signature = self.client.send_and_confirm_transaction(&transaction)?;
+        let signature = self.client.send_and_confirm_transaction(&transaction).await?;
         Ok(signature.to_string())
     }
+
+    /// Cancels or expires an order on behalf of its owner, releasing whatever
+    /// the order still has locked in the user's vault.
+    pub async fn cancel_order(&self, task: &UnlockTask, market: &Market) -> Result<String> {
+        let authority = self.authority()?;
+        let _timer = METRICS.rpc_latency.with_label_values(&["cancel_order"]).start_timer();
+        let user_wallet = Pubkey::from_str(&task.user_wallet)?;
+        let base_mint = Pubkey::from_str(&market.base_mint)?;
+        let quote_mint = Pubkey::from_str(&market.quote_mint)?;
+
+        let (market_pda, _) = Pubkey::find_program_address(
+            &[MARKET_SEED, base_mint.as_ref(), quote_mint.as_ref()],
+            &self.program_id,
+        );
+
+        let (user_vault, _) = Pubkey::find_program_address(
+            &[VAULT_SEED, user_wallet.as_ref(), market_pda.as_ref()],
+            &self.program_id,
+        );
+
+        let order_id_u128 = u128::from_str(&task.order_id)?;
+        let (order, _) = Pubkey::find_program_address(
+            &[ORDER_SEED, &order_id_u128.to_le_bytes()],
+            &self.program_id,
+        );
+
+        // Both instructions take the same accounts; `expire_order` is permissionless
+        // and only succeeds once the on-chain expiry has passed.
+        let accounts = vec![
+            AccountMeta::new_readonly(authority.pubkey(), true), // authority / caller
+            AccountMeta::new_readonly(market_pda, false),         // market
+            AccountMeta::new(user_vault, false),                  // user_vault
+            AccountMeta::new(order, false),                       // order
+        ];
+
+        let discriminator = match task.reason {
+            CancelReason::Expired => EXPIRE_ORDER_DISCRIMINATOR,
+            _ => AUTHORITY_CANCEL_ORDER_DISCRIMINATOR,
+        };
+
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts,
+            data: discriminator.to_vec(),
+        };
+
+        let recent_blockhash = self.client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&authority.pubkey()),
+            &[authority],
+            recent_blockhash,
+        );
+
+        let signature = self.client.send_and_confirm_transaction(&transaction).await?;
+        Ok(signature.to_string())
+    }
+
+    /// Raw balance of an SPL token account.
+    pub async fn token_balance(&self, address: &Pubkey) -> Result<u64> {
+        let _timer = METRICS.rpc_latency.with_label_values(&["token_balance"]).start_timer();
+        let balance = self.client.get_token_account_balance(address).await?;
+        Ok(balance.amount.parse()?)
+    }
+
+    /// Slots the node's confirmed slot trails the newest shred it has
+    /// received, i.e. how far it is behind the cluster.
+    pub async fn slot_lag(&self) -> Result<u64> {
+        let _timer = METRICS.rpc_latency.with_label_values(&["slot_lag"]).start_timer();
+        let slot = self.client.get_slot().await?;
+        let tip = self.client.get_max_shred_insert_slot().await?;
+        Ok(tip.saturating_sub(slot))
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// Reads and decodes the `Market` account at `address`.
+    pub async fn fetch_market(&self, address: &Pubkey) -> Result<OnChainMarket> {
+        let _timer = METRICS.rpc_latency.with_label_values(&["fetch_market"]).start_timer();
+        let account = self.client.get_account(address).await?;
+        anyhow::ensure!(
+            account.owner == self.program_id,
+            "Account {} is not owned by the DEX program",
+            address
+        );
+        decode_market_account(address, &account.data)
+    }
+
+    /// Reads the on-chain order `order_id`, if the order account exists.
+    /// Reads at `confirmed`, which clients wait for before handing the order
+    /// to the engine.
+    pub async fn fetch_order(&self, order_id: u128) -> Result<Option<OnChainOrder>> {
+        let _timer = METRICS.rpc_latency.with_label_values(&["fetch_order"]).start_timer();
+        let (address, _) = Pubkey::find_program_address(&[ORDER_SEED, &order_id.to_le_bytes()], &self.program_id);
+        let account = self
+            .client
+            .get_account_with_commitment(&address, CommitmentConfig::confirmed())
+            .await?
+            .value;
+        account
+            .map(|account| {
+                anyhow::ensure!(
+                    account.owner == self.program_id,
+                    "Account {} is not owned by the DEX program",
+                    address
+                );
+                decode_order_account(&address, &account.data)
+            })
+            .transpose()
+    }
+
+    /// Reads the delegate `wallet` authorized on `market`, if any. Answers
+    /// from a short-lived cache when the account was read recently.
+    pub async fn fetch_delegate(&self, market: &Pubkey, wallet: &Pubkey) -> Result<Option<OnChainDelegate>> {
+        let address = delegate_address(&self.program_id, market, wallet);
+        if let Some(delegate) = self.delegates.read().await.get(&address, Instant::now()) {
+            return Ok(delegate);
+        }
+
+        let _timer = METRICS.rpc_latency.with_label_values(&["fetch_delegate"]).start_timer();
+        let account = self
+            .client
+            .get_account_with_commitment(&address, self.client.commitment())
+            .await?
+            .value;
+        let delegate = account
+            .map(|account| {
+                anyhow::ensure!(
+                    account.owner == self.program_id,
+                    "Account {} is not owned by the DEX program",
+                    address
+                );
+                decode_delegate_account(&address, &account.data)
+            })
+            .transpose()?;
+        self.delegates.write().await.insert(address, delegate.clone(), Instant::now());
+        Ok(delegate)
+    }
+
+    /// Creates the market PDA and its escrow vaults, with the engine key as
+    /// market authority. Returns the transaction signature and market address.
+    pub async fn initialize_market(
+        &self,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+        fee_recipient: &Pubkey,
+        params: &InitializeMarketParams,
+    ) -> Result<(String, Pubkey)> {
+        let authority = self.authority()?;
+        let _timer = METRICS.rpc_latency.with_label_values(&["initialize_market"]).start_timer();
+        let market_pda = market_address(&self.program_id, base_mint, quote_mint);
+        let (base_vault, _) = Pubkey::find_program_address(
+            &[ESCROW_SEED, market_pda.as_ref(), b"base"],
+            &self.program_id,
+        );
+        let (quote_vault, _) = Pubkey::find_program_address(
+            &[ESCROW_SEED, market_pda.as_ref(), b"quote"],
+            &self.program_id,
+        );
+
+        let accounts = vec![
+            AccountMeta::new(authority.pubkey(), true),                        // authority
+            AccountMeta::new(market_pda, false),                                // market
+            AccountMeta::new_readonly(*base_mint, false),                       // base_mint
+            AccountMeta::new_readonly(*quote_mint, false),                      // quote_mint
+            AccountMeta::new(base_vault, false),                                // base_vault
+            AccountMeta::new(quote_vault, false),                               // quote_vault
+            AccountMeta::new_readonly(*fee_recipient, false),                   // fee_recipient
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false), // system_program
+            AccountMeta::new_readonly(spl_token::id(), false),                  // token_program
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::id(), false),   // rent
+        ];
+
+        let mut data = Vec::with_capacity(8 + 8 + 8 + 2 + 2);
+        data.extend_from_slice(&INITIALIZE_MARKET_DISCRIMINATOR);
+        data.extend_from_slice(&params.min_order_size.to_le_bytes());
+        data.extend_from_slice(&params.tick_size.to_le_bytes());
+        data.extend_from_slice(&params.maker_fee_bps.to_le_bytes());
+        data.extend_from_slice(&params.taker_fee_bps.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        };
+
+        let recent_blockhash = self.client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&authority.pubkey()),
+            &[authority],
+            recent_blockhash,
+        );
+
+        let signature = self.client.send_and_confirm_transaction(&transaction).await?;
+        Ok((signature.to_string(), market_pda))
+    }
+}
+
+/// Decodes a Borsh-serialized `Market` account, fields in declaration order
+/// after the 8-byte Anchor discriminator.
+pub fn decode_market_account(address: &Pubkey, data: &[u8]) -> Result<OnChainMarket> {
+    let mut reader = AccountReader { data };
+    anyhow::ensure!(
+        reader.take::<8>()? == MARKET_DISCRIMINATOR,
+        "Account {} is not a market",
+        address
+    );
+
+    let authority = reader.pubkey()?;
+    let base_mint = reader.pubkey()?;
+    let quote_mint = reader.pubkey()?;
+    let base_vault = reader.pubkey()?;
+    let quote_vault = reader.pubkey()?;
+    let base_decimals = u8::from_le_bytes(reader.take()?);
+    let quote_decimals = u8::from_le_bytes(reader.take()?);
+    let min_order_size = u64::from_le_bytes(reader.take()?);
+    let tick_size = u64::from_le_bytes(reader.take()?);
+    let maker_fee_bps = u16::from_le_bytes(reader.take()?);
+    let taker_fee_bps = u16::from_le_bytes(reader.take()?);
+    let fee_recipient = reader.pubkey()?;
+    let is_active = reader.take::<1>()?[0] != 0;
+    let _totals = reader.take::<16>()?;
+    let _bump = reader.take::<1>()?;
+    let referral_share_bps = u16::from_le_bytes(reader.take()?);
+
+    Ok(OnChainMarket {
+        address: address.to_string(),
+        authority: authority.to_string(),
+        base_mint: base_mint.to_string(),
+        quote_mint: quote_mint.to_string(),
+        base_vault: base_vault.to_string(),
+        quote_vault: quote_vault.to_string(),
+        base_decimals,
+        quote_decimals,
+        min_order_size,
+        tick_size,
+        maker_fee_bps,
+        taker_fee_bps,
+        fee_recipient: fee_recipient.to_string(),
+        is_active,
+        referral_share_bps,
+    })
+}
+
+/// Decodes a `FeeTier` account into its maker and taker rates.
+pub fn decode_fee_tier_account(address: &Pubkey, data: &[u8]) -> Result<(i16, u16)> {
+    let mut reader = AccountReader { data };
+    anyhow::ensure!(
+        reader.take::<8>()? == FEE_TIER_DISCRIMINATOR,
+        "Account {} is not a fee tier",
+        address
+    );
+
+    let _market = reader.pubkey()?;
+    let _wallet = reader.pubkey()?;
+    let maker_fee_bps = i16::from_le_bytes(reader.take()?);
+    let taker_fee_bps = u16::from_le_bytes(reader.take()?);
+    Ok((maker_fee_bps, taker_fee_bps))
+}
+
+/// Decodes the status and expiry of an `Order` account.
+pub fn decode_order_account(address: &Pubkey, data: &[u8]) -> Result<OnChainOrder> {
+    let mut reader = AccountReader { data };
+    anyhow::ensure!(
+        reader.take::<8>()? == ORDER_DISCRIMINATOR,
+        "Account {} is not an order",
+        address
+    );
+
+    // user, market, order_id, side, price, size, filled
+    let _fields = reader.take::<{ 32 + 32 + 16 + 1 + 8 + 8 + 8 }>()?;
+    let [status] = reader.take()?;
+    // created_at, updated_at, bump
+    let _fields = reader.take::<{ 8 + 8 + 1 }>()?;
+    let expires_at = i64::from_le_bytes(reader.take()?);
+
+    Ok(OnChainOrder {
+        is_open: status == ORDER_STATUS_PENDING || status == ORDER_STATUS_PARTIALLY_FILLED,
+        expires_at,
+    })
+}
+
+/// Decodes a `Delegate` account.
+pub fn decode_delegate_account(address: &Pubkey, data: &[u8]) -> Result<OnChainDelegate> {
+    let mut reader = AccountReader { data };
+    anyhow::ensure!(
+        reader.take::<8>()? == DELEGATE_DISCRIMINATOR,
+        "Account {} is not a delegate",
+        address
+    );
+
+    let _user_vault = reader.pubkey()?;
+    let wallet = reader.pubkey()?;
+    let delegate = reader.pubkey()?;
+    let expires_at = i64::from_le_bytes(reader.take()?);
+    let max_order_notional = u64::from_le_bytes(reader.take()?);
+    let max_total_notional = u64::from_le_bytes(reader.take()?);
+    let notional_used = u64::from_le_bytes(reader.take()?);
+
+    Ok(OnChainDelegate {
+        wallet: wallet.to_string(),
+        delegate: delegate.to_string(),
+        expires_at,
+        max_order_notional,
+        max_total_notional,
+        notional_used,
+    })
+}
+
+struct AccountReader<'a> {
+    data: &'a [u8],
+}
+
+impl AccountReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        anyhow::ensure!(self.data.len() >= N, "Account data is truncated");
+        let (head, rest) = self.data.split_at(N);
+        self.data = rest;
+        Ok(head.try_into()?)
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey> {
+        Ok(Pubkey::new_from_array(self.take()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(preimage.as_bytes())[..8].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_discriminators_match_anchor() {
+        let discriminators = [
+            ("global:initialize_market", INITIALIZE_MARKET_DISCRIMINATOR),
+            ("global:settle_trade", SETTLE_TRADE_DISCRIMINATOR),
+            ("global:authority_cancel_order", AUTHORITY_CANCEL_ORDER_DISCRIMINATOR),
+            ("global:expire_order", EXPIRE_ORDER_DISCRIMINATOR),
+            ("global:set_fee_tier", SET_FEE_TIER_DISCRIMINATOR),
+            ("global:set_referral", SET_REFERRAL_DISCRIMINATOR),
+            ("global:set_referral_share", SET_REFERRAL_SHARE_DISCRIMINATOR),
+            ("account:Market", MARKET_DISCRIMINATOR),
+            ("account:FeeTier", FEE_TIER_DISCRIMINATOR),
+            ("account:Referral", REFERRAL_DISCRIMINATOR),
+            ("account:Order", ORDER_DISCRIMINATOR),
+            ("account:Delegate", DELEGATE_DISCRIMINATOR),
+        ];
+        for (preimage, discriminator) in discriminators {
+            assert_eq!(discriminator, anchor_discriminator(preimage), "{}", preimage);
+        }
+    }
+
+    #[test]
+    fn test_decode_market_account() {
+        let address = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+
+        let mut data = MARKET_DISCRIMINATOR.to_vec();
+        for key in &keys[..5] {
+            data.extend_from_slice(key.as_ref());
+        }
+        data.extend_from_slice(&[9, 6]);
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&10_000u64.to_le_bytes());
+        data.extend_from_slice(&5u16.to_le_bytes());
+        data.extend_from_slice(&10u16.to_le_bytes());
+        data.extend_from_slice(keys[5].as_ref());
+        data.push(1);
+        data.extend_from_slice(&[0; 17]); // totals, bump
+        data.extend_from_slice(&2_000u16.to_le_bytes());
+        data.extend_from_slice(&[0; 62]); // padding
+
+        let market = decode_market_account(&address, &data).unwrap();
+        assert_eq!(market.address, address.to_string());
+        assert_eq!(market.base_mint, keys[1].to_string());
+        assert_eq!(market.quote_vault, keys[4].to_string());
+        assert_eq!((market.base_decimals, market.quote_decimals), (9, 6));
+        assert_eq!((market.min_order_size, market.tick_size), (1_000_000, 10_000));
+        assert_eq!((market.maker_fee_bps, market.taker_fee_bps), (5, 10));
+        assert_eq!(market.fee_recipient, keys[5].to_string());
+        assert!(market.is_active);
+        assert_eq!(market.referral_share_bps, 2_000);
+
+        data[0] = 0;
+        assert!(decode_market_account(&address, &data).is_err());
+        assert!(decode_market_account(&address, &data[..40]).is_err());
+    }
+
+    #[test]
+    fn test_decode_fee_tier_account() {
+        let address = Pubkey::new_unique();
+        let mut data = FEE_TIER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&(-2i16).to_le_bytes());
+        data.extend_from_slice(&8u16.to_le_bytes());
+        data.extend_from_slice(&[0; 1 + 32]); // bump, padding
+
+        assert_eq!(decode_fee_tier_account(&address, &data).unwrap(), (-2, 8));
+        assert!(decode_market_account(&address, &data).is_err());
+    }
+
+    #[test]
+    fn test_decode_order_account() {
+        let address = Pubkey::new_unique();
+        let order = |status: u8| {
+            let mut data = ORDER_DISCRIMINATOR.to_vec();
+            data.extend_from_slice(&[7; 32 + 32 + 16 + 1 + 8 + 8 + 8]);
+            data.push(status);
+            data.extend_from_slice(&[7; 8 + 8 + 1]);
+            data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+            data.extend_from_slice(&[0; 24]); // padding
+            data
+        };
+
+        let partially_filled = decode_order_account(&address, &order(1)).unwrap();
+        assert_eq!(partially_filled, OnChainOrder { is_open: true, expires_at: 1_700_000_000 });
+        // Filled, cancelled and expired orders hold nothing to unlock.
+        for status in [2, 3, 4] {
+            assert!(!decode_order_account(&address, &order(status)).unwrap().is_open);
+        }
+        assert!(decode_order_account(&address, &order(0)[..100]).is_err());
+        assert!(decode_market_account(&address, &order(0)).is_err());
+    }
+
+    #[test]
+    fn test_decode_delegate_account() {
+        let address = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let session_key = Pubkey::new_unique();
+        let mut data = DELEGATE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(session_key.as_ref());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&500u64.to_le_bytes());
+        data.extend_from_slice(&10_000u64.to_le_bytes());
+        data.extend_from_slice(&1_200u64.to_le_bytes());
+        data.extend_from_slice(&[0; 1 + 32]); // bump, padding
+
+        let delegate = decode_delegate_account(&address, &data).unwrap();
+        assert_eq!(delegate.wallet, wallet.to_string());
+        assert_eq!(delegate.delegate, session_key.to_string());
+        assert_eq!(delegate.expires_at, 1_700_000_000);
+        assert_eq!((delegate.max_order_notional, delegate.max_total_notional), (500, 10_000));
+        assert_eq!(delegate.notional_used, 1_200);
+        assert!(decode_fee_tier_account(&address, &data).is_err());
+    }
+
+    #[test]
+    fn test_delegate_cache_expires() {
+        let mut cache = DelegateCache::default();
+        let address = Pubkey::new_unique();
+        let fetched_at = Instant::now();
+        cache.insert(address, None, fetched_at);
+
+        assert_eq!(cache.get(&address, fetched_at), Some(None));
+        assert_eq!(cache.get(&Pubkey::new_unique(), fetched_at), None);
+        assert_eq!(cache.get(&address, fetched_at + DELEGATE_CACHE_TTL), None);
+
+        // Stale entries are dropped on the next insert.
+        cache.insert(Pubkey::new_unique(), None, fetched_at + DELEGATE_CACHE_TTL);
+        assert_eq!(cache.entries.len(), 1);
+    }
 }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
@@ -10,6 +10,15 @@ pub enum OrderSide {
     Sell,
 }
 
+impl OrderSide {
+    pub fn opposite(&self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
@@ -18,6 +27,33 @@ pub enum OrderStatus {
     PartiallyFilled,
     Filled,
     Cancelled,
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OrderType {
+    #[default]
+    Limit,
+    StopMarket,
+    StopLimit,
+    TakeProfitMarket,
+    TakeProfitLimit,
+}
+
+impl OrderType {
+    /// Trigger orders wait in the trigger book until the last trade price
+    /// crosses their `trigger_price`.
+    pub fn is_trigger(&self) -> bool {
+        !matches!(self, OrderType::Limit)
+    }
+
+    /// Whether an unfilled remainder rests in the book after matching. Market
+    /// variants use the order price only as a slippage cap and drop the rest.
+    pub fn rests(&self) -> bool {
+        !matches!(self, OrderType::StopMarket | OrderType::TakeProfitMarket)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,7 +67,13 @@ pub struct Order {
     pub size: i64,
     pub filled: i64,
     pub status: OrderStatus,
+    pub order_type: OrderType,
+    pub trigger_price: Option<i64>,
+    pub triggered_at: Option<DateTime<Utc>>,
+    /// Iceberg orders show at most this much of their remaining size.
+    pub display_size: Option<i64>,
     pub on_chain_signature: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -52,12 +94,310 @@ pub struct Trade {
     pub taker_wallet: String,
     pub price: i64,
     pub size: i64,
+    /// Side of the incoming order that took liquidity.
+    pub taker_side: OrderSide,
+    /// Per-market trade number, starting at 1 and without gaps.
+    pub sequence: i64,
     pub maker_fee: i64,
     pub taker_fee: i64,
+    pub settlement_status: SettlementStatus,
+    pub settlement_signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementStatus {
+    Pending,
+    Settled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum LiquidityRole {
+    Maker,
+    Taker,
+}
+
+/// One side of a trade, as seen by the wallet that owns the order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub trade_id: i64,
+    pub market_id: Uuid,
+    pub order_id: String,
+    pub wallet: String,
+    pub side: OrderSide,
+    pub role: LiquidityRole,
+    pub price: i64,
+    pub size: i64,
+    /// Fee charged to this side, in quote atoms.
+    pub fee: i64,
+    pub sequence: i64,
+    pub settlement_status: SettlementStatus,
     pub settlement_signature: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
+impl Trade {
+    pub fn fill_for(&self, role: LiquidityRole) -> Fill {
+        let (order_id, wallet, side, fee) = match role {
+            LiquidityRole::Maker => (
+                &self.maker_order_id,
+                &self.maker_wallet,
+                self.taker_side.opposite(),
+                self.maker_fee,
+            ),
+            LiquidityRole::Taker => (
+                &self.taker_order_id,
+                &self.taker_wallet,
+                self.taker_side,
+                self.taker_fee,
+            ),
+        };
+
+        Fill {
+            trade_id: self.id,
+            market_id: self.market_id,
+            order_id: order_id.clone(),
+            wallet: wallet.clone(),
+            side,
+            role,
+            price: self.price,
+            size: self.size,
+            fee,
+            sequence: self.sequence,
+            settlement_status: self.settlement_status,
+            settlement_signature: self.settlement_signature.clone(),
+            created_at: self.created_at,
+        }
+    }
+
+    /// The wallet's fills in this trade: one, or both sides on a self-trade.
+    pub fn fills_for_wallet(&self, wallet: &str) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        if self.maker_wallet == wallet {
+            fills.push(self.fill_for(LiquidityRole::Maker));
+        }
+        if self.taker_wallet == wallet {
+            fills.push(self.fill_for(LiquidityRole::Taker));
+        }
+        fills
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceChangeReason {
+    Deposit,
+    Withdrawal,
+    Trade,
+}
+
+/// Change to a wallet's vault balances in one market, in base and quote atoms.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub market_id: Uuid,
+    pub reason: BalanceChangeReason,
+    pub base_delta: i64,
+    pub quote_delta: i64,
+    /// Deposit/withdrawal signature or trade id.
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar")]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    #[sqlx(rename = "1m")]
+    M1,
+    #[serde(rename = "5m")]
+    #[sqlx(rename = "5m")]
+    M5,
+    #[serde(rename = "15m")]
+    #[sqlx(rename = "15m")]
+    M15,
+    #[serde(rename = "1h")]
+    #[sqlx(rename = "1h")]
+    H1,
+    #[serde(rename = "4h")]
+    #[sqlx(rename = "4h")]
+    H4,
+    #[serde(rename = "1d")]
+    #[sqlx(rename = "1d")]
+    D1,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 6] = [
+        CandleInterval::M1,
+        CandleInterval::M5,
+        CandleInterval::M15,
+        CandleInterval::H1,
+        CandleInterval::H4,
+        CandleInterval::D1,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandleInterval::M1 => "1m",
+            CandleInterval::M5 => "5m",
+            CandleInterval::M15 => "15m",
+            CandleInterval::H1 => "1h",
+            CandleInterval::H4 => "4h",
+            CandleInterval::D1 => "1d",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            CandleInterval::M1 => 60,
+            CandleInterval::M5 => 5 * 60,
+            CandleInterval::M15 => 15 * 60,
+            CandleInterval::H1 => 60 * 60,
+            CandleInterval::H4 => 4 * 60 * 60,
+            CandleInterval::D1 => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the candle containing `time`. Buckets are aligned to the Unix
+    /// epoch, so daily candles open at 00:00 UTC.
+    pub fn open_time(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let seconds = time.timestamp();
+        let start = seconds - seconds.rem_euclid(self.seconds());
+        DateTime::from_timestamp(start, 0).unwrap_or(time)
+    }
+}
+
+/// OHLCV for one market and interval. Prices are in quote atoms, `volume` in
+/// base atoms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candle {
+    pub market_id: Uuid,
+    pub interval: CandleInterval,
+    pub open_time: DateTime<Utc>,
+    pub open: i64,
+    pub high: i64,
+    pub low: i64,
+    pub close: i64,
+    pub volume: i64,
+    pub trade_count: i64,
+}
+
+impl std::str::FromStr for CandleInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CandleInterval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == s)
+            .ok_or_else(|| format!("Unknown candle interval '{}'", s))
+    }
+}
+
+/// A WebSocket stream. Market channels need a `market_id`; wallet channels
+/// need a logged-in session and deliver only that wallet's events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Channel {
+    /// `book.L2`: snapshot then sequenced level deltas.
+    BookL2,
+    /// `book.L1`: best bid and ask whenever either changes.
+    BookL1,
+    Trades,
+    Ticker,
+    /// `candles.<interval>`, e.g. `candles.1m`.
+    Candles(CandleInterval),
+    Orders,
+    Fills,
+    Balances,
+}
+
+impl Channel {
+    pub fn is_private(&self) -> bool {
+        matches!(self, Channel::Orders | Channel::Fills | Channel::Balances)
+    }
+
+    /// Channels whose updates can be coalesced for a throttled subscriber.
+    pub fn supports_throttle(&self) -> bool {
+        matches!(
+            self,
+            Channel::BookL2 | Channel::BookL1 | Channel::Ticker | Channel::Candles(_)
+        )
+    }
+}
+
+impl TryFrom<String> for Channel {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "book.L2" => Ok(Channel::BookL2),
+            "book.L1" => Ok(Channel::BookL1),
+            "trades" => Ok(Channel::Trades),
+            "ticker" => Ok(Channel::Ticker),
+            "orders" => Ok(Channel::Orders),
+            "fills" => Ok(Channel::Fills),
+            "balances" => Ok(Channel::Balances),
+            other => match other.strip_prefix("candles.") {
+                Some(interval) => Ok(Channel::Candles(interval.parse()?)),
+                None => Err(format!("Unknown channel '{}'", other)),
+            },
+        }
+    }
+}
+
+impl From<Channel> for String {
+    fn from(channel: Channel) -> Self {
+        match channel {
+            Channel::BookL2 => "book.L2".to_string(),
+            Channel::BookL1 => "book.L1".to_string(),
+            Channel::Trades => "trades".to_string(),
+            Channel::Ticker => "ticker".to_string(),
+            Channel::Candles(interval) => format!("candles.{}", interval.as_str()),
+            Channel::Orders => "orders".to_string(),
+            Channel::Fills => "fills".to_string(),
+            Channel::Balances => "balances".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscribeParams {
+    pub channel: Channel,
+    pub market_id: Option<Uuid>,
+    /// `book.L2` only: number of levels per side; the full book if absent.
+    pub depth: Option<usize>,
+    /// Send at most one coalesced update per interval.
+    pub throttle_ms: Option<u64>,
+}
+
+/// Best bid and ask of a market.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookTop {
+    pub market_id: Uuid,
+    pub sequence: u64,
+    pub bid: Option<OrderbookLevel>,
+    pub ask: Option<OrderbookLevel>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// How a taker's quantity is shared between the makers at one price level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MatchingAlgorithm {
+    /// Strict price-time priority.
+    #[default]
+    Fifo,
+    /// In proportion to each maker's displayed size.
+    ProRata,
+    /// `fifo_slice_bps` of the quantity in time priority, the rest pro-rata.
+    Hybrid,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Market {
     pub id: Uuid,
@@ -70,10 +410,323 @@ pub struct Market {
     pub maker_fee_bps: i16,
     pub taker_fee_bps: i16,
     pub is_active: bool,
+    pub matching_algorithm: MatchingAlgorithm,
+    pub fifo_slice_bps: i16,
+    /// Market PDA; `None` until the market is created or imported from chain.
+    pub market_address: Option<String>,
+    pub base_vault: Option<String>,
+    pub quote_vault: Option<String>,
+    pub fee_recipient: Option<String>,
+    /// Share of a referred taker's fee paid to the referrer.
+    pub referral_share_bps: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a wallet's fee rates come from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeSource {
+    Override,
+    Tier,
+    Market,
+}
+
+/// Rates one wallet pays on one market. A negative maker rate is a rebate,
+/// paid out of the taker fee of the same fill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeRates {
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    pub source: FeeSource,
+}
+
+impl FeeRates {
+    pub fn market_default(market: &Market) -> Self {
+        Self {
+            maker_fee_bps: market.maker_fee_bps,
+            taker_fee_bps: market.taker_fee_bps,
+            source: FeeSource::Market,
+        }
+    }
+}
+
+/// Rates for wallets that traded at least `min_volume` quote atoms on the
+/// market over the last 30 days.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub min_volume: i64,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+}
+
+/// Rates granted to one wallet regardless of its volume.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeOverride {
+    pub wallet: String,
+    pub market_id: Uuid,
+    pub maker_fee_bps: i16,
+    pub taker_fee_bps: i16,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A wallet's current rates on a market and the volume they were based on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletFees {
+    pub wallet: String,
+    pub market_id: Uuid,
+    /// Quote atoms traded as maker or taker over the last 30 days.
+    pub volume_30d: i64,
+    #[serde(flatten)]
+    pub rates: FeeRates,
+    /// Gets a share of the wallet's taker fees.
+    pub referrer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Referral {
+    pub wallet: String,
+    pub referrer: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `wallet` signs `auth::referral_message(wallet, referrer)`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegisterReferralRequest {
+    pub wallet: String,
+    pub referrer: String,
+    pub signature: String,
+}
+
+/// A referrer's earnings on one market, in quote atoms.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralEarnings {
+    pub market_id: Uuid,
+    /// Paid out by settled trades.
+    pub settled: i64,
+    /// Owed by trades still waiting for settlement.
+    pub pending: i64,
+    pub trade_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralSummary {
+    pub wallet: String,
+    /// Who referred this wallet.
+    pub referrer: Option<String>,
+    pub referee_count: i64,
+    pub earnings: Vec<ReferralEarnings>,
+}
+
+/// What an API key may do. Every key can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    Read,
+    /// Place and cancel orders.
+    Trade,
+    /// Cancel orders but not place them, e.g. for a kill switch.
+    CancelOnly,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Trade => "trade",
+            ApiKeyScope::CancelOnly => "cancel_only",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read" => Some(ApiKeyScope::Read),
+            "trade" => Some(ApiKeyScope::Trade),
+            "cancel_only" => Some(ApiKeyScope::CancelOnly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: String,
+    pub wallet: String,
+    pub label: String,
+    #[serde(skip)]
+    pub secret_hash: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub ip_allowlist: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
+impl ApiKey {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+
+    pub fn can_place(&self) -> bool {
+        self.scopes.contains(&ApiKeyScope::Trade)
+    }
+
+    pub fn can_cancel(&self) -> bool {
+        self.scopes.iter().any(|scope| matches!(scope, ApiKeyScope::Trade | ApiKeyScope::CancelOnly))
+    }
+}
+
+/// `wallet` signs `auth::api_key_message` over the other fields.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub wallet: String,
+    pub label: String,
+    pub scopes: Vec<ApiKeyScope>,
+    #[serde(default)]
+    pub ip_allowlist: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Unix milliseconds; the signature is only accepted shortly after.
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Returned once on creation; the secret cannot be retrieved later.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+/// `wallet` signs `auth::revoke_api_key_message(wallet, key_id, timestamp)`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RevokeApiKeyRequest {
+    pub wallet: String,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+/// Fees booked for one market, UTC day and role once their trades settled.
+/// Amounts are quote atoms; maker `fees` are negative where rebates exceed
+/// maker fees.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeLedgerEntry {
+    pub market_id: Uuid,
+    pub day: NaiveDate,
+    pub role: LiquidityRole,
+    pub fees: i64,
+    /// Part of `fees` paid on to referrers.
+    pub referrer_fees: i64,
+    /// What the fee recipient kept.
+    pub net_fees: i64,
+    pub trade_count: i64,
+}
+
+/// Protocol revenue of one market over a range of days.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueTotals {
+    pub market_id: Uuid,
+    pub maker_fees: i64,
+    pub taker_fees: i64,
+    pub referrer_fees: i64,
+    pub net_revenue: i64,
+    pub trade_count: i64,
+}
+
+/// A fee recipient's token balance against the net fees the ledger says it
+/// received. Sweeps out of the account show up as a negative difference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeReconciliation {
+    pub id: i64,
+    pub fee_recipient: String,
+    pub observed_balance: i64,
+    pub ledger_total: i64,
+    pub difference: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The program's `Market` account, the source of truth for a market's mints,
+/// vaults, decimals, tick and fees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnChainMarket {
+    pub address: String,
+    pub authority: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub base_vault: String,
+    pub quote_vault: String,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub min_order_size: u64,
+    pub tick_size: u64,
+    pub maker_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    pub fee_recipient: String,
+    pub is_active: bool,
+    pub referral_share_bps: u16,
+}
+
+/// The program's `Delegate` account: a session key the vault owner allowed
+/// to place and cancel orders. Zero limits and expiry mean unlimited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OnChainDelegate {
+    pub wallet: String,
+    pub delegate: String,
+    pub expires_at: i64,
+    pub max_order_notional: u64,
+    pub max_total_notional: u64,
+    pub notional_used: u64,
+}
+
+impl OnChainDelegate {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at != 0 && now.timestamp() >= self.expires_at
+    }
+
+    /// Whether an order worth `notional` quote atoms fits the limits the
+    /// program enforces in `delegate_place_order`.
+    pub fn allows_notional(&self, notional: u64) -> bool {
+        let within_order = self.max_order_notional == 0 || notional <= self.max_order_notional;
+        let within_total = self.max_total_notional == 0
+            || self
+                .notional_used
+                .checked_add(notional)
+                .is_some_and(|used| used <= self.max_total_notional);
+        within_order && within_total
+    }
+}
+
+/// The parts of the program's `Order` account the engine checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnChainOrder {
+    /// Pending or partially filled, so the order still holds locked funds.
+    pub is_open: bool,
+    /// 0 means good-till-cancel.
+    pub expires_at: i64,
+}
+
+/// Rolling 24h statistics for one market. Prices are in quote atoms,
+/// `volume_24h` in base atoms and `quote_volume_24h` in quote atoms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ticker {
+    pub market_id: Uuid,
+    /// Latest trade price, even when older than the window.
+    pub last_price: Option<i64>,
+    pub best_bid: Option<i64>,
+    pub best_ask: Option<i64>,
+    pub open_24h: Option<i64>,
+    pub high_24h: Option<i64>,
+    pub low_24h: Option<i64>,
+    pub price_change_24h: Option<i64>,
+    /// `price_change_24h` relative to `open_24h`, in basis points.
+    pub price_change_bps_24h: Option<i64>,
+    pub volume_24h: i64,
+    pub quote_volume_24h: i64,
+    pub trade_count_24h: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OrderbookLevel {
     pub price: i64,
     pub size: i64,
@@ -83,6 +736,27 @@ pub struct OrderbookLevel {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderbookSnapshot {
     pub market_id: Uuid,
+    /// Book sequence this snapshot reflects; see [`OrderbookDelta`].
+    pub sequence: u64,
+    pub bids: Vec<OrderbookLevel>,
+    pub asks: Vec<OrderbookLevel>,
+    pub last_price: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Changed L2 levels of one book mutation. A level with `size == 0` was
+/// removed.
+///
+/// Every delta bumps the market's `sequence` by one and carries the sequence
+/// it applies on top of as `prev_sequence`. Clients apply a delta only when
+/// `prev_sequence` equals the sequence of their local book, drop deltas with
+/// `sequence` at or below it (already contained in the snapshot), and treat
+/// anything else as a gap: resubscribe to get a fresh snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderbookDelta {
+    pub market_id: Uuid,
+    pub sequence: u64,
+    pub prev_sequence: u64,
     pub bids: Vec<OrderbookLevel>,
     pub asks: Vec<OrderbookLevel>,
     pub last_price: Option<i64>,
@@ -97,14 +771,92 @@ pub struct PlaceOrderRequest {
     pub size: i64,
     pub wallet: String,
     pub signature: String,
-    pub order_id: Option<String>, // Added optional order_id
+    /// Id of the order placed on chain. Required on on-chain markets, once
+    /// the `place_order` transaction is `confirmed`.
+    pub order_id: Option<String>,
+    pub session_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub order_type: OrderType,
+    pub trigger_price: Option<i64>,
+    pub display_size: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CancelOrderRequest {
-    pub order_id: String, // Changed to String
-    pub wallet: String,
-    pub signature: String,
+/// Why an order is being pulled from the book. Cancels the engine performs on
+/// the user's behalf also have to release the locked funds on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    UserRequested,
+    SessionDisconnect,
+    Expired,
+}
+
+impl CancelReason {
+    pub fn requires_on_chain_unlock(&self) -> bool {
+        !matches!(self, CancelReason::UserRequested)
+    }
+
+    pub fn final_status(&self) -> OrderStatus {
+        match self {
+            CancelReason::Expired => OrderStatus::Expired,
+            _ => OrderStatus::Cancelled,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CancelReason::UserRequested => "user_requested",
+            CancelReason::SessionDisconnect => "session_disconnect",
+            CancelReason::Expired => "expired",
+        }
+    }
+}
+
+/// Keyset pagination over rows ordered by `id`. Pages are returned newest
+/// first; pass the last row's `id` as `before` for the next (older) page or
+/// the first row's `id` as `after` for the previous (newer) one.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageParams {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    /// Inclusive lower bound on `created_at`.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `created_at`.
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl PageParams {
+    pub const DEFAULT_LIMIT: i64 = 100;
+    pub const MAX_LIMIT: i64 = 500;
+
+    /// The requested page size, capped at `MAX_LIMIT`.
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err("`from` must be before `to`".to_string());
+            }
+        }
+        if let (Some(before), Some(after)) = (self.before, self.after) {
+            if after >= before {
+                return Err("`after` must be below `before`".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Queries read `after` pages oldest first so the page starts right at
+    /// the cursor; this puts rows back in newest-first order.
+    pub fn newest_first<T>(&self, mut rows: Vec<T>) -> Vec<T> {
+        if self.after.is_some() {
+            rows.reverse();
+        }
+        rows
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,18 +902,65 @@ pub struct WithdrawalRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum WsMessage {
+    /// Client requests may carry a top-level `"id"`, echoed in the reply.
     #[serde(rename = "subscribe")]
-    Subscribe { market_id: Uuid },
+    Subscribe(SubscribeParams),
     #[serde(rename = "unsubscribe")]
-    Unsubscribe { market_id: Uuid },
+    Unsubscribe { channel: Channel, market_id: Option<Uuid> },
+    #[serde(rename = "subscribed")]
+    Subscribed { id: Option<String>, params: SubscribeParams },
+    #[serde(rename = "unsubscribed")]
+    Unsubscribed { id: Option<String>, channel: Channel, market_id: Option<Uuid> },
+    #[serde(rename = "ping")]
+    Ping,
+    #[serde(rename = "pong")]
+    Pong { id: Option<String>, timestamp: DateTime<Utc> },
     #[serde(rename = "orderbook_snapshot")]
     OrderbookSnapshot(OrderbookSnapshot),
     #[serde(rename = "orderbook_update")]
-    OrderbookUpdate { market_id: Uuid, bids: Vec<OrderbookLevel>, asks: Vec<OrderbookLevel> },
+    OrderbookUpdate(OrderbookDelta),
+    #[serde(rename = "book_top")]
+    BookTop(BookTop),
     #[serde(rename = "trade")]
     Trade(Trade),
+    /// Follows a `trade` once its on-chain settlement succeeded or failed.
+    #[serde(rename = "trade_settlement")]
+    TradeSettlement {
+        market_id: Uuid,
+        trade_id: i64,
+        sequence: i64,
+        status: SettlementStatus,
+        signature: Option<String>,
+    },
     #[serde(rename = "order_update")]
     OrderUpdate(Order),
+    #[serde(rename = "fill")]
+    Fill(Fill),
+    #[serde(rename = "balance_update")]
+    BalanceUpdate(BalanceChange),
+    #[serde(rename = "ticker")]
+    Ticker(Ticker),
+    #[serde(rename = "candle")]
+    Candle(Candle),
+    #[serde(rename = "order_triggered")]
+    OrderTriggered { market_id: Uuid, order_id: String, trigger_price: i64, last_price: i64 },
+    #[serde(rename = "auth_challenge")]
+    AuthChallenge { wallet: String },
+    #[serde(rename = "challenge")]
+    Challenge { id: Option<String>, message: String },
+    #[serde(rename = "login")]
+    Login { wallet: String, signature: String },
+    #[serde(rename = "session_options")]
+    SessionOptions { cancel_on_disconnect: bool, heartbeat_timeout_ms: Option<u64> },
+    #[serde(rename = "session")]
+    Session {
+        session_id: Uuid,
+        wallet: String,
+        cancel_on_disconnect: bool,
+        heartbeat_timeout_ms: Option<u64>,
+    },
+    #[serde(rename = "heartbeat")]
+    Heartbeat,
     #[serde(rename = "error")]
-    Error { message: String },
+    Error { id: Option<String>, message: String },
 }
@@ -1,17 +1,138 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use prometheus::{IntCounter, IntGauge};
+use serde::Serialize;
 use tokio::sync::{mpsc, RwLock};
+use tokio::sync::mpsc::error::TrySendError;
 use uuid::Uuid;
 
-use crate::types::{OrderbookSnapshot, Trade, Order, WsMessage};
+use crate::orderbook::Orderbook;
+use crate::types::{
+    BalanceChange, BookTop, Channel, Fill, OrderbookDelta, Trade, Order, WsMessage,
+};
 
-type ClientId = u64;
-type ClientSender = mpsc::UnboundedSender<WsMessage>;
+pub mod session;
+pub mod subscription;
+pub use self::session::{Session, SessionManager};
+pub use self::subscription::{Subscription, Topic};
+use self::subscription::BookView;
+
+pub type ClientId = u64;
+
+/// A message serialized once and shared by every client it is sent to.
+pub type Frame = Arc<str>;
+
+/// Frames a client may have queued before it counts as lagging.
+const CLIENT_QUEUE_CAPACITY: usize = 256;
+/// A client whose queue stays full this long is disconnected.
+const LAG_DISCONNECT_AFTER: Duration = Duration::from_secs(5);
+const THROTTLE_FLUSH_INTERVAL: Duration = Duration::from_millis(10);
+
+struct Client {
+    sender: mpsc::Sender<Frame>,
+    /// Set when the queue first turned out full, cleared on the next delivery.
+    lagging_since: Mutex<Option<Instant>>,
+}
+
+impl Client {
+    fn deliver(&self, frame: Frame, metrics: &WsMetrics) -> bool {
+        match self.sender.try_send(frame) {
+            Ok(()) => {
+                *self.lagging_since.lock().unwrap() = None;
+                metrics.frames_sent.inc();
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.lagging_since.lock().unwrap().get_or_insert_with(Instant::now);
+                false
+            }
+            // The socket is closing; its cleanup removes the client.
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    fn lagging_longer_than(&self, limit: Duration, now: Instant) -> bool {
+        self.lagging_since
+            .lock()
+            .unwrap()
+            .map(|since| now.duration_since(since) > limit)
+            .unwrap_or(false)
+    }
+}
+
+/// Delivery counters across all clients, also exported on `/metrics`.
+pub struct WsMetrics {
+    pub connected_clients: IntGauge,
+    pub frames_sent: IntCounter,
+    /// Updates that could not be queued and were lost.
+    pub frames_dropped: IntCounter,
+    /// Book and ticker updates held back for a lagging client and merged into
+    /// its next update instead of being lost.
+    pub frames_conflated: IntCounter,
+    pub lagging_disconnects: IntCounter,
+}
+
+impl WsMetrics {
+    fn new() -> Self {
+        let counter = |name: &str, help: &str| IntCounter::new(name, help).expect("valid metric");
+        Self {
+            connected_clients: IntGauge::new("ws_connected_clients", "Connected WebSocket clients")
+                .expect("valid metric"),
+            frames_sent: counter("ws_frames_sent_total", "Frames queued to WebSocket clients"),
+            frames_dropped: counter("ws_frames_dropped_total", "Frames lost because a client queue was full"),
+            frames_conflated: counter(
+                "ws_frames_conflated_total",
+                "Book and ticker updates merged into a lagging client's next update",
+            ),
+            lagging_disconnects: counter(
+                "ws_lagging_disconnects_total",
+                "Clients disconnected for lagging too long or missing a private update",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WsStats {
+    pub connected_clients: usize,
+    pub frames_sent: u64,
+    pub frames_dropped: u64,
+    pub frames_conflated: u64,
+    pub lagging_disconnects: u64,
+}
+
+/// A message and its serialized frame, built once per publish.
+struct Outgoing {
+    message: WsMessage,
+    frame: Frame,
+}
+
+impl Outgoing {
+    fn new(message: WsMessage) -> Option<Self> {
+        let frame = serialize(&message)?;
+        Some(Self { message, frame })
+    }
+}
+
+fn serialize(message: &WsMessage) -> Option<Frame> {
+    match serde_json::to_string(message) {
+        Ok(json) => Some(Frame::from(json)),
+        Err(e) => {
+            tracing::error!("Failed to serialize WebSocket message: {}", e);
+            None
+        }
+    }
+}
 
 pub struct WebSocketManager {
-    clients: RwLock<HashMap<ClientId, ClientSender>>,
-    subscriptions: RwLock<HashMap<Uuid, HashSet<ClientId>>>,
-    next_client_id: std::sync::atomic::AtomicU64,
+    clients: RwLock<HashMap<ClientId, Client>>,
+    subscriptions: RwLock<HashMap<Topic, HashMap<ClientId, Subscription>>>,
+    book_views: RwLock<HashMap<(Uuid, usize), BookView>>,
+    metrics: WsMetrics,
+    next_client_id: AtomicU64,
 }
 
 impl WebSocketManager {
@@ -19,77 +140,467 @@ impl WebSocketManager {
         Self {
             clients: RwLock::new(HashMap::new()),
             subscriptions: RwLock::new(HashMap::new()),
-            next_client_id: std::sync::atomic::AtomicU64::new(1),
+            book_views: RwLock::new(HashMap::new()),
+            metrics: WsMetrics::new(),
+            next_client_id: AtomicU64::new(1),
         }
     }
 
-    pub async fn add_client(&self) -> (ClientId, mpsc::UnboundedReceiver<WsMessage>) {
-        let client_id = self.next_client_id.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        let (tx, rx) = mpsc::unbounded_channel();
-        
-        self.clients.write().await.insert(client_id, tx);
-        
+    pub async fn add_client(&self) -> (ClientId, mpsc::Receiver<Frame>) {
+        let client_id = self.next_client_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+
+        let mut clients = self.clients.write().await;
+        clients.insert(client_id, Client {
+            sender,
+            lagging_since: Mutex::new(None),
+        });
+        self.metrics.connected_clients.set(clients.len() as i64);
+        drop(clients);
+
         (client_id, rx)
     }
 
+    /// Forgets the client. Dropping its sender also ends the socket's send
+    /// loop, which is how lagging clients get disconnected.
     pub async fn remove_client(&self, client_id: ClientId) {
-        self.clients.write().await.remove(&client_id);
-        
+        {
+            let mut clients = self.clients.write().await;
+            clients.remove(&client_id);
+            self.metrics.connected_clients.set(clients.len() as i64);
+        }
+
         let mut subscriptions = self.subscriptions.write().await;
         for subscribers in subscriptions.values_mut() {
             subscribers.remove(&client_id);
         }
+        subscriptions.retain(|_, subscribers| !subscribers.is_empty());
     }
-
-    pub async fn subscribe(&self, client_id: ClientId, market_id: Uuid) {
+    /// Adds or replaces the client's subscription to `topic`.
+    pub async fn subscribe(&self, client_id: ClientId, topic: Topic, subscription: Subscription) {
         self.subscriptions
             .write()
             .await
-            .entry(market_id)
-            .or_insert_with(HashSet::new)
-            .insert(client_id);
+            .entry(topic)
+            .or_default()
+            .insert(client_id, subscription);
     }
 
-    pub async fn unsubscribe(&self, client_id: ClientId, market_id: Uuid) {
-        if let Some(subscribers) = self.subscriptions.write().await.get_mut(&market_id) {
-            subscribers.remove(&client_id);
+    /// Returns whether the client was subscribed.
+    pub async fn unsubscribe(&self, client_id: ClientId, topic: &Topic) -> bool {
+        let mut subscriptions = self.subscriptions.write().await;
+        let Some(subscribers) = subscriptions.get_mut(topic) else {
+            return false;
+        };
+        let removed = subscribers.remove(&client_id).is_some();
+        if subscribers.is_empty() {
+            subscriptions.remove(topic);
         }
+        removed
+    }
+
+    /// Drops the client's wallet channel subscriptions, e.g. when it logs in again.
+    pub async fn unsubscribe_wallet_topics(&self, client_id: ClientId) {
+        let mut subscriptions = self.subscriptions.write().await;
+        for (topic, subscribers) in subscriptions.iter_mut() {
+            if matches!(topic, Topic::Wallet(..)) {
+                subscribers.remove(&client_id);
+            }
+        }
+        subscriptions.retain(|_, subscribers| !subscribers.is_empty());
+    }
+
+    /// Makes sure the top-`depth` view of the book exists before a subscriber
+    /// relies on it. Called with the book lock held, like [`Self::publish_book`].
+    pub async fn init_book_view(&self, orderbook: &Orderbook, depth: usize) {
+        self.book_views
+            .write()
+            .await
+            .entry((orderbook.market_id, depth))
+            .or_insert_with(|| BookView {
+                sequence: orderbook.sequence,
+                bids: orderbook.get_bids(depth),
+                asks: orderbook.get_asks(depth),
+            });
     }
 
+    /// Sends a reply or snapshot to one client. A client whose queue is full
+    /// is disconnected rather than left without it.
     pub async fn send_to_client(&self, client_id: ClientId, message: WsMessage) {
-        if let Some(sender) = self.clients.read().await.get(&client_id) {
-            let _ = sender.send(message);
+        let Some(frame) = serialize(&message) else {
+            return;
+        };
+        let delivered = match self.clients.read().await.get(&client_id) {
+            Some(client) => client.deliver(frame, &self.metrics),
+            None => return,
+        };
+        if !delivered {
+            self.metrics.frames_dropped.inc();
+            self.disconnect_overflowed(client_id).await;
         }
     }
 
-    pub async fn broadcast_to_market(&self, market_id: &Uuid, message: WsMessage) {
-        let subscriptions = self.subscriptions.read().await;
+    /// Sends `message` to every subscriber of `topic`, subject to throttling.
+    pub async fn publish(&self, topic: &Topic, message: WsMessage) {
+        let Some(outgoing) = Outgoing::new(message) else {
+            return;
+        };
+        self.publish_with(topic, |_| Some(&outgoing)).await;
+    }
+
+    async fn publish_with<'a>(
+        &self,
+        topic: &Topic,
+        mut outgoing_for: impl FnMut(&Subscription) -> Option<&'a Outgoing>,
+    ) {
+        let now = Instant::now();
+        let conflatable = match topic {
+            Topic::Market(_, channel) | Topic::Wallet(_, channel) => channel.supports_throttle(),
+        };
+        let mut overflowed = Vec::new();
+
+        let mut subscriptions = self.subscriptions.write().await;
+        let Some(subscribers) = subscriptions.get_mut(topic) else {
+            return;
+        };
         let clients = self.clients.read().await;
-        
-        if let Some(subscribers) = subscriptions.get(market_id) {
-            for client_id in subscribers {
-                if let Some(sender) = clients.get(client_id) {
-                    let _ = sender.send(message.clone());
+
+        for (client_id, subscription) in subscribers.iter_mut() {
+            let (Some(outgoing), Some(client)) = (outgoing_for(subscription), clients.get(client_id)) else {
+                continue;
+            };
+
+            // The shared frame only fits when nothing is held back for this
+            // subscriber; otherwise the update is merged and serialized alone.
+            let (message, frame) = if subscription.is_idle() {
+                (None, outgoing.frame.clone())
+            } else {
+                let Some(message) = subscription.offer(outgoing.message.clone(), now) else {
+                    continue;
+                };
+                let Some(frame) = serialize(&message) else {
+                    continue;
+                };
+                (Some(message), frame)
+            };
+
+            if !client.deliver(frame, &self.metrics)
+                && !self.hold_back(subscription, conflatable, message.unwrap_or_else(|| outgoing.message.clone()))
+                && matches!(topic, Topic::Wallet(..))
+            {
+                overflowed.push(*client_id);
+            }
+        }
+        drop(clients);
+        drop(subscriptions);
+
+        for client_id in overflowed {
+            self.disconnect_overflowed(client_id).await;
+        }
+    }
+
+    /// Keeps an update that did not fit for later, merged with the next one,
+    /// if its channel allows. Returns whether it was kept.
+    fn hold_back(&self, subscription: &mut Subscription, conflatable: bool, message: WsMessage) -> bool {
+        if conflatable {
+            subscription.defer(message);
+            self.metrics.frames_conflated.inc();
+            true
+        } else {
+            self.metrics.frames_dropped.inc();
+            false
+        }
+    }
+
+    /// A client that missed one of its own fills, order updates or balances
+    /// holds stale state it cannot detect. Disconnecting it makes it
+    /// reconnect and reload instead.
+    async fn disconnect_overflowed(&self, client_id: ClientId) {
+        tracing::warn!("Disconnecting WebSocket client {}: a private update did not fit its queue", client_id);
+        self.metrics.lagging_disconnects.inc();
+        self.remove_client(client_id).await;
+    }
+
+    /// Publishes a book change to `book.L2` and `book.L1` subscribers.
+    ///
+    /// Full-depth subscribers get `delta` itself; depth-limited ones a delta
+    /// of their top-`depth` view. Callers hold the book's write lock across
+    /// `take_delta` and this call so updates go out in sequence order.
+    pub async fn publish_book(&self, orderbook: &Orderbook, delta: OrderbookDelta) {
+        let market_id = delta.market_id;
+        let l2 = Topic::Market(market_id, Channel::BookL2);
+        let l1 = Topic::Market(market_id, Channel::BookL1);
+
+        let depths: BTreeSet<usize> = {
+            let subscriptions = self.subscriptions.read().await;
+            let mut depths: BTreeSet<usize> = subscriptions
+                .get(&l2)
+                .into_iter()
+                .flat_map(|subscribers| subscribers.values().filter_map(|s| s.depth))
+                .collect();
+            if subscriptions.contains_key(&l1) {
+                depths.insert(1);
+            }
+            depths
+        };
+
+        let mut view_updates = HashMap::new();
+        let mut top = None;
+        {
+            let mut views = self.book_views.write().await;
+            views.retain(|(market, depth), _| *market != market_id || depths.contains(depth));
+            for &depth in &depths {
+                let Some(view) = views.get_mut(&(market_id, depth)) else {
+                    continue;
+                };
+                let bids = orderbook.get_bids(depth);
+                let asks = orderbook.get_asks(depth);
+                if let Some(view_delta) = view.update(&delta, bids, asks) {
+                    if depth == 1 {
+                        top = Outgoing::new(WsMessage::BookTop(book_top(market_id, view)));
+                    }
+                    if let Some(outgoing) = Outgoing::new(WsMessage::OrderbookUpdate(view_delta)) {
+                        view_updates.insert(depth, outgoing);
+                    }
+                }
+            }
+        }
+
+        let full = Outgoing::new(WsMessage::OrderbookUpdate(delta));
+        self.publish_with(&l2, |subscription| match subscription.depth {
+            None => full.as_ref(),
+            Some(depth) => view_updates.get(&depth),
+        })
+        .await;
+        if let Some(top) = &top {
+            self.publish_with(&l1, |_| Some(top)).await;
+        }
+    }
+
+    /// Sends held-back updates that are due, then disconnects clients that
+    /// have been lagging for too long.
+    pub async fn flush_pending(&self) {
+        let now = Instant::now();
+        let mut lagging = Vec::new();
+        {
+            let mut subscriptions = self.subscriptions.write().await;
+            let clients = self.clients.read().await;
+
+            for subscribers in subscriptions.values_mut() {
+                for (client_id, subscription) in subscribers.iter_mut() {
+                    let Some(client) = clients.get(client_id) else {
+                        continue;
+                    };
+                    let Some(message) = subscription.take_due(now) else {
+                        continue;
+                    };
+                    let Some(frame) = serialize(&message) else {
+                        continue;
+                    };
+                    if !client.deliver(frame, &self.metrics) {
+                        // Only conflatable updates are ever held back.
+                        subscription.defer(message);
+                    }
                 }
             }
+
+            for (client_id, client) in clients.iter() {
+                if client.lagging_longer_than(LAG_DISCONNECT_AFTER, now) {
+                    lagging.push(*client_id);
+                }
+            }
+        }
+
+        for client_id in lagging {
+            tracing::warn!("Disconnecting lagging WebSocket client {}", client_id);
+            self.metrics.lagging_disconnects.inc();
+            self.remove_client(client_id).await;
+        }
+    }
+
+    pub async fn stats(&self) -> WsStats {
+        WsStats {
+            connected_clients: self.clients.read().await.len(),
+            frames_sent: self.metrics.frames_sent.get(),
+            frames_dropped: self.metrics.frames_dropped.get(),
+            frames_conflated: self.metrics.frames_conflated.get(),
+            lagging_disconnects: self.metrics.lagging_disconnects.get(),
         }
     }
 
-    pub async fn broadcast_orderbook_snapshot(&self, snapshot: OrderbookSnapshot) {
-        let market_id = snapshot.market_id;
-        let message = WsMessage::OrderbookSnapshot(snapshot);
-        self.broadcast_to_market(&market_id, message).await;
+    pub fn metrics(&self) -> &WsMetrics {
+        &self.metrics
     }
 
+    /// Publishes a trade as it is matched. Callers hold the book's write lock
+    /// so trades go out in `sequence` order.
     pub async fn broadcast_trade(&self, trade: Trade) {
-        let market_id = trade.market_id;
-        let message = WsMessage::Trade(trade);
-        self.broadcast_to_market(&market_id, message).await;
+        let topic = Topic::Market(trade.market_id, Channel::Trades);
+        self.publish(&topic, WsMessage::Trade(trade)).await;
+    }
+
+    pub async fn send_order_update(&self, order: Order) {
+        let topic = Topic::Wallet(order.user_wallet.clone(), Channel::Orders);
+        self.publish(&topic, WsMessage::OrderUpdate(order)).await;
+    }
+
+    pub async fn send_order_triggered(&self, order: &Order, trigger_price: i64, last_price: i64) {
+        let message = WsMessage::OrderTriggered {
+            market_id: order.market_id,
+            order_id: order.order_id.clone(),
+            trigger_price,
+            last_price,
+        };
+        let topic = Topic::Wallet(order.user_wallet.clone(), Channel::Orders);
+        self.publish(&topic, message).await;
+    }
+
+    pub async fn send_fill(&self, fill: Fill) {
+        let topic = Topic::Wallet(fill.wallet.clone(), Channel::Fills);
+        self.publish(&topic, WsMessage::Fill(fill)).await;
+    }
+
+    pub async fn send_balance_change(&self, wallet: &str, change: BalanceChange) {
+        let topic = Topic::Wallet(wallet.to_string(), Channel::Balances);
+        self.publish(&topic, WsMessage::BalanceUpdate(change)).await;
+    }
+}
+
+pub fn book_top(market_id: Uuid, view: &BookView) -> BookTop {
+    BookTop {
+        market_id,
+        sequence: view.sequence,
+        bid: view.bids.first().cloned(),
+        ask: view.asks.first().cloned(),
+        timestamp: Utc::now(),
+    }
+}
+
+/// Background task that releases throttled and held-back updates and drops
+/// clients that stopped reading.
+pub async fn run_pending_flusher(ws_manager: Arc<WebSocketManager>) {
+    let mut interval = tokio::time::interval(THROTTLE_FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        ws_manager.flush_pending().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::types::{OrderSide, OrderStatus, OrderType};
+
+    fn order_for(wallet: &str) -> Order {
+        Order {
+            id: 1,
+            order_id: "1".to_string(),
+            user_wallet: wallet.to_string(),
+            market_id: Uuid::new_v4(),
+            side: OrderSide::Buy,
+            price: 100,
+            size: 10,
+            filled: 0,
+            status: OrderStatus::Pending,
+            order_type: OrderType::Limit,
+            trigger_price: None,
+            triggered_at: None,
+            display_size: None,
+            on_chain_signature: None,
+            expires_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_order_updates_reach_only_owner() {
+        let manager = WebSocketManager::new();
+        let (alice, mut alice_rx) = manager.add_client().await;
+        let (bob, mut bob_rx) = manager.add_client().await;
+        let order = order_for("alice-wallet");
+
+        // Market subscribers no longer see anyone's orders.
+        let book = Topic::Market(order.market_id, Channel::BookL2);
+        manager.subscribe(bob, book, Subscription::default()).await;
+        let alice_orders = Topic::Wallet("alice-wallet".to_string(), Channel::Orders);
+        let bob_orders = Topic::Wallet("bob-wallet".to_string(), Channel::Orders);
+        manager.subscribe(alice, alice_orders, Subscription::default()).await;
+        manager.subscribe(bob, bob_orders, Subscription::default()).await;
+
+        manager.send_order_update(order).await;
+
+        assert!(alice_rx.try_recv().unwrap().contains("\"order_update\""));
+        assert!(bob_rx.try_recv().is_err());
+
+        manager.unsubscribe_wallet_topics(alice).await;
+        manager.send_order_update(order_for("alice-wallet")).await;
+        assert!(alice_rx.try_recv().is_err());
     }
 
-    pub async fn broadcast_order_update(&self, order: Order) {
-        let market_id = order.market_id;
-        let message = WsMessage::OrderUpdate(order);
-        self.broadcast_to_market(&market_id, message).await;
+    #[tokio::test]
+    async fn test_full_queue_conflates_book_updates_and_drops_trades() {
+        let manager = WebSocketManager::new();
+        let (client_id, mut rx) = manager.add_client().await;
+        let market_id = Uuid::new_v4();
+        let book = Topic::Market(market_id, Channel::BookL2);
+        let trades = Topic::Market(market_id, Channel::Trades);
+        manager.subscribe(client_id, book.clone(), Subscription::default()).await;
+        manager.subscribe(client_id, trades.clone(), Subscription::default()).await;
+
+        let delta = |sequence: u64| OrderbookDelta {
+            market_id,
+            sequence,
+            prev_sequence: sequence - 1,
+            bids: Vec::new(),
+            asks: Vec::new(),
+            last_price: None,
+            timestamp: Utc::now(),
+        };
+
+        for sequence in 1..=CLIENT_QUEUE_CAPACITY as u64 {
+            manager.publish(&book, WsMessage::OrderbookUpdate(delta(sequence))).await;
+        }
+        // Queue full: the next two book updates are merged, the ping is lost.
+        let next = CLIENT_QUEUE_CAPACITY as u64 + 1;
+        manager.publish(&book, WsMessage::OrderbookUpdate(delta(next))).await;
+        manager.publish(&book, WsMessage::OrderbookUpdate(delta(next + 1))).await;
+        manager.publish(&trades, WsMessage::Ping).await;
+
+        let stats = manager.stats().await;
+        assert_eq!(stats.frames_conflated, 2);
+        assert_eq!(stats.frames_dropped, 1);
+
+        while rx.try_recv().is_ok() {}
+        manager.flush_pending().await;
+        let merged = rx.try_recv().unwrap();
+        assert!(merged.contains(&format!("\"prev_sequence\":{}", next - 1)));
+        assert!(merged.contains(&format!("\"sequence\":{}", next + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_disconnects_on_private_update() {
+        let manager = WebSocketManager::new();
+        let (client_id, mut rx) = manager.add_client().await;
+        let orders = Topic::Wallet("alice-wallet".to_string(), Channel::Orders);
+        manager.subscribe(client_id, orders, Subscription::default()).await;
+
+        for _ in 0..CLIENT_QUEUE_CAPACITY {
+            manager.send_order_update(order_for("alice-wallet")).await;
+        }
+        assert_eq!(manager.stats().await.connected_clients, 1);
+
+        // An order update that does not fit is not dropped silently.
+        manager.send_order_update(order_for("alice-wallet")).await;
+        let stats = manager.stats().await;
+        assert_eq!(stats.connected_clients, 0);
+        assert_eq!(stats.lagging_disconnects, 1);
+
+        // The queued frames still drain, then the socket sees the end.
+        for _ in 0..CLIENT_QUEUE_CAPACITY {
+            assert!(rx.try_recv().is_ok());
+        }
+        assert_eq!(rx.try_recv(), Err(mpsc::error::TryRecvError::Disconnected));
     }
 }
@@ -0,0 +1,402 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use super::ClientId;
+use crate::auth::verify_wallet_signature;
+
+pub const MIN_HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(1_000);
+pub const MAX_HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(60_000);
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    #[error("No login challenge issued for this wallet")]
+    NoChallenge,
+
+    #[error("Invalid wallet signature")]
+    InvalidSignature,
+
+    #[error("Not authenticated")]
+    NotAuthenticated,
+
+    #[error("Session not found")]
+    SessionNotFound,
+
+    #[error("Session belongs to a different wallet")]
+    WalletMismatch,
+
+    #[error("Already logged in as another wallet on this connection")]
+    AlreadyLoggedIn,
+
+    #[error("Heartbeat timeout must be between {}ms and {}ms", MIN_HEARTBEAT_TIMEOUT.as_millis(), MAX_HEARTBEAT_TIMEOUT.as_millis())]
+    InvalidHeartbeatTimeout,
+}
+
+/// An authenticated WebSocket session. Orders placed with its `session_id` are
+/// tracked so they can be cancelled when the client disconnects or stops
+/// sending heartbeats.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub session_id: Uuid,
+    pub wallet: String,
+    pub cancel_on_disconnect: bool,
+    pub heartbeat_timeout: Option<Duration>,
+    pub last_heartbeat: Instant,
+    pub order_ids: HashSet<String>,
+}
+
+impl Session {
+    /// Whether resting orders must be pulled once this session ends.
+    pub fn is_armed(&self) -> bool {
+        self.cancel_on_disconnect || self.heartbeat_timeout.is_some()
+    }
+
+    pub fn heartbeat_expired(&self, now: Instant) -> bool {
+        self.heartbeat_timeout
+            .map(|timeout| now.duration_since(self.last_heartbeat) > timeout)
+            .unwrap_or(false)
+    }
+}
+
+pub fn login_message(wallet: &str, nonce: &Uuid) -> String {
+    format!("DCEX WebSocket login\nwallet: {}\nnonce: {}", wallet, nonce)
+}
+
+pub struct SessionManager {
+    challenges: RwLock<HashMap<ClientId, (String, String)>>,
+    sessions: RwLock<HashMap<Uuid, Session>>,
+    client_sessions: RwLock<HashMap<ClientId, Uuid>>,
+    /// Which session each tracked order belongs to, so finished orders can
+    /// be dropped without scanning every session.
+    order_sessions: RwLock<HashMap<String, Uuid>>,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self {
+            challenges: RwLock::new(HashMap::new()),
+            sessions: RwLock::new(HashMap::new()),
+            client_sessions: RwLock::new(HashMap::new()),
+            order_sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Issues a fresh challenge for `wallet` on this connection, replacing any previous one.
+    pub async fn issue_challenge(&self, client_id: ClientId, wallet: &str) -> String {
+        let message = login_message(wallet, &Uuid::new_v4());
+        self.challenges
+            .write()
+            .await
+            .insert(client_id, (wallet.to_string(), message.clone()));
+        message
+    }
+
+    pub async fn login(
+        &self,
+        client_id: ClientId,
+        wallet: &str,
+        signature: &str,
+    ) -> Result<Session, SessionError> {
+        let (challenge_wallet, message) = self
+            .challenges
+            .write()
+            .await
+            .remove(&client_id)
+            .ok_or(SessionError::NoChallenge)?;
+
+        if challenge_wallet != wallet {
+            return Err(SessionError::NoChallenge);
+        }
+        if !verify_wallet_signature(wallet, &message, signature) {
+            return Err(SessionError::InvalidSignature);
+        }
+
+        let mut client_sessions = self.client_sessions.write().await;
+        let mut sessions = self.sessions.write().await;
+        // Logging in again as the same wallet keeps the session as it is, so
+        // its id, orders and cancel-on-disconnect settings stay in force.
+        if let Some(previous) = client_sessions.get(&client_id).and_then(|id| sessions.get(id)) {
+            if previous.wallet != wallet {
+                return Err(SessionError::AlreadyLoggedIn);
+            }
+            return Ok(previous.clone());
+        }
+
+        let session = Session {
+            session_id: Uuid::new_v4(),
+            wallet: wallet.to_string(),
+            cancel_on_disconnect: false,
+            heartbeat_timeout: None,
+            last_heartbeat: Instant::now(),
+            order_ids: HashSet::new(),
+        };
+        client_sessions.insert(client_id, session.session_id);
+        sessions.insert(session.session_id, session.clone());
+
+        Ok(session)
+    }
+
+    pub async fn configure(
+        &self,
+        client_id: ClientId,
+        cancel_on_disconnect: bool,
+        heartbeat_timeout: Option<Duration>,
+    ) -> Result<Session, SessionError> {
+        if let Some(timeout) = heartbeat_timeout {
+            if !(MIN_HEARTBEAT_TIMEOUT..=MAX_HEARTBEAT_TIMEOUT).contains(&timeout) {
+                return Err(SessionError::InvalidHeartbeatTimeout);
+            }
+        }
+
+        let session_id = self.session_id_for_client(client_id).await?;
+        let mut sessions = self.sessions.write().await;
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or(SessionError::NotAuthenticated)?;
+
+        session.cancel_on_disconnect = cancel_on_disconnect;
+        session.heartbeat_timeout = heartbeat_timeout;
+        session.last_heartbeat = Instant::now();
+
+        Ok(session.clone())
+    }
+
+    pub async fn heartbeat(&self, client_id: ClientId) -> Result<(), SessionError> {
+        let session_id = self.session_id_for_client(client_id).await?;
+        if let Some(session) = self.sessions.write().await.get_mut(&session_id) {
+            session.last_heartbeat = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub async fn heartbeat_expired(&self, client_id: ClientId) -> bool {
+        let Some(session_id) = self.client_sessions.read().await.get(&client_id).copied() else {
+            return false;
+        };
+        self.sessions
+            .read()
+            .await
+            .get(&session_id)
+            .map(|session| session.heartbeat_expired(Instant::now()))
+            .unwrap_or(false)
+    }
+
+    /// Attaches an order to a live session after checking it belongs to `wallet`.
+    pub async fn track_order(
+        &self,
+        session_id: Uuid,
+        wallet: &str,
+        order_id: &str,
+    ) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or(SessionError::SessionNotFound)?;
+
+        if session.wallet != wallet {
+            return Err(SessionError::WalletMismatch);
+        }
+
+        session.order_ids.insert(order_id.to_string());
+        self.order_sessions
+            .write()
+            .await
+            .insert(order_id.to_string(), session_id);
+        Ok(())
+    }
+
+    /// Forgets an order that filled, was cancelled or expired.
+    pub async fn untrack_order(&self, order_id: &str) {
+        let mut sessions = self.sessions.write().await;
+        let Some(session_id) = self.order_sessions.write().await.remove(order_id) else {
+            return;
+        };
+        if let Some(session) = sessions.get_mut(&session_id) {
+            session.order_ids.remove(order_id);
+        }
+    }
+
+    pub async fn validate(&self, session_id: Uuid, wallet: &str) -> Result<(), SessionError> {
+        match self.sessions.read().await.get(&session_id) {
+            Some(session) if session.wallet == wallet => Ok(()),
+            Some(_) => Err(SessionError::WalletMismatch),
+            None => Err(SessionError::SessionNotFound),
+        }
+    }
+
+    /// Wallet the connection is logged in as.
+    pub async fn wallet_for_client(&self, client_id: ClientId) -> Result<String, SessionError> {
+        let session_id = self.session_id_for_client(client_id).await?;
+        self.sessions
+            .read()
+            .await
+            .get(&session_id)
+            .map(|session| session.wallet.clone())
+            .ok_or(SessionError::NotAuthenticated)
+    }
+
+    /// Drops all state for a connection and returns its session, if it had one.
+    pub async fn end_session(&self, client_id: ClientId) -> Option<Session> {
+        self.challenges.write().await.remove(&client_id);
+        let session_id = self.client_sessions.write().await.remove(&client_id)?;
+        let session = self.sessions.write().await.remove(&session_id)?;
+        let mut order_sessions = self.order_sessions.write().await;
+        for order_id in &session.order_ids {
+            order_sessions.remove(order_id);
+        }
+        Some(session)
+    }
+
+    async fn session_id_for_client(&self, client_id: ClientId) -> Result<Uuid, SessionError> {
+        self.client_sessions
+            .read()
+            .await
+            .get(&client_id)
+            .copied()
+            .ok_or(SessionError::NotAuthenticated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    async fn logged_in(manager: &SessionManager, client_id: ClientId, keypair: &Keypair) -> Session {
+        let wallet = keypair.pubkey().to_string();
+        let challenge = manager.issue_challenge(client_id, &wallet).await;
+        let signature = keypair.sign_message(challenge.as_bytes()).to_string();
+        manager.login(client_id, &wallet, &signature).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_login_requires_signed_challenge() {
+        let manager = SessionManager::new();
+        let keypair = Keypair::new();
+        let wallet = keypair.pubkey().to_string();
+
+        assert_eq!(
+            manager.login(1, &wallet, "sig").await.unwrap_err(),
+            SessionError::NoChallenge
+        );
+
+        let challenge = manager.issue_challenge(1, &wallet).await;
+        let forged = Keypair::new().sign_message(challenge.as_bytes()).to_string();
+        assert_eq!(
+            manager.login(1, &wallet, &forged).await.unwrap_err(),
+            SessionError::InvalidSignature
+        );
+
+        let session = logged_in(&manager, 1, &keypair).await;
+        assert_eq!(session.wallet, wallet);
+        assert!(!session.is_armed());
+    }
+
+    #[tokio::test]
+    async fn test_orders_tracked_only_for_session_wallet() {
+        let manager = SessionManager::new();
+        let keypair = Keypair::new();
+        let session = logged_in(&manager, 1, &keypair).await;
+
+        assert_eq!(
+            manager.track_order(session.session_id, "someone-else", "1").await,
+            Err(SessionError::WalletMismatch)
+        );
+        manager
+            .track_order(session.session_id, &session.wallet, "1")
+            .await
+            .unwrap();
+
+        let ended = manager.end_session(1).await.unwrap();
+        assert!(ended.order_ids.contains("1"));
+        assert_eq!(
+            manager.validate(session.session_id, &session.wallet).await,
+            Err(SessionError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relogin_keeps_session() {
+        let manager = SessionManager::new();
+        let keypair = Keypair::new();
+        let session = logged_in(&manager, 1, &keypair).await;
+        manager
+            .configure(1, true, Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        manager
+            .track_order(session.session_id, &session.wallet, "1")
+            .await
+            .unwrap();
+
+        let again = logged_in(&manager, 1, &keypair).await;
+        assert_eq!(again.session_id, session.session_id);
+        assert!(again.cancel_on_disconnect);
+        assert_eq!(again.heartbeat_timeout, Some(Duration::from_secs(5)));
+        assert!(again.order_ids.contains("1"));
+        assert!(manager.validate(session.session_id, &session.wallet).await.is_ok());
+
+        let other = Keypair::new();
+        let other_wallet = other.pubkey().to_string();
+        let challenge = manager.issue_challenge(1, &other_wallet).await;
+        let signature = other.sign_message(challenge.as_bytes()).to_string();
+        assert_eq!(
+            manager.login(1, &other_wallet, &signature).await.unwrap_err(),
+            SessionError::AlreadyLoggedIn
+        );
+        assert_eq!(manager.wallet_for_client(1).await, Ok(session.wallet));
+    }
+
+    #[tokio::test]
+    async fn test_finished_orders_are_untracked() {
+        let manager = SessionManager::new();
+        let keypair = Keypair::new();
+        let session = logged_in(&manager, 1, &keypair).await;
+        for order_id in ["1", "2"] {
+            manager
+                .track_order(session.session_id, &session.wallet, order_id)
+                .await
+                .unwrap();
+        }
+
+        manager.untrack_order("1").await;
+        manager.untrack_order("unknown").await;
+
+        let ended = manager.end_session(1).await.unwrap();
+        assert_eq!(ended.order_ids, HashSet::from(["2".to_string()]));
+        assert!(manager.order_sessions.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_for_client_requires_login() {
+        let manager = SessionManager::new();
+        assert_eq!(
+            manager.wallet_for_client(1).await,
+            Err(SessionError::NotAuthenticated)
+        );
+
+        let keypair = Keypair::new();
+        let session = logged_in(&manager, 1, &keypair).await;
+        assert_eq!(manager.wallet_for_client(1).await, Ok(session.wallet));
+        assert!(manager.wallet_for_client(2).await.is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_expiry() {
+        let start = Instant::now();
+        let session = Session {
+            session_id: Uuid::new_v4(),
+            wallet: "wallet".to_string(),
+            cancel_on_disconnect: false,
+            heartbeat_timeout: Some(Duration::from_secs(5)),
+            last_heartbeat: start,
+            order_ids: HashSet::new(),
+        };
+
+        assert!(session.is_armed());
+        assert!(!session.heartbeat_expired(start + Duration::from_secs(5)));
+        assert!(session.heartbeat_expired(start + Duration::from_secs(6)));
+    }
+}
@@ -0,0 +1,285 @@
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::types::{Channel, OrderbookDelta, OrderbookLevel, WsMessage};
+
+pub const MAX_BOOK_DEPTH: usize = 1_000;
+pub const MIN_THROTTLE: Duration = Duration::from_millis(10);
+pub const MAX_THROTTLE: Duration = Duration::from_millis(5_000);
+
+/// What a subscription is keyed by: a market stream or a wallet's private one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Market(Uuid, Channel),
+    Wallet(String, Channel),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    pub depth: Option<usize>,
+    pub throttle: Option<Duration>,
+    last_sent: Option<Instant>,
+    pending: Option<WsMessage>,
+}
+
+impl Subscription {
+    pub fn new(depth: Option<usize>, throttle: Option<Duration>) -> Self {
+        Self {
+            depth,
+            throttle,
+            ..Self::default()
+        }
+    }
+
+    /// Nothing held back: updates can go straight to the client.
+    pub fn is_idle(&self) -> bool {
+        self.throttle.is_none() && self.pending.is_none()
+    }
+
+    /// Returns the message if it may go out now; otherwise keeps it, coalesced
+    /// with anything already pending, for [`Subscription::take_due`].
+    pub fn offer(&mut self, message: WsMessage, now: Instant) -> Option<WsMessage> {
+        let message = match self.pending.take() {
+            Some(pending) => coalesce(pending, message),
+            None => message,
+        };
+        let Some(throttle) = self.throttle else {
+            return Some(message);
+        };
+
+        match self.last_sent {
+            Some(last_sent) if now.duration_since(last_sent) < throttle => {
+                self.pending = Some(message);
+                None
+            }
+            _ => {
+                self.last_sent = Some(now);
+                Some(message)
+            }
+        }
+    }
+
+    /// Holds back an update the client's queue had no room for, coalesced
+    /// with anything already pending.
+    pub fn defer(&mut self, message: WsMessage) {
+        self.pending = Some(match self.pending.take() {
+            Some(pending) => coalesce(pending, message),
+            None => message,
+        });
+    }
+
+    /// The pending message, once the throttle interval (if any) has passed.
+    pub fn take_due(&mut self, now: Instant) -> Option<WsMessage> {
+        self.pending.as_ref()?;
+        if let (Some(throttle), Some(last_sent)) = (self.throttle, self.last_sent) {
+            if now.duration_since(last_sent) < throttle {
+                return None;
+            }
+        }
+        self.last_sent = Some(now);
+        self.pending.take()
+    }
+}
+
+/// Folds a newer update into an older one that was never sent. Book deltas
+/// are merged level by level; anything else is a full state and replaces.
+fn coalesce(older: WsMessage, newer: WsMessage) -> WsMessage {
+    match (older, newer) {
+        (WsMessage::OrderbookUpdate(older), WsMessage::OrderbookUpdate(newer)) => {
+            WsMessage::OrderbookUpdate(merge_deltas(older, newer))
+        }
+        (_, newer) => newer,
+    }
+}
+
+pub fn merge_deltas(older: OrderbookDelta, newer: OrderbookDelta) -> OrderbookDelta {
+    OrderbookDelta {
+        prev_sequence: older.prev_sequence,
+        bids: merge_levels(older.bids, newer.bids, |a, b| b.cmp(&a)),
+        asks: merge_levels(older.asks, newer.asks, |a, b| a.cmp(&b)),
+        ..newer
+    }
+}
+
+fn merge_levels(
+    older: Vec<OrderbookLevel>,
+    newer: Vec<OrderbookLevel>,
+    order: impl Fn(i64, i64) -> std::cmp::Ordering,
+) -> Vec<OrderbookLevel> {
+    let mut levels: Vec<OrderbookLevel> = older
+        .into_iter()
+        .filter(|level| !newer.iter().any(|n| n.price == level.price))
+        .collect();
+    levels.extend(newer);
+    levels.sort_by(|a, b| order(a.price, b.price));
+    levels
+}
+
+/// The top `depth` levels last published to depth-limited `book.L2` and to
+/// `book.L1` subscribers of one market.
+#[derive(Debug, Clone)]
+pub struct BookView {
+    pub sequence: u64,
+    pub bids: Vec<OrderbookLevel>,
+    pub asks: Vec<OrderbookLevel>,
+}
+
+impl BookView {
+    /// Replaces the view with the book's current top levels and returns the
+    /// difference as a delta, or `None` if the view did not change.
+    ///
+    /// The delta's `prev_sequence` is the sequence of the last change to this
+    /// view, so it can be lower than `sequence - 1`: book changes outside
+    /// the top levels never reach depth-limited subscribers.
+    pub fn update(
+        &mut self,
+        book: &OrderbookDelta,
+        bids: Vec<OrderbookLevel>,
+        asks: Vec<OrderbookLevel>,
+    ) -> Option<OrderbookDelta> {
+        let bid_changes = diff_levels(&self.bids, &bids, |a, b| b.cmp(&a));
+        let ask_changes = diff_levels(&self.asks, &asks, |a, b| a.cmp(&b));
+        if bid_changes.is_empty() && ask_changes.is_empty() {
+            return None;
+        }
+
+        let delta = OrderbookDelta {
+            market_id: book.market_id,
+            sequence: book.sequence,
+            prev_sequence: self.sequence,
+            bids: bid_changes,
+            asks: ask_changes,
+            last_price: book.last_price,
+            timestamp: book.timestamp,
+        };
+        *self = BookView {
+            sequence: book.sequence,
+            bids,
+            asks,
+        };
+        Some(delta)
+    }
+}
+
+/// Levels that differ between `before` and `after`; levels that left are
+/// reported with zero size.
+fn diff_levels(
+    before: &[OrderbookLevel],
+    after: &[OrderbookLevel],
+    order: impl Fn(i64, i64) -> std::cmp::Ordering,
+) -> Vec<OrderbookLevel> {
+    let mut changes: Vec<OrderbookLevel> = before
+        .iter()
+        .filter(|old| !after.iter().any(|new| new.price == old.price))
+        .map(|old| OrderbookLevel {
+            price: old.price,
+            size: 0,
+            order_count: 0,
+        })
+        .chain(after.iter().filter(|new| !before.contains(new)).cloned())
+        .collect();
+    changes.sort_by(|a, b| order(a.price, b.price));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn level(price: i64, size: i64) -> OrderbookLevel {
+        OrderbookLevel {
+            price,
+            size,
+            order_count: if size > 0 { 1 } else { 0 },
+        }
+    }
+
+    fn delta(prev_sequence: u64, sequence: u64, asks: Vec<OrderbookLevel>) -> OrderbookDelta {
+        OrderbookDelta {
+            market_id: Uuid::nil(),
+            sequence,
+            prev_sequence,
+            bids: Vec::new(),
+            asks,
+            last_price: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_throttled_deltas_coalesce() {
+        let start = Instant::now();
+        let mut subscription = Subscription::new(None, Some(Duration::from_millis(100)));
+
+        let first = subscription.offer(
+            WsMessage::OrderbookUpdate(delta(0, 1, vec![level(100, 5)])),
+            start,
+        );
+        assert!(first.is_some());
+
+        let held = subscription.offer(
+            WsMessage::OrderbookUpdate(delta(1, 2, vec![level(100, 3), level(101, 4)])),
+            start + Duration::from_millis(10),
+        );
+        assert!(held.is_none());
+        subscription.offer(
+            WsMessage::OrderbookUpdate(delta(2, 3, vec![level(101, 0)])),
+            start + Duration::from_millis(20),
+        );
+        assert!(subscription.take_due(start + Duration::from_millis(50)).is_none());
+
+        let Some(WsMessage::OrderbookUpdate(merged)) =
+            subscription.take_due(start + Duration::from_millis(100))
+        else {
+            panic!("expected a coalesced delta");
+        };
+        assert_eq!((merged.prev_sequence, merged.sequence), (1, 3));
+        assert_eq!(merged.asks, vec![level(100, 3), level(101, 0)]);
+        assert!(subscription.take_due(start + Duration::from_millis(500)).is_none());
+    }
+
+    #[test]
+    fn test_deferred_delta_keeps_order_for_unthrottled_subscriber() {
+        let now = Instant::now();
+        let mut subscription = Subscription::default();
+        assert!(subscription.is_idle());
+
+        subscription.defer(WsMessage::OrderbookUpdate(delta(0, 1, vec![level(100, 5)])));
+        assert!(!subscription.is_idle());
+
+        // The next update folds the deferred one in rather than overtaking it.
+        let Some(WsMessage::OrderbookUpdate(merged)) = subscription.offer(
+            WsMessage::OrderbookUpdate(delta(1, 2, vec![level(101, 1)])),
+            now,
+        ) else {
+            panic!("expected a merged delta");
+        };
+        assert_eq!((merged.prev_sequence, merged.sequence), (0, 2));
+        assert_eq!(merged.asks.len(), 2);
+        assert!(subscription.is_idle());
+    }
+
+    #[test]
+    fn test_book_view_reports_only_top_level_changes() {
+        let mut view = BookView {
+            sequence: 4,
+            bids: Vec::new(),
+            asks: vec![level(100, 5), level(101, 5)],
+        };
+
+        // A change deeper than the view leaves it untouched.
+        let book = delta(4, 5, vec![level(105, 1)]);
+        assert!(view
+            .update(&book, Vec::new(), vec![level(100, 5), level(101, 5)])
+            .is_none());
+
+        // Level 100 is taken out; 102 moves into the top two.
+        let book = delta(5, 6, vec![level(100, 0)]);
+        let change = view
+            .update(&book, Vec::new(), vec![level(101, 5), level(102, 7)])
+            .unwrap();
+        assert_eq!((change.prev_sequence, change.sequence), (4, 6));
+        assert_eq!(change.asks, vec![level(100, 0), level(102, 7)]);
+    }
+}